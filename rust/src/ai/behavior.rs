@@ -1,5 +1,26 @@
+use std::cell::Cell;
+use std::collections::VecDeque;
+use std::fmt::{Display, Formatter};
 use godot::prelude::*;
+use serde::{Deserialize, Serialize};
+use crate::ai::command::{Attack, Command, Move};
+use crate::ai::perception::Memory;
+use crate::ai::skill::Targeting;
 use crate::dnd::ability::Ability;
+use crate::dnd::condition::{Condition, ConditionType};
+use crate::dnd::reaction::Attitude;
+
+/// How much better another strategy has to score before it takes over from the one in charge.
+pub const DEFAULT_HYSTERESIS: f32 = 0.1;
+/// Least [`Retreat`] score worth running for. Retreat has the upper hand over everything else, so
+/// less than that counts as nothing.
+pub const RETREAT_THRESHOLD: f32 = 0.25;
+/// Seconds spent searching around where somebody was last seen.
+pub const DEFAULT_SEARCH_TIME: f64 = 5.0;
+/// Seconds spent on each spot while searching.
+const SEARCH_LEG: f64 = 1.5;
+/// Decisions an [`Intelligence`] remembers.
+const DECISION_LOG_SIZE: usize = 32;
 
 // TODO
 pub struct Buff {
@@ -9,40 +30,129 @@ pub struct Buff {
 pub struct Attributes {
     pub ability: Ability,
     pub hit_points: u32,
+    pub max_hit_points: u32,
     pub mana_points: u32,
     pub buffs: Vec<Buff>,
 }
 
+impl Attributes {
+    /// Hit points left, from 0 to 1.
+    pub fn hit_point_fraction(&self) -> f32 {
+        if self.max_hit_points == 0 {
+            return 1.0
+        }
+        (self.hit_points as f32 / self.max_hit_points as f32).min(1.0)
+    }
+}
+
 pub struct State {
     pub position: Vector2,
     pub attributes: Attributes,
+    /// Whether an attack could go out right now, cool downs and conditions allowing.
+    pub attack_ready: bool,
 }
 
-pub struct Environment {
+/// Something a strategy can aim at: a node in real time, a combatant in a turn-based encounter.
+pub trait Target: Clone {
+    fn position(&self) -> Vector2;
+    fn is_same(&self, other: &Self) -> bool;
+}
+
+impl Target for Gd<Node2D> {
+    fn position(&self) -> Vector2 {
+        self.get_global_position()
+    }
+
+    fn is_same(&self, other: &Self) -> bool {
+        self.instance_id() == other.instance_id()
+    }
+}
+
+/// What a character knows about its surroundings. Enemies come closest first.
+pub struct Environment<T = Gd<Node2D>> {
     pub time: f64,
     pub delta: f64,
-    pub characters_in_attack_range: Vec<Gd<Node2D>>,
-    pub characters_in_sight: Vec<Gd<Node2D>>,
+    pub characters_in_attack_range: Vec<T>,
+    pub characters_in_sight: Vec<T>,
+    pub allies_in_sight: Vec<T>,
+    /// Enemies seen before but out of sight now, most recently seen first.
+    pub characters_remembered: Vec<Memory<T>>,
+    /// The most threatening enemy according to the unit's threat table, if it keeps one.
+    pub threat_target: Option<T>,
 }
 
-pub trait Strategy {
-    fn evaluate(&self, state: &State, env: &Environment) -> Command;
+impl<T: Target> Environment<T> {
+    /// Whom to attack among those in attack range: the most threatening if it is in range, the
+    /// closest otherwise.
+    pub fn attack_target(&self) -> Option<&T> {
+        self.threat_target.as_ref()
+            .and_then(|threat| self.characters_in_attack_range.iter().find(|character| character.is_same(threat)))
+            .or_else(|| self.characters_in_attack_range.first())
+    }
 }
 
-pub struct Aggroed {
-    pub target: Gd<Node2D>,
-    pub position: Vector2,
+pub trait Strategy<T = Gd<Node2D>> {
+    /// Shows up in the decision log.
+    fn name(&self) -> &'static str;
+
+    /// How much the strategy wants to be in charge right now, from 0 (not at all) to 1.
+    fn score(&self, state: &State, env: &Environment<T>) -> f32;
+
+    fn evaluate(&self, state: &State, env: &Environment<T>) -> Command<T>;
+}
+
+/// 1 right on top of something, falling to 0 at `range` and beyond.
+fn closeness(distance: real, range: real) -> f32 {
+    if range <= 0.0 {
+        return 0.0
+    }
+    (1.0 - distance / range).clamp(0.0, 1.0)
+}
+
+pub struct Aggroed<T = Gd<Node2D>> {
+    pub target: T,
     pub attack_range: real,
 }
 
-impl Strategy for Aggroed {
-    fn evaluate(&self, _state: &State, _env: &Environment) -> Command {
-        if self.target.get_position().distance_to(self.position) < self.attack_range {
-            Command::Attack(Attack::Direction {
-                direction: self.target.get_position() - self.position,
-            })
+impl<T: Target> Aggroed<T> {
+    /// Where the target is if in sight, otherwise where it was headed when last seen. Out of
+    /// sight and forgotten, the target is nowhere.
+    fn whereabouts(&self, env: &Environment<T>) -> Option<(Vector2, bool)> {
+        if let Some(target) = env.characters_in_sight.iter().find(|character| character.is_same(&self.target)) {
+            return Some((target.position(), true))
+        }
+        env.characters_remembered.iter()
+            .find(|memory| memory.character.is_same(&self.target))
+            .map(|memory| (memory.expected_position(env.time), false))
+    }
+}
+
+impl<T: Target> Strategy<T> for Aggroed<T> {
+    fn name(&self) -> &'static str {
+        "aggroed"
+    }
+
+    /// Out of sight the target is left to [`Investigate`], which scores higher.
+    fn score(&self, state: &State, env: &Environment<T>) -> f32 {
+        match self.whereabouts(env) {
+            Some((position, true)) if position.distance_to(state.position) < self.attack_range => 1.0,
+            Some((_, true)) => 0.8,
+            Some((_, false)) => 0.3,
+            None => 0.0,
+        }
+    }
+
+    fn evaluate(&self, state: &State, env: &Environment<T>) -> Command<T> {
+        let Some((position, _)) = self.whereabouts(env) else {
+            return Command::Stop
+        };
+        if position.distance_to(state.position) < self.attack_range {
+            if !state.attack_ready {
+                return Command::Hold
+            }
+            Command::Attack(Attack::towards(position - state.position))
         } else {
-            Command::Move(self.target.get_position())
+            Command::Move(Move::To(position))
         }
     }
 }
@@ -52,64 +162,355 @@ pub struct Sentry {
     pub follow_range: real,
 }
 
-impl Strategy for Sentry {
-    fn evaluate(&self, state: &State, env: &Environment) -> Command {
+impl<T: Target> Strategy<T> for Sentry {
+    fn name(&self) -> &'static str {
+        "sentry"
+    }
+
+    fn score(&self, state: &State, env: &Environment<T>) -> f32 {
+        if !env.characters_in_attack_range.is_empty() {
+            return 0.8
+        }
+        if state.position.distance_to(self.stay_position) > self.follow_range {
+            return 0.4
+        }
+        match env.characters_in_sight.first() {
+            // the closer the intruder, the more it matters
+            Some(visible) => 0.5 + 0.3 * closeness(visible.position().distance_to(state.position), self.follow_range),
+            None => 0.1,
+        }
+    }
+
+    fn evaluate(&self, state: &State, env: &Environment<T>) -> Command<T> {
         // attack if in range
-        if let Some(closest_character) = env.characters_in_attack_range.first() {
-            return Command::Attack(Attack::LockOn {
-                target: closest_character.clone(),
-            })
+        if let Some(target) = env.attack_target() {
+            return engage(state, target)
         }
 
         // move back to position if out of range
         if state.position.distance_to(self.stay_position) > self.follow_range {
-            return Command::Move(self.stay_position)
+            return Command::Move(Move::To(self.stay_position))
         }
 
         // move to character if in sight
         if let Some(visible_character) = env.characters_in_sight.first() {
-            return Command::Move(visible_character.get_position())
+            return Command::Move(Move::To(visible_character.position()))
         }
 
         Command::ContinueLast
     }
 }
 
-pub struct Intelligence {
-    pub strategies: Vec<Box<dyn Strategy>>
+/// Walks a round of waypoints, fighting whoever crosses its way and picking the round up again
+/// where it left off.
+pub struct Patrol {
+    pub waypoints: Vec<Vector2>,
+    /// How close to a waypoint counts as there.
+    pub arrival_distance: real,
+    next: Cell<usize>,
 }
 
-pub trait Behavior<I, A> {
-    fn update(&mut self, info: I, delta: f64);
-    fn action(&self) -> A;
+impl Patrol {
+    pub fn new(waypoints: Vec<Vector2>, arrival_distance: real) -> Self {
+        Patrol { waypoints, arrival_distance, next: Cell::new(0) }
+    }
+}
+
+impl<T: Target> Strategy<T> for Patrol {
+    fn name(&self) -> &'static str {
+        "patrol"
+    }
+
+    fn score(&self, _state: &State, env: &Environment<T>) -> f32 {
+        if !env.characters_in_attack_range.is_empty() {
+            0.8
+        } else if !env.characters_in_sight.is_empty() {
+            0.6
+        } else if !self.waypoints.is_empty() {
+            0.3
+        } else {
+            0.0
+        }
+    }
+
+    fn evaluate(&self, state: &State, env: &Environment<T>) -> Command<T> {
+        if let Some(target) = env.attack_target() {
+            return engage(state, target)
+        }
+        if let Some(visible_character) = env.characters_in_sight.first() {
+            return Command::Move(Move::To(visible_character.position()))
+        }
+        if self.waypoints.is_empty() {
+            return Command::Stop
+        }
+        let mut next = self.next.get() % self.waypoints.len();
+        if state.position.distance_to(self.waypoints[next]) <= self.arrival_distance {
+            next = (next + 1) % self.waypoints.len();
+        }
+        self.next.set(next);
+        Command::Move(Move::To(self.waypoints[next]))
+    }
+}
+
+/// Goes to where the last enemy to get out of sight was headed and searches the area for
+/// `search_time` seconds, unless somebody shows up. On a leash it never leaves the leash's range.
+pub struct Investigate {
+    pub search_time: f64,
+    /// How far from the last known position to look.
+    pub search_radius: real,
+    /// How close to the last known position counts as there.
+    pub arrival_distance: real,
+    /// Where to stay close to and how close, e.g. a sentry's post.
+    pub leash: Option<(Vector2, real)>,
+    /// When the enemy searched for was last seen and when the search started.
+    search: Cell<Option<(f64, f64)>>,
+}
+
+impl Investigate {
+    pub fn new(search_time: f64, search_radius: real, arrival_distance: real) -> Self {
+        Investigate { search_time, search_radius, arrival_distance, leash: None, search: Cell::new(None) }
+    }
+
+    pub fn with_leash(mut self, stay_position: Vector2, range: real) -> Self {
+        self.leash = Some((stay_position, range));
+        self
+    }
+
+    fn within_leash(&self, position: Vector2) -> bool {
+        !self.leash.is_some_and(|(stay_position, range)| position.distance_to(stay_position) > range)
+    }
+
+    /// Seconds spent searching for `memory`, if the search has started.
+    fn searching<T>(&self, memory: &Memory<T>, time: f64) -> Option<f64> {
+        self.search.get()
+            .filter(|&(seen_at, _)| seen_at == memory.seen_at)
+            .map(|(_, started)| time - started)
+    }
+}
+
+impl<T: Target> Strategy<T> for Investigate {
+    fn name(&self) -> &'static str {
+        "investigate"
+    }
+
+    fn score(&self, _state: &State, env: &Environment<T>) -> f32 {
+        if !env.characters_in_sight.is_empty() {
+            return 0.0
+        }
+        match env.characters_remembered.first() {
+            Some(memory) if self.within_leash(memory.expected_position(env.time))
+                && !self.searching(memory, env.time).is_some_and(|elapsed| elapsed >= self.search_time) => 0.55,
+            _ => 0.0,
+        }
+    }
+
+    fn evaluate(&self, state: &State, env: &Environment<T>) -> Command<T> {
+        let Some(memory) = env.characters_remembered.first() else {
+            return Command::Stop
+        };
+        let last_known = memory.expected_position(env.time);
+        let elapsed = match self.searching(memory, env.time) {
+            Some(elapsed) => elapsed,
+            None if state.position.distance_to(last_known) <= self.arrival_distance => {
+                self.search.set(Some((memory.seen_at, env.time)));
+                0.0
+            }
+            None => return Command::Move(Move::To(last_known)),
+        };
+        if elapsed >= self.search_time {
+            return Command::Stop
+        }
+        // a new spot around the last known position every leg, going round in wide steps
+        let leg = (elapsed / SEARCH_LEG) as usize;
+        let angle = leg as real * 2.4;
+        let spot = last_known + Vector2::new(angle.cos(), angle.sin()) * self.search_radius;
+        match self.leash {
+            Some((stay_position, range)) if !self.within_leash(spot) =>
+                Command::Move(Move::To(stay_position + (spot - stay_position).normalized() * range)),
+            _ => Command::Move(Move::To(spot)),
+        }
+    }
+}
+
+/// Falls back to a safe spot when badly hurt and outnumbered. Friends close by talk it out of
+/// running, see [`RETREAT_THRESHOLD`].
+pub struct Retreat {
+    pub to: Vector2,
+    /// Hit point fraction below which retreating is considered at all.
+    pub below: f32,
+}
+
+impl<T: Target> Strategy<T> for Retreat {
+    fn name(&self) -> &'static str {
+        "retreat"
+    }
+
+    fn score(&self, state: &State, env: &Environment<T>) -> f32 {
+        let enemies = env.characters_in_sight.len();
+        let hit_points = state.attributes.hit_point_fraction();
+        if enemies == 0 || self.below <= 0.0 || hit_points >= self.below {
+            return 0.0
+        }
+        let urgency = 1.0 - hit_points / self.below;
+        // friends close by make a stand worth it
+        let odds = enemies as f32 / (enemies + env.allies_in_sight.len()) as f32;
+        let score = urgency * odds;
+        if score < RETREAT_THRESHOLD {
+            return 0.0
+        }
+        score
+    }
+
+    fn evaluate(&self, state: &State, _env: &Environment<T>) -> Command<T> {
+        if state.position.distance_to(self.to) < 1.0 {
+            return Command::Hold
+        }
+        Command::Move(Move::To(self.to))
+    }
+}
+
+/// Attacks `target` if an attack is ready, stands its ground otherwise.
+fn engage<T: Target>(state: &State, target: &T) -> Command<T> {
+    if state.attack_ready {
+        Command::Attack(Attack::lock_on(target.clone()))
+    } else {
+        Command::Hold
+    }
 }
 
-pub enum Command {
-    ContinueLast,
-    Move(Vector2),
-    Attack(Attack),
-    Skill(Skill),
-    Stop,
-    Hold,
+/// What one strategy scored in a [`Decision`].
+#[derive(Debug, Clone, PartialEq)]
+pub struct Score {
+    pub strategy: &'static str,
+    pub priority: u8,
+    pub score: f32,
 }
 
-pub enum Attack {
-    LockOn {
-        target: Gd<Node2D>,
-    },
-    Direction {
-        direction: Vector2,
-    },
+/// Why an [`Intelligence`] put a strategy in charge.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Decision {
+    pub time: f64,
+    pub scores: Vec<Score>,
+    pub chosen: Option<&'static str>,
+    pub previous: Option<&'static str>,
+}
+
+impl Display for Decision {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{:.1}s: {} -> {} (", self.time, self.previous.unwrap_or("nothing"), self.chosen.unwrap_or("nothing"))?;
+        for (i, score) in self.scores.iter().enumerate() {
+            if i > 0 {
+                write!(f, ", ")?;
+            }
+            write!(f, "{} {:.2}", score.strategy, score.score)?;
+            if score.priority > 0 {
+                write!(f, " [priority {}]", score.priority)?;
+            }
+        }
+        write!(f, ")")
+    }
+}
+
+/// Puts one of its strategies in charge at a time. The highest priority among those scoring above
+/// zero wins, then the highest score. The strategy in charge gets `hysteresis` on top of its score
+/// so that close calls do not flip-flop every tick.
+pub struct Intelligence<T = Gd<Node2D>> {
+    strategies: Vec<(u8, Box<dyn Strategy<T>>)>,
+    pub hysteresis: f32,
+    current: Option<usize>,
+    latest: Option<Decision>,
+    /// Every change of mind, oldest first.
+    log: VecDeque<Decision>,
+}
+
+impl<T> Intelligence<T> {
+    /// Strategies that all have the same priority.
+    pub fn new(strategies: Vec<Box<dyn Strategy<T>>>) -> Self {
+        Intelligence {
+            strategies: strategies.into_iter().map(|strategy| (0, strategy)).collect(),
+            hysteresis: DEFAULT_HYSTERESIS,
+            current: None,
+            latest: None,
+            log: VecDeque::new(),
+        }
+    }
+
+    pub fn with_strategy(mut self, priority: u8, strategy: Box<dyn Strategy<T>>) -> Self {
+        self.strategies.push((priority, strategy));
+        self
+    }
+
+    pub fn with_hysteresis(mut self, hysteresis: f32) -> Self {
+        self.hysteresis = hysteresis;
+        self
+    }
+
+    /// Swaps the strategy at `index` for another one with the same priority. If it was in charge,
+    /// the new one is.
+    pub fn set_strategy(&mut self, index: usize, strategy: Box<dyn Strategy<T>>) {
+        self.strategies[index].1 = strategy;
+    }
+
+    /// The name of the strategy in charge.
+    pub fn current(&self) -> Option<&'static str> {
+        self.current.map(|i| self.strategies[i].1.name())
+    }
+
+    /// The scores of the last evaluation.
+    pub fn latest(&self) -> Option<&Decision> {
+        self.latest.as_ref()
+    }
+
+    /// The last changes of mind, oldest first.
+    pub fn decisions(&self) -> impl Iterator<Item = &Decision> {
+        self.log.iter()
+    }
+
+    /// Scores every strategy, puts the best one in charge and asks it for a command.
+    pub fn evaluate(&mut self, state: &State, env: &Environment<T>) -> Command<T> {
+        let scores: Vec<Score> = self.strategies.iter()
+            .map(|(priority, strategy)| Score {
+                strategy: strategy.name(),
+                priority: *priority,
+                score: strategy.score(state, env).clamp(0.0, 1.0),
+            })
+            .collect();
+        let previous = self.current;
+        let effective = |i: usize| scores[i].score + if Some(i) == previous { self.hysteresis } else { 0.0 };
+        // reversed so that ties go to the strategy that comes first
+        let chosen = (0..scores.len()).rev()
+            .filter(|&i| scores[i].score > 0.0)
+            .max_by(|&a, &b| scores[a].priority.cmp(&scores[b].priority).then(effective(a).total_cmp(&effective(b))));
+
+        let decision = Decision {
+            time: env.time,
+            chosen: chosen.map(|i| scores[i].strategy),
+            previous: previous.map(|i| self.strategies[i].1.name()),
+            scores,
+        };
+        if chosen != previous {
+            if self.log.len() == DECISION_LOG_SIZE {
+                self.log.pop_front();
+            }
+            self.log.push_back(decision.clone());
+        }
+        self.latest = Some(decision);
+        self.current = chosen;
+        match chosen {
+            Some(i) => self.strategies[i].1.evaluate(state, env),
+            None => Command::ContinueLast,
+        }
+    }
 }
 
-pub enum AttackType {
-    Melee,
-    Ranged,
+pub trait Behavior<I, A> {
+    fn update(&mut self, info: I, delta: f64);
+    fn action(&self) -> A;
 }
 
 // Offensive skills
-#[derive(Debug)]
-enum OffensiveSkill {
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub enum OffensiveSkill {
     DirectDamage { damage: i32 },
     DamageOverTime { damage: i32, duration: i32, tick_rate: real },
     AreaOfEffect { damage: i32, radius: real },
@@ -118,8 +519,8 @@ enum OffensiveSkill {
 }
 
 // Defensive skills
-#[derive(Debug)]
-enum DefensiveSkill {
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub enum DefensiveSkill {
     Shielding { shield_strength: i32 },
     Healing { heal_amount: i32 },
     DamageAbsorption { absorb_limit: i32 },
@@ -128,8 +529,8 @@ enum DefensiveSkill {
 }
 
 // Utility skills
-#[derive(Debug)]
-enum UtilitySkill {
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub enum UtilitySkill {
     Buffing { ability: Ability, duration: i32 },
     Debuffing { ability: Ability, duration: i32 },
     Summoning { summon_id: String },
@@ -138,19 +539,39 @@ enum UtilitySkill {
 }
 
 // Crowd control skills
-#[derive(Debug)]
-enum CrowdControlSkill {
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub enum CrowdControlSkill {
     Stun { duration: i32 },
-    Slow { amount: real, duration: i32 },
+    /// Halves movement speed, as the slowed condition does.
+    Slow { duration: i32 },
     Knockback { distance: real },
     Silence { duration: i32 },
     Fear { duration: i32 },
     Charm { duration: i32 },
+    /// Makes the target go after the caster, whoever else it is angry with.
+    Taunt { duration: i32 },
+}
+
+impl CrowdControlSkill {
+    /// The condition the skill inflicts, durations are in seconds. Knocked back targets lie prone
+    /// for a second while they get back up; a taunt is no condition.
+    pub fn condition(&self) -> Option<Condition> {
+        let (kind, duration) = match *self {
+            CrowdControlSkill::Stun { duration } => (ConditionType::Stunned, duration),
+            CrowdControlSkill::Slow { duration } => (ConditionType::Slowed, duration),
+            CrowdControlSkill::Silence { duration } => (ConditionType::Silenced, duration),
+            CrowdControlSkill::Fear { duration } => (ConditionType::Frightened, duration),
+            CrowdControlSkill::Charm { duration } => (ConditionType::Charmed, duration),
+            CrowdControlSkill::Knockback { .. } => (ConditionType::Prone, 1),
+            CrowdControlSkill::Taunt { .. } => return None,
+        };
+        Some(Condition::for_seconds(kind, duration as f64))
+    }
 }
 
 // Elemental/Magic skills
-#[derive(Debug)]
-enum ElementalSkill {
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub enum ElementalSkill {
     Fire { damage: i32, area: real },
     Ice { damage: i32, slow_effect: real },
     Lightning { damage: i32, chain_targets: i32 },
@@ -160,8 +581,8 @@ enum ElementalSkill {
 }
 
 // General Skill enum that encompasses all types of skills
-#[derive(Debug)]
-enum SkillType {
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub enum SkillType {
     Offensive(OffensiveSkill),
     Defensive(DefensiveSkill),
     Utility(UtilitySkill),
@@ -169,11 +590,229 @@ enum SkillType {
     Elemental(ElementalSkill),
 }
 
-#[derive(Debug)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub struct Skill {
     pub name: String,
+    #[serde(default)]
     pub description: String,
     pub skill_types: Vec<SkillType>,
+    #[serde(default)]
+    pub targeting: Targeting,
+    /// Seconds between two casts.
     pub cooldown: f64,
+    /// Seconds from starting the cast until it takes effect.
+    #[serde(default)]
+    pub cast_time: f64,
+    /// Seconds since the last cast.
+    #[serde(skip)]
     pub elapsed: f64,
-}
\ No newline at end of file
+}
+
+impl Skill {
+    pub fn is_ready(&self) -> bool {
+        self.elapsed >= self.cooldown
+    }
+
+    /// Seconds until the skill can be cast again.
+    pub fn remaining_cooldown(&self) -> f64 {
+        (self.cooldown - self.elapsed).max(0.0)
+    }
+
+    /// Whether the skill is meant for somebody the caster has `attitude` toward. Skills that only
+    /// heal, shield or buff are for friends, everything else is for enemies.
+    pub fn reaches(&self, attitude: Attitude) -> bool {
+        let supportive = self.skill_types.iter().all(|skill_type| {
+            matches!(skill_type, SkillType::Defensive(_) | SkillType::Utility(UtilitySkill::Buffing { .. }))
+        });
+        match attitude {
+            Attitude::Friendly => supportive,
+            Attitude::Hostile => !supportive,
+            Attitude::Unfriendly | Attitude::Indifferent => false,
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::ai::fixture::Dummy;
+    use super::*;
+
+    fn state(position: Vector2, attack_ready: bool) -> State {
+        hurt(position, attack_ready, 10)
+    }
+
+    fn hurt(position: Vector2, attack_ready: bool, hit_points: u32) -> State {
+        let attributes = Attributes { ability: Ability::default(), hit_points, max_hit_points: 10, mana_points: 0, buffs: Vec::new() };
+        State { position, attributes, attack_ready }
+    }
+
+    fn env(in_sight: Vec<Dummy>, attack_range: real, position: Vector2) -> Environment<Dummy> {
+        Environment {
+            time: 0.0,
+            delta: 0.1,
+            characters_in_attack_range: in_sight.iter()
+                .filter(|dummy| dummy.0.distance_to(position) <= attack_range)
+                .cloned()
+                .collect(),
+            characters_in_sight: in_sight,
+            allies_in_sight: Vec::new(),
+            characters_remembered: Vec::new(),
+            threat_target: None,
+        }
+    }
+
+    #[test]
+    fn test_patrol() {
+        let waypoints = vec![Vector2::new(0.0, 0.0), Vector2::new(100.0, 0.0)];
+        let patrol = Patrol::new(waypoints, 4.0);
+        let at_start = state(Vector2::new(1.0, 0.0), true);
+        assert_eq!(patrol.evaluate(&at_start, &env(vec![], 10.0, at_start.position)), Command::Move(Move::To(Vector2::new(100.0, 0.0))));
+        // halfway there
+        let halfway = state(Vector2::new(50.0, 0.0), true);
+        assert_eq!(patrol.evaluate(&halfway, &env(vec![], 10.0, halfway.position)), Command::Move(Move::To(Vector2::new(100.0, 0.0))));
+
+        let intruder = Dummy(Vector2::new(55.0, 0.0));
+        assert_eq!(patrol.evaluate(&halfway, &env(vec![intruder.clone()], 10.0, halfway.position)), Command::Attack(Attack::lock_on(intruder.clone())));
+        let cooling_down = state(halfway.position, false);
+        assert_eq!(patrol.evaluate(&cooling_down, &env(vec![intruder.clone()], 10.0, halfway.position)), Command::Hold);
+
+        // the most threatening in range goes first, sentries alike
+        let threat = Dummy(Vector2::new(45.0, 0.0));
+        let mut crowded = env(vec![intruder.clone(), threat.clone()], 10.0, halfway.position);
+        crowded.threat_target = Some(threat.clone());
+        assert_eq!(patrol.evaluate(&halfway, &crowded), Command::Attack(Attack::lock_on(threat.clone())));
+        let sentry = Sentry { stay_position: halfway.position, follow_range: 50.0 };
+        assert_eq!(sentry.evaluate(&halfway, &crowded), Command::Attack(Attack::lock_on(threat)));
+        crowded.threat_target = Some(Dummy(Vector2::new(90.0, 0.0)));
+        assert_eq!(sentry.evaluate(&halfway, &crowded), Command::Attack(Attack::lock_on(intruder)));
+
+        let at_end = state(Vector2::new(99.0, 0.0), true);
+        assert_eq!(patrol.evaluate(&at_end, &env(vec![], 10.0, at_end.position)), Command::Move(Move::To(Vector2::ZERO)));
+        assert_eq!(Strategy::<Dummy>::evaluate(&Patrol::new(Vec::new(), 4.0), &at_end, &env(vec![], 10.0, at_end.position)), Command::Stop);
+    }
+
+    #[test]
+    fn test_intelligence() {
+        let home = Vector2::ZERO;
+        let sentry = Sentry { stay_position: home, follow_range: 50.0 };
+        let patrol = Patrol::new(vec![Vector2::new(30.0, 0.0)], 4.0);
+        let mut intelligence: Intelligence<Dummy> = Intelligence::new(vec![Box::new(sentry), Box::new(patrol)])
+            .with_strategy(1, Box::new(Retreat { to: home, below: 0.5 }));
+
+        // an idle sentry scores less than a patrol with somewhere to go
+        let idle = state(home, true);
+        assert_eq!(intelligence.evaluate(&idle, &env(vec![], 10.0, home)), Command::Move(Move::To(Vector2::new(30.0, 0.0))));
+        assert_eq!(intelligence.current(), Some("patrol"));
+
+        // both want to fight an intruder, the patrol keeps the job rather than flip-flopping
+        let intruder = Dummy(Vector2::new(5.0, 0.0));
+        assert_eq!(intelligence.evaluate(&idle, &env(vec![intruder.clone()], 10.0, home)), Command::Attack(Attack::lock_on(intruder.clone())));
+        assert_eq!(intelligence.current(), Some("patrol"));
+        let latest = intelligence.latest().unwrap();
+        assert_eq!(latest.scores.iter().map(|score| score.score).collect::<Vec<_>>(), vec![0.8, 0.8, 0.0]);
+
+        // badly hurt and alone, retreating outranks everything else
+        let dying = hurt(Vector2::new(5.0, 5.0), true, 1);
+        assert_eq!(intelligence.evaluate(&dying, &env(vec![intruder.clone()], 10.0, dying.position)), Command::Move(Move::To(home)));
+        assert_eq!(intelligence.current(), Some("retreat"));
+        // with friends around it is not worth running
+        let mut friends = env(vec![intruder.clone()], 10.0, dying.position);
+        friends.allies_in_sight = vec![Dummy(home), Dummy(home), Dummy(home)];
+        assert_eq!(Strategy::<Dummy>::score(&Retreat { to: home, below: 0.5 }, &dying, &friends), 0.0);
+        assert_eq!(intelligence.evaluate(&dying, &friends), Command::Attack(Attack::lock_on(intruder.clone())));
+        friends.allies_in_sight.truncate(1);
+        assert_eq!(Strategy::<Dummy>::score(&Retreat { to: home, below: 0.5 }, &dying, &friends), 0.4);
+
+        let decisions: Vec<_> = intelligence.decisions().map(|decision| (decision.previous, decision.chosen)).collect();
+        assert_eq!(decisions, vec![(None, Some("patrol")), (Some("patrol"), Some("retreat")), (Some("retreat"), Some("sentry"))]);
+        assert_eq!(
+            intelligence.decisions().nth(1).unwrap().to_string(),
+            "0.0s: patrol -> retreat (sentry 0.80, patrol 0.80, retreat 0.80 [priority 1])"
+        );
+        assert_eq!(Intelligence::<Dummy>::new(Vec::new()).evaluate(&idle, &env(vec![], 10.0, home)), Command::ContinueLast);
+    }
+
+    #[test]
+    fn test_hysteresis() {
+        let far = Vector2::new(80.0, 0.0);
+        let sentry = Sentry { stay_position: Vector2::ZERO, follow_range: 50.0 };
+        let patrol = Patrol::new(vec![far], 4.0);
+        let mut intelligence: Intelligence<Dummy> = Intelligence::new(vec![Box::new(patrol), Box::new(sentry)]);
+        // out at the far waypoint the sentry wants back home, 0.4 against the patrol's 0.3
+        let away = state(far, true);
+        assert_eq!(intelligence.evaluate(&away, &env(vec![], 10.0, far)), Command::Move(Move::To(Vector2::ZERO)));
+        assert_eq!(intelligence.current(), Some("sentry"));
+
+        let mut stubborn: Intelligence<Dummy> = Intelligence::new(vec![
+            Box::new(Patrol::new(vec![far], 4.0)),
+            Box::new(Sentry { stay_position: Vector2::ZERO, follow_range: 50.0 }),
+        ]).with_hysteresis(0.2);
+        let close = state(Vector2::new(40.0, 0.0), true);
+        stubborn.evaluate(&close, &env(vec![], 10.0, close.position));
+        assert_eq!(stubborn.current(), Some("patrol"));
+        // a lead of 0.1 is not enough to take over
+        stubborn.evaluate(&away, &env(vec![], 10.0, far));
+        assert_eq!(stubborn.current(), Some("patrol"));
+        assert_eq!(stubborn.decisions().count(), 1);
+    }
+
+    #[test]
+    fn test_investigate() {
+        let investigate = Investigate::new(3.0, 20.0, 4.0);
+        let fled = Memory {
+            character: Dummy(Vector2::new(200.0, 0.0)),
+            position: Vector2::new(90.0, 0.0),
+            velocity: Vector2::new(10.0, 0.0),
+            seen_at: 1.0,
+            in_sight: false,
+        };
+        let at = |time: f64| {
+            let mut env = env(vec![], 10.0, Vector2::ZERO);
+            env.time = time;
+            env.characters_remembered = vec![fled.clone()];
+            env
+        };
+        let idle = state(Vector2::ZERO, true);
+        assert_eq!(Strategy::<Dummy>::score(&investigate, &idle, &env(vec![], 10.0, Vector2::ZERO)), 0.0);
+        assert_eq!(investigate.score(&idle, &at(2.0)), 0.55);
+        // heads for where the enemy was going rather than where it is
+        assert_eq!(investigate.evaluate(&idle, &at(2.0)), Command::Move(Move::To(Vector2::new(100.0, 0.0))));
+
+        let arrived = state(Vector2::new(99.0, 0.0), true);
+        let Command::Move(Move::To(first)) = investigate.evaluate(&arrived, &at(3.0)) else {
+            panic!("not searching")
+        };
+        assert!((first.distance_to(Vector2::new(100.0, 0.0)) - 20.0).abs() < 0.01);
+        let Command::Move(Move::To(second)) = investigate.evaluate(&arrived, &at(4.5)) else {
+            panic!("not searching")
+        };
+        assert_ne!(first, second);
+
+        // gives up after a while, and somebody in sight matters more anyway
+        assert_eq!(investigate.evaluate(&arrived, &at(6.0)), Command::Stop);
+        assert_eq!(investigate.score(&arrived, &at(6.0)), 0.0);
+        let mut spotted = at(4.0);
+        spotted.characters_in_sight = vec![Dummy(Vector2::new(120.0, 0.0))];
+        assert_eq!(investigate.score(&arrived, &spotted), 0.0);
+
+        // a sentry does not search beyond its post's range
+        let leashed = Investigate::new(3.0, 20.0, 4.0).with_leash(Vector2::ZERO, 50.0);
+        assert_eq!(leashed.score(&idle, &at(2.0)), 0.0);
+        let near = Investigate::new(3.0, 20.0, 4.0).with_leash(Vector2::ZERO, 100.0);
+        assert_eq!(near.score(&idle, &at(2.0)), 0.55);
+        near.evaluate(&arrived, &at(3.0));
+        let Command::Move(Move::To(spot)) = near.evaluate(&arrived, &at(3.1)) else {
+            panic!("not searching")
+        };
+        assert!(spot.length() <= 100.01);
+
+        // an aggroed goblin leaves the search to investigating, and knows no more than it saw
+        let aggroed = Aggroed { target: fled.character.clone(), attack_range: 10.0 };
+        assert_eq!(aggroed.score(&idle, &at(2.0)), 0.3);
+        assert_eq!(aggroed.evaluate(&idle, &at(2.0)), Command::Move(Move::To(Vector2::new(100.0, 0.0))));
+        assert_eq!(aggroed.score(&idle, &env(vec![], 10.0, Vector2::ZERO)), 0.0);
+        let mut intelligence: Intelligence<Dummy> = Intelligence::new(vec![Box::new(aggroed), Box::new(Investigate::new(3.0, 20.0, 4.0))]);
+        intelligence.evaluate(&idle, &at(2.0));
+        assert_eq!(intelligence.current(), Some("investigate"));
+    }
+}
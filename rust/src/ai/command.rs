@@ -1,28 +1,140 @@
 use godot::prelude::*;
 
-pub enum Command {
+/// What a controller wants a character to do. Player input, AI strategies, replays and the network
+/// all speak it, and every character carries it out through its [`Executor`].
+#[derive(Debug, Clone, PartialEq)]
+pub enum Command<T = Gd<Node2D>> {
+    /// Keep on with whatever the last command started.
+    ContinueLast,
     Move(Move),
-    Attack(Attack)
+    Attack(Attack<T>),
+    /// Use the skill called `name`.
+    Skill {
+        name: String,
+        target: AttackTarget<T>,
+    },
+    /// Stop moving and forget where it was going.
+    Stop,
+    /// Stand the ground, e.g. to wait out a cool down.
+    Hold,
 }
 
+#[derive(Debug, Clone, Copy, PartialEq)]
 pub enum Move {
-    Target(Vector2),
+    /// Find a way to a point in the world.
+    To(Vector2),
+    /// Walk straight on, the way a stick or the arrow keys steer. A zero direction stands still.
     Direction(Vector2),
 }
 
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum AttackType {
     Melee,
     Ranged,
-    Skill(String),
 }
 
-pub enum AttackTarget {
-    Target(Gd<Node2D>),
+#[derive(Debug, Clone, PartialEq)]
+pub enum AttackTarget<T = Gd<Node2D>> {
+    LockOn(T),
     Direction(Vector2),
+    /// A spot in the world, for skills that land somewhere rather than on someone.
+    Point(Vector2),
 }
 
-pub struct Attack {
+#[derive(Debug, Clone, PartialEq)]
+pub struct Attack<T = Gd<Node2D>> {
     pub r#type: AttackType,
-    pub target: AttackTarget,
+    pub target: AttackTarget<T>,
 }
 
+impl<T> Attack<T> {
+    /// A melee attack on `target`, wherever it goes.
+    pub fn lock_on(target: T) -> Self {
+        Attack { r#type: AttackType::Melee, target: AttackTarget::LockOn(target) }
+    }
+
+    /// A melee attack on whatever is in `direction`.
+    pub fn towards(direction: Vector2) -> Self {
+        Attack { r#type: AttackType::Melee, target: AttackTarget::Direction(direction) }
+    }
+}
+
+/// Turns [`Command`]s into movement, animation and attacks. Moves only set where the character is
+/// headed, it keeps going there on its own until told otherwise.
+pub trait Executor<T = Gd<Node2D>> {
+    fn move_to(&mut self, destination: Vector2);
+
+    fn walk(&mut self, direction: Vector2);
+
+    fn attack(&mut self, attack: Attack<T>);
+
+    /// Characters without skills ignore it.
+    fn use_skill(&mut self, _name: &str, _target: AttackTarget<T>) {}
+
+    fn stop(&mut self);
+
+    fn hold(&mut self) {
+        self.stop()
+    }
+
+    fn execute(&mut self, command: Command<T>) {
+        match command {
+            Command::ContinueLast => {}
+            Command::Move(Move::To(destination)) => self.move_to(destination),
+            Command::Move(Move::Direction(direction)) => self.walk(direction),
+            Command::Attack(attack) => self.attack(attack),
+            Command::Skill { name, target } => self.use_skill(&name, target),
+            Command::Stop => self.stop(),
+            Command::Hold => self.hold(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    /// Remembers what it was told, in the order it was told.
+    #[derive(Default)]
+    struct Recorder(Vec<String>);
+
+    impl Executor<u32> for Recorder {
+        fn move_to(&mut self, destination: Vector2) {
+            self.0.push(format!("move to {:?}", destination));
+        }
+
+        fn walk(&mut self, direction: Vector2) {
+            self.0.push(format!("walk {:?}", direction));
+        }
+
+        fn attack(&mut self, attack: Attack<u32>) {
+            self.0.push(format!("attack {:?}", attack.target));
+        }
+
+        fn stop(&mut self) {
+            self.0.push("stop".to_string());
+        }
+    }
+
+    #[test]
+    fn test_execute() {
+        let mut recorder = Recorder::default();
+        let commands = [
+            Command::Move(Move::To(Vector2::new(1.0, 2.0))),
+            Command::ContinueLast,
+            Command::Attack(Attack::lock_on(7)),
+            Command::Skill { name: "Fireball".to_string(), target: AttackTarget::LockOn(7) },
+            Command::Move(Move::Direction(Vector2::RIGHT)),
+            Command::Hold,
+        ];
+        for command in commands {
+            recorder.execute(command);
+        }
+        assert_eq!(recorder.0, vec![
+            format!("move to {:?}", Vector2::new(1.0, 2.0)),
+            "attack LockOn(7)".to_string(),
+            format!("walk {:?}", Vector2::RIGHT),
+            "stop".to_string(),
+        ]);
+    }
+}
@@ -0,0 +1,30 @@
+use godot::prelude::*;
+use crate::ai::behavior::Target;
+
+/// A character in the tests, the same as another only if it stands in the same spot.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Dummy(pub Vector2);
+
+impl Target for Dummy {
+    fn position(&self) -> Vector2 {
+        self.0
+    }
+
+    fn is_same(&self, other: &Self) -> bool {
+        self == other
+    }
+}
+
+/// A character in the tests that is told apart by its number, wherever it goes.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Tagged(pub u32, pub Vector2);
+
+impl Target for Tagged {
+    fn position(&self) -> Vector2 {
+        self.1
+    }
+
+    fn is_same(&self, other: &Self) -> bool {
+        self.0 == other.0
+    }
+}
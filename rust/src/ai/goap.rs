@@ -0,0 +1,378 @@
+use std::cmp::{Ordering, Reverse};
+use std::collections::{BTreeSet, BinaryHeap, HashSet, VecDeque};
+use godot::prelude::*;
+use crate::ai::behavior::Target;
+use crate::ai::command::{Attack, Command, Move};
+
+/// Something that is either true or not about the world as a character sees it.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum Fact {
+    HasWood,
+    TreeInSight,
+    NearTree,
+    NearHome,
+    EnemyVisible,
+    NearEnemy,
+    /// Only ever planned for, nobody sees wood being stored.
+    WoodStored,
+    /// Only ever planned for, like [`Fact::WoodStored`].
+    EnemyHurt,
+}
+
+/// A fact and whether it should hold, as wanted by a precondition, an effect or a goal.
+pub type Condition = (Fact, bool);
+
+/// The facts that hold, all others do not.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Hash)]
+pub struct WorldState(BTreeSet<Fact>);
+
+impl WorldState {
+    pub fn new(facts: &[Fact]) -> Self {
+        WorldState(facts.iter().copied().collect())
+    }
+
+    pub fn holds(&self, fact: Fact) -> bool {
+        self.0.contains(&fact)
+    }
+
+    pub fn set(&mut self, fact: Fact, holds: bool) {
+        if holds {
+            self.0.insert(fact);
+        } else {
+            self.0.remove(&fact);
+        }
+    }
+
+    pub fn satisfies(&self, conditions: &[Condition]) -> bool {
+        conditions.iter().all(|&(fact, holds)| self.holds(fact) == holds)
+    }
+
+    fn unmet(&self, conditions: &[Condition]) -> usize {
+        conditions.iter().filter(|&&(fact, holds)| self.holds(fact) != holds).count()
+    }
+
+    fn apply(&self, effects: &[Condition]) -> Self {
+        let mut next = self.clone();
+        effects.iter().for_each(|&(fact, holds)| next.set(fact, holds));
+        next
+    }
+}
+
+/// Where the things a plan is about are, to turn its steps into commands.
+pub struct Situation<T = Gd<Node2D>> {
+    pub position: Vector2,
+    pub home: Vector2,
+    /// The closest tree still standing.
+    pub tree: Option<Vector2>,
+    /// The closest enemy in sight.
+    pub enemy: Option<T>,
+    pub attack_ready: bool,
+}
+
+pub struct GoapAction<T = Gd<Node2D>> {
+    pub name: &'static str,
+    pub cost: f32,
+    pub preconditions: Vec<Condition>,
+    pub effects: Vec<Condition>,
+    /// Done as soon as its command went out, instead of running until the world changes. What it
+    /// achieved shows in the world, so the next update plans anew.
+    pub instant: bool,
+    /// The command that carries the action out, `None` when the situation does not allow it.
+    pub command: fn(&Situation<T>) -> Option<Command<T>>,
+}
+
+pub struct Goal {
+    pub name: &'static str,
+    /// Goals with a higher priority are planned for first.
+    pub priority: u8,
+    pub conditions: Vec<Condition>,
+}
+
+/// A state on the frontier of the search, ordered so that the cheapest estimate comes out of the
+/// heap first.
+struct Open {
+    estimate: f32,
+    cost: f32,
+    state: WorldState,
+    steps: Vec<usize>,
+}
+
+impl PartialEq for Open {
+    fn eq(&self, other: &Self) -> bool {
+        self.cmp(other) == Ordering::Equal
+    }
+}
+
+impl Eq for Open {}
+
+impl PartialOrd for Open {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for Open {
+    fn cmp(&self, other: &Self) -> Ordering {
+        // reversed for the max-heap, shorter plans win ties
+        other.estimate.total_cmp(&self.estimate).then(other.steps.len().cmp(&self.steps.len()))
+    }
+}
+
+/// The cheapest sequence of actions, as indices into `actions`, that takes the world from `start`
+/// to one satisfying `goal`. A* over world states; the estimate counts the unmet conditions as if
+/// the cheapest action fixed as many of them at once as any action does, so it never overshoots.
+pub fn plan<T>(actions: &[GoapAction<T>], start: &WorldState, goal: &[Condition]) -> Option<Vec<usize>> {
+    let cheapest = actions.iter().map(|a| a.cost).fold(f32::INFINITY, f32::min);
+    let widest = actions.iter().map(|a| a.effects.len()).max().unwrap_or(1).max(1);
+    let heuristic = |state: &WorldState| state.unmet(goal).div_ceil(widest) as f32 * cheapest;
+
+    let mut open = BinaryHeap::from([Open { estimate: heuristic(start), cost: 0.0, state: start.clone(), steps: Vec::new() }]);
+    let mut closed = HashSet::new();
+    while let Some(Open { cost, state, steps, .. }) = open.pop() {
+        if state.satisfies(goal) {
+            return Some(steps)
+        }
+        if !closed.insert(state.clone()) {
+            continue
+        }
+        for (i, action) in actions.iter().enumerate() {
+            if !state.satisfies(&action.preconditions) {
+                continue
+            }
+            let next = state.apply(&action.effects);
+            if closed.contains(&next) {
+                continue
+            }
+            let cost = cost + action.cost;
+            let mut steps = steps.clone();
+            steps.push(i);
+            open.push(Open { estimate: cost + heuristic(&next), cost, state: next, steps });
+        }
+    }
+    None
+}
+
+/// Plans for the most important goal it can reach and follows the plan one command at a time.
+/// Whenever the world turns out other than before, it plans anew.
+pub struct Planner<T = Gd<Node2D>> {
+    actions: Vec<GoapAction<T>>,
+    goals: Vec<Goal>,
+    goal: Option<usize>,
+    steps: VecDeque<usize>,
+    /// The world as last seen.
+    world: Option<WorldState>,
+}
+
+impl<T: Target> Planner<T> {
+    pub fn new(actions: Vec<GoapAction<T>>, mut goals: Vec<Goal>) -> Self {
+        goals.sort_by_key(|goal| Reverse(goal.priority));
+        Planner { actions, goals, goal: None, steps: VecDeque::new(), world: None }
+    }
+
+    /// The goal being worked towards.
+    pub fn goal(&self) -> Option<&'static str> {
+        self.goal.map(|i| self.goals[i].name)
+    }
+
+    /// The actions left in the plan, the one under way first.
+    pub fn steps(&self) -> impl Iterator<Item = &'static str> + '_ {
+        self.steps.iter().map(|&i| self.actions[i].name)
+    }
+
+    /// Drops the plan, the next update makes a new one.
+    pub fn invalidate(&mut self) {
+        self.steps.clear();
+        self.world = None;
+    }
+
+    pub fn update(&mut self, world: &WorldState, situation: &Situation<T>) -> Command<T> {
+        let stale = !self.steps.front()
+            .is_some_and(|&step| world.satisfies(&self.actions[step].preconditions));
+        if stale || self.world.as_ref() != Some(world) {
+            self.replan(world);
+        }
+        self.world = Some(world.clone());
+
+        let Some(&step) = self.steps.front() else {
+            return Command::Hold
+        };
+        let action = &self.actions[step];
+        match (action.command)(situation) {
+            Some(command) => {
+                if action.instant {
+                    self.invalidate();
+                }
+                command
+            }
+            None => {
+                self.invalidate();
+                Command::Hold
+            }
+        }
+    }
+
+    fn replan(&mut self, world: &WorldState) {
+        let found = self.goals.iter().enumerate()
+            .filter(|(_, goal)| !world.satisfies(&goal.conditions))
+            .find_map(|(i, goal)| plan(&self.actions, world, &goal.conditions).map(|steps| (i, steps)));
+        match found {
+            Some((goal, steps)) => {
+                if self.goal != Some(goal) {
+                    tracing::debug!("planning for {}: {:?}", self.goals[goal].name, steps.iter().map(|&i| self.actions[i].name).collect::<Vec<_>>());
+                }
+                self.goal = Some(goal);
+                self.steps = steps.into();
+            }
+            None => {
+                self.goal = None;
+                self.steps.clear();
+            }
+        }
+    }
+}
+
+/// Chopping trees and carrying the wood home, or raiding whoever comes into sight.
+pub fn goblin_actions<T: Target>() -> Vec<GoapAction<T>> {
+    use Fact::*;
+    vec![
+        GoapAction {
+            name: "go to tree",
+            cost: 2.0,
+            preconditions: vec![(TreeInSight, true), (HasWood, false)],
+            effects: vec![(NearTree, true), (NearHome, false), (NearEnemy, false)],
+            instant: false,
+            command: |s| s.tree.map(|tree| Command::Move(Move::To(tree))),
+        },
+        GoapAction {
+            name: "chop",
+            cost: 1.0,
+            preconditions: vec![(NearTree, true), (HasWood, false)],
+            effects: vec![(HasWood, true)],
+            instant: true,
+            command: |s| match s.tree {
+                Some(_) if !s.attack_ready => Some(Command::Hold),
+                Some(tree) => Some(Command::Attack(Attack::towards(tree - s.position))),
+                None => None,
+            },
+        },
+        GoapAction {
+            name: "go home",
+            cost: 2.0,
+            preconditions: vec![],
+            effects: vec![(NearHome, true), (NearTree, false), (NearEnemy, false)],
+            instant: false,
+            command: |s| Some(Command::Move(Move::To(s.home))),
+        },
+        GoapAction {
+            name: "drop wood",
+            cost: 1.0,
+            preconditions: vec![(HasWood, true), (NearHome, true)],
+            effects: vec![(HasWood, false), (WoodStored, true)],
+            instant: false,
+            command: |_| Some(Command::Hold),
+        },
+        GoapAction {
+            name: "approach enemy",
+            cost: 2.0,
+            preconditions: vec![(EnemyVisible, true)],
+            effects: vec![(NearEnemy, true), (NearHome, false), (NearTree, false)],
+            instant: false,
+            command: |s| s.enemy.as_ref().map(|enemy| Command::Move(Move::To(enemy.position()))),
+        },
+        GoapAction {
+            name: "attack enemy",
+            cost: 1.0,
+            preconditions: vec![(EnemyVisible, true), (NearEnemy, true)],
+            effects: vec![(EnemyHurt, true)],
+            instant: true,
+            command: |s| match &s.enemy {
+                Some(_) if !s.attack_ready => Some(Command::Hold),
+                Some(enemy) => Some(Command::Attack(Attack::lock_on(enemy.clone()))),
+                None => None,
+            },
+        },
+    ]
+}
+
+/// Raiding comes before gathering, when there is anybody to raid.
+pub fn goblin_goals() -> Vec<Goal> {
+    vec![
+        Goal { name: "gather wood", priority: 0, conditions: vec![(Fact::WoodStored, true)] },
+        Goal { name: "raid", priority: 1, conditions: vec![(Fact::EnemyHurt, true)] },
+    ]
+}
+
+#[cfg(test)]
+mod test {
+    use crate::ai::fixture::Dummy;
+    use super::*;
+    use Fact::*;
+
+    fn names(actions: &[GoapAction<Dummy>], steps: Option<Vec<usize>>) -> Option<Vec<&'static str>> {
+        steps.map(|steps| steps.into_iter().map(|i| actions[i].name).collect())
+    }
+
+    #[test]
+    fn test_plan() {
+        let mut actions = goblin_actions::<Dummy>();
+        let stored = [(WoodStored, true)];
+        let start = WorldState::new(&[TreeInSight]);
+        assert_eq!(names(&actions, plan(&actions, &start, &stored)), Some(vec!["go to tree", "chop", "go home", "drop wood"]));
+        let carrying = WorldState::new(&[HasWood, NearTree]);
+        assert_eq!(names(&actions, plan(&actions, &carrying, &stored)), Some(vec!["go home", "drop wood"]));
+        assert_eq!(plan(&actions, &WorldState::new(&[]), &stored), None);
+        assert_eq!(plan(&actions, &WorldState::new(&[WoodStored]), &stored), Some(vec![]));
+
+        // buying is a way too, but dearer than chopping
+        actions.push(GoapAction {
+            name: "buy wood",
+            cost: 8.0,
+            preconditions: vec![(NearHome, true)],
+            effects: vec![(HasWood, true)],
+            instant: false,
+            command: |_| Some(Command::Hold),
+        });
+        let home = WorldState::new(&[TreeInSight, NearHome]);
+        assert_eq!(names(&actions, plan(&actions, &home, &stored)), Some(vec!["go to tree", "chop", "go home", "drop wood"]));
+        let no_trees = WorldState::new(&[NearHome]);
+        assert_eq!(names(&actions, plan(&actions, &no_trees, &stored)), Some(vec!["buy wood", "drop wood"]));
+    }
+
+    #[test]
+    fn test_replan() {
+        let mut planner = Planner::new(goblin_actions::<Dummy>(), goblin_goals());
+        let tree = Vector2::new(100.0, 0.0);
+        let enemy = Dummy(Vector2::new(0.0, 50.0));
+        let mut situation = Situation { position: Vector2::ZERO, home: Vector2::ZERO, tree: Some(tree), enemy: None, attack_ready: true };
+
+        let mut world = WorldState::new(&[TreeInSight, NearHome]);
+        assert_eq!(planner.update(&world, &situation), Command::Move(Move::To(tree)));
+        assert_eq!(planner.goal(), Some("gather wood"));
+        assert_eq!(planner.steps().collect::<Vec<_>>(), vec!["go to tree", "chop", "go home", "drop wood"]);
+
+        // arrived, chop until the wood is in hand
+        world = WorldState::new(&[TreeInSight, NearTree]);
+        situation.position = Vector2::new(90.0, 0.0);
+        assert_eq!(planner.update(&world, &situation), Command::Attack(Attack::towards(Vector2::new(10.0, 0.0))));
+        situation.attack_ready = false;
+        assert_eq!(planner.update(&world, &situation), Command::Hold);
+        world.set(HasWood, true);
+        assert_eq!(planner.update(&world, &situation), Command::Move(Move::To(situation.home)));
+
+        // somebody shows up, raiding comes first
+        world.set(EnemyVisible, true);
+        situation.enemy = Some(enemy.clone());
+        situation.attack_ready = true;
+        assert_eq!(planner.update(&world, &situation), Command::Move(Move::To(enemy.0)));
+        assert_eq!(planner.goal(), Some("raid"));
+        world.set(NearEnemy, true);
+        assert_eq!(planner.update(&world, &situation), Command::Attack(Attack::lock_on(enemy)));
+
+        // gone again, carry on home with the wood
+        world.set(EnemyVisible, false);
+        world.set(NearEnemy, false);
+        situation.enemy = None;
+        assert_eq!(planner.update(&world, &situation), Command::Move(Move::To(situation.home)));
+        assert_eq!(planner.goal(), Some("gather wood"));
+    }
+}
@@ -1,2 +1,9 @@
-mod behavior;
-mod command;
\ No newline at end of file
+pub mod behavior;
+pub mod command;
+#[cfg(test)]
+pub(crate) mod fixture;
+pub mod goap;
+pub mod perception;
+pub mod skill;
+pub mod threat;
+pub mod tree;
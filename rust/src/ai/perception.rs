@@ -0,0 +1,137 @@
+use godot::prelude::*;
+use crate::ai::behavior::Target;
+
+/// Seconds a character is remembered after it was last seen.
+pub const DEFAULT_FORGET_AFTER: f64 = 10.0;
+/// Seconds of movement guessed ahead from where a character was last seen. Guessing further is
+/// mostly wrong.
+const MAX_PREDICTION: f64 = 1.0;
+
+/// Where and when a character was last seen, and how it was moving at the time.
+#[derive(Debug, Clone)]
+pub struct Memory<T> {
+    pub character: T,
+    pub position: Vector2,
+    /// Pixels per second, from the last two sightings.
+    pub velocity: Vector2,
+    pub seen_at: f64,
+    /// Whether it was in sight as of the last update.
+    pub in_sight: bool,
+}
+
+impl<T> Memory<T> {
+    /// Seconds since it was last seen.
+    pub fn age(&self, time: f64) -> f64 {
+        (time - self.seen_at).max(0.0)
+    }
+
+    /// Where it would be by `time` if it kept going the way it did, for a short while at most.
+    pub fn expected_position(&self, time: f64) -> Vector2 {
+        self.position + self.velocity * self.age(time).min(MAX_PREDICTION) as real
+    }
+}
+
+/// Keeps track of the characters a unit has seen, so that losing sight of someone does not mean
+/// forgetting about them right away.
+pub struct PerceptionMemory<T = Gd<Node2D>> {
+    memories: Vec<Memory<T>>,
+    /// Seconds after which a character out of sight is forgotten.
+    pub forget_after: f64,
+}
+
+impl<T: Target> PerceptionMemory<T> {
+    pub fn new(forget_after: f64) -> Self {
+        PerceptionMemory { memories: Vec::new(), forget_after }
+    }
+
+    pub fn memories(&self) -> &[Memory<T>] {
+        &self.memories
+    }
+
+    pub fn recall(&self, character: &T) -> Option<&Memory<T>> {
+        self.memories.iter().find(|memory| memory.character.is_same(character))
+    }
+
+    /// Those remembered but out of sight, most recently seen first.
+    pub fn lost(&self) -> Vec<Memory<T>> {
+        let mut lost: Vec<Memory<T>> = self.memories.iter().filter(|memory| !memory.in_sight).cloned().collect();
+        lost.sort_by(|a, b| b.seen_at.total_cmp(&a.seen_at));
+        lost
+    }
+
+    pub fn forget(&mut self, character: &T) {
+        self.memories.retain(|memory| !memory.character.is_same(character));
+    }
+
+    /// Forgets everyone `keep` rejects, e.g. characters that died while out of sight.
+    pub fn retain(&mut self, keep: impl Fn(&T) -> bool) {
+        self.memories.retain(|memory| keep(&memory.character));
+    }
+
+    /// Takes note of everyone `in_sight` at `time` and forgets those not seen for too long.
+    pub fn update(&mut self, in_sight: &[T], time: f64) {
+        self.memories.iter_mut().for_each(|memory| memory.in_sight = false);
+        for character in in_sight {
+            let position = character.position();
+            match self.memories.iter_mut().find(|memory| memory.character.is_same(character)) {
+                Some(memory) => {
+                    let elapsed = time - memory.seen_at;
+                    if elapsed > 0.0 {
+                        memory.velocity = (position - memory.position) / elapsed as real;
+                    }
+                    memory.position = position;
+                    memory.seen_at = time;
+                    memory.in_sight = true;
+                }
+                None => self.memories.push(Memory {
+                    character: character.clone(),
+                    position,
+                    velocity: Vector2::ZERO,
+                    seen_at: time,
+                    in_sight: true,
+                }),
+            }
+        }
+        let forget_after = self.forget_after;
+        self.memories.retain(|memory| memory.in_sight || memory.age(time) <= forget_after);
+    }
+}
+
+impl<T: Target> Default for PerceptionMemory<T> {
+    fn default() -> Self {
+        PerceptionMemory::new(DEFAULT_FORGET_AFTER)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::ai::fixture::Tagged;
+    use super::*;
+
+    #[test]
+    fn test_memory() {
+        let mut memory = PerceptionMemory::new(5.0);
+        memory.update(&[Tagged(1, Vector2::new(0.0, 0.0)), Tagged(2, Vector2::new(50.0, 0.0))], 0.0);
+        assert!(memory.lost().is_empty());
+
+        memory.update(&[Tagged(1, Vector2::new(10.0, 0.0))], 0.5);
+        let lost = memory.lost();
+        assert_eq!(lost.iter().map(|m| m.character.0).collect::<Vec<_>>(), vec![2]);
+        assert_eq!(lost[0].position, Vector2::new(50.0, 0.0));
+
+        // gone from sight, but where it was heading is remembered
+        memory.update(&[], 1.0);
+        let fled = memory.recall(&Tagged(1, Vector2::ZERO)).unwrap();
+        assert!(!fled.in_sight);
+        assert_eq!(fled.seen_at, 0.5);
+        assert_eq!(fled.velocity, Vector2::new(20.0, 0.0));
+        assert_eq!(fled.expected_position(1.0), Vector2::new(20.0, 0.0));
+        assert_eq!(fled.expected_position(10.0), Vector2::new(30.0, 0.0));
+        assert_eq!(memory.lost().iter().map(|m| m.character.0).collect::<Vec<_>>(), vec![1, 2]);
+
+        memory.update(&[], 5.2);
+        assert_eq!(memory.memories().iter().map(|m| m.character.0).collect::<Vec<_>>(), vec![1]);
+        memory.update(&[], 5.6);
+        assert!(memory.memories().is_empty());
+    }
+}
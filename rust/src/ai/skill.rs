@@ -0,0 +1,470 @@
+use std::fmt::{Display, Formatter};
+use godot::prelude::*;
+use rand::Rng;
+use serde::{Deserialize, Serialize};
+use crate::ai::behavior::{CrowdControlSkill, DefensiveSkill, ElementalSkill, OffensiveSkill, Skill, SkillType, Target, UtilitySkill};
+use crate::ai::command::AttackTarget;
+use crate::dnd::condition::{Condition, ConditionType, ROUND_SECONDS};
+use crate::interactable::effect::{self, Damage, Effect, Heal};
+
+/// Who or where a skill can be aimed at. Ranges and radii are in pixels.
+#[derive(Debug, Clone, Copy, PartialEq, Default, Serialize, Deserialize)]
+pub enum Targeting {
+    /// The caster itself, e.g. a heal or a buff.
+    #[default]
+    Caster,
+    /// One character within `range`.
+    Single { range: real },
+    /// Everyone in a `width` wide line reaching `range` out, a miss still fires.
+    Direction { range: real, width: real },
+    /// Everyone within `radius` of a point up to `range` away.
+    Area { range: real, radius: real },
+}
+
+impl Targeting {
+    /// Where a skill aimed with `aim` lands and whom it hits among `candidates`.
+    pub fn resolve<T: Target>(&self, caster: Vector2, aim: &AttackTarget<T>, candidates: &[T]) -> Result<(Vector2, Vec<T>), SkillError> {
+        match *self {
+            Targeting::Caster => Ok((caster, Vec::new())),
+            Targeting::Single { range } => {
+                let target = match aim {
+                    AttackTarget::LockOn(target) => Some(target.clone()),
+                    // the closest one roughly that way
+                    AttackTarget::Direction(direction) if direction.length() > 0.0 => candidates.iter()
+                        .filter(|c| {
+                            let offset = c.position() - caster;
+                            offset.length() <= range && offset.normalized().dot(direction.normalized()) > 0.5
+                        })
+                        .min_by(|a, b| a.position().distance_to(caster).total_cmp(&b.position().distance_to(caster)))
+                        .cloned(),
+                    AttackTarget::Direction(_) => None,
+                    AttackTarget::Point(point) => candidates.iter()
+                        .min_by(|a, b| a.position().distance_to(*point).total_cmp(&b.position().distance_to(*point)))
+                        .cloned(),
+                };
+                let target = target.ok_or(SkillError::NoTarget)?;
+                let distance = target.position().distance_to(caster);
+                if distance > range {
+                    return Err(SkillError::OutOfRange { distance, range })
+                }
+                Ok((target.position(), vec![target]))
+            }
+            Targeting::Direction { range, width } => {
+                let direction = aim_point(caster, aim) - caster;
+                if direction.length() == 0.0 {
+                    return Err(SkillError::NoTarget)
+                }
+                let direction = direction.normalized();
+                let targets = candidates.iter()
+                    .filter(|c| {
+                        let offset = c.position() - caster;
+                        let along = offset.dot(direction);
+                        (0.0..=range).contains(&along) && (offset - direction * along).length() <= width / 2.0
+                    })
+                    .cloned()
+                    .collect();
+                Ok((caster + direction * range, targets))
+            }
+            Targeting::Area { range, radius } => {
+                let center = match aim {
+                    AttackTarget::Direction(direction) if direction.length() > 0.0 => caster + direction.normalized() * range,
+                    AttackTarget::Direction(_) => return Err(SkillError::NoTarget),
+                    _ => aim_point(caster, aim),
+                };
+                let distance = center.distance_to(caster);
+                if distance > range {
+                    return Err(SkillError::OutOfRange { distance, range })
+                }
+                Ok((center, within(candidates, center, radius)))
+            }
+        }
+    }
+}
+
+fn aim_point<T: Target>(caster: Vector2, aim: &AttackTarget<T>) -> Vector2 {
+    match aim {
+        AttackTarget::LockOn(target) => target.position(),
+        AttackTarget::Direction(direction) => caster + *direction,
+        AttackTarget::Point(point) => *point,
+    }
+}
+
+fn within<T: Target>(candidates: &[T], center: Vector2, radius: real) -> Vec<T> {
+    candidates.iter().filter(|c| c.position().distance_to(center) <= radius).cloned().collect()
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum SkillError {
+    UnknownSkill(String),
+    CoolingDown { remaining: f64 },
+    OutOfRange { distance: real, range: real },
+    NoTarget,
+    /// Another skill is still being cast.
+    Casting,
+    /// The skill does something nothing in the game can take effect from yet, e.g. buffs.
+    Unsupported(String),
+    /// The caster is silenced or otherwise in no state to use skills.
+    Silenced,
+}
+
+impl Display for SkillError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            SkillError::UnknownSkill(name) => write!(f, "unknown skill {}", name),
+            SkillError::CoolingDown { remaining } => write!(f, "ready again in {:.1}s", remaining),
+            SkillError::OutOfRange { distance, range } => write!(f, "target is {:.0} away, out of range {:.0}", distance, range),
+            SkillError::NoTarget => write!(f, "no target"),
+            SkillError::Casting => write!(f, "already casting"),
+            SkillError::Unsupported(what) => write!(f, "{} is not supported", what),
+            SkillError::Silenced => write!(f, "cannot use skills right now"),
+        }
+    }
+}
+
+impl std::error::Error for SkillError {}
+
+/// Something a skill puts into the world, e.g. a summoned creature or a trap.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Spawn {
+    /// Scene path of what to spawn.
+    pub scene: String,
+    pub position: Vector2,
+}
+
+/// A cast that got past its cool down and targeting, ready to be delivered once its cast time is
+/// up.
+#[derive(Debug, Clone)]
+pub struct SkillCast<T> {
+    pub skill: String,
+    pub cast_time: f64,
+    pub origin: Vector2,
+    /// Where the skill lands.
+    pub point: Vector2,
+    pub targets: Vec<T>,
+    /// What every target suffers.
+    pub on_targets: Vec<Effect>,
+    /// What the caster gets, e.g. healing or drained health.
+    pub on_caster: Vec<Effect>,
+    pub spawns: Vec<Spawn>,
+    /// Where the caster ends up after teleporting.
+    pub teleport: Option<Vector2>,
+    /// How far targets are pushed away from the caster.
+    pub knockback: real,
+}
+
+impl<T: Target> SkillCast<T> {
+    fn new(skill: &Skill, origin: Vector2, point: Vector2, targets: Vec<T>) -> Self {
+        SkillCast {
+            skill: skill.name.clone(),
+            cast_time: skill.cast_time,
+            origin,
+            point,
+            targets,
+            on_targets: Vec::new(),
+            on_caster: Vec::new(),
+            spawns: Vec::new(),
+            teleport: None,
+            knockback: 0.0,
+        }
+    }
+
+    /// Splash damage also hits whoever stands within `radius` of where the skill lands.
+    fn splash(&mut self, candidates: &[T], radius: real) {
+        for c in within(candidates, self.point, radius) {
+            if !self.targets.iter().any(|t| t.is_same(&c)) {
+                self.targets.push(c);
+            }
+        }
+    }
+
+    /// Lightning jumps on to the `count` characters closest to the first one hit.
+    fn chain(&mut self, candidates: &[T], count: usize) {
+        let Some(first) = self.targets.first().map(Target::position) else { return };
+        let mut others: Vec<&T> = candidates.iter().filter(|c| !self.targets.iter().any(|t| t.is_same(c))).collect();
+        others.sort_by(|a, b| a.position().distance_to(first).total_cmp(&b.position().distance_to(first)));
+        self.targets.extend(others.into_iter().take(count).cloned());
+    }
+
+    /// Seconds are rounded down for effects that only count whole seconds. Fails for the kinds of
+    /// skill that would have no effect.
+    fn apply<R: Rng + ?Sized>(&mut self, skill_type: &SkillType, on_caster: bool, rng: &mut R) -> Result<(), SkillError> {
+        let mut effects = Vec::new();
+        let damage = |amount: i32| Effect::Damage(Damage { amount });
+        match skill_type {
+            SkillType::Offensive(skill) => effects.push(damage(match *skill {
+                OffensiveSkill::DirectDamage { damage } => damage,
+                OffensiveSkill::AreaOfEffect { damage, .. } => damage,
+                OffensiveSkill::PiercingAttack { damage, .. } => damage,
+                // effects cannot tick yet, so every tick lands at once
+                OffensiveSkill::DamageOverTime { damage, duration, tick_rate } if tick_rate > 0.0 =>
+                    damage * ((duration as real / tick_rate) as i32).max(1),
+                OffensiveSkill::DamageOverTime { damage, .. } => damage,
+                OffensiveSkill::CriticalStrike { damage, crit_chance } if rng.gen::<real>() < crit_chance => damage * 2,
+                OffensiveSkill::CriticalStrike { damage, .. } => damage,
+            })),
+            SkillType::Defensive(DefensiveSkill::Healing { heal_amount }) => effects.push(Effect::Heal(Heal { amount: *heal_amount })),
+            // nobody keeps track of shields and buffs yet
+            SkillType::Defensive(skill) => return Err(SkillError::Unsupported(format!("{:?}", skill))),
+            SkillType::Utility(skill) => match skill {
+                UtilitySkill::Buffing { .. } | UtilitySkill::Debuffing { .. } =>
+                    return Err(SkillError::Unsupported(format!("{:?}", skill))),
+                UtilitySkill::Summoning { summon_id: scene } | UtilitySkill::TrapSetting { trap_id: scene } =>
+                    self.spawns.push(Spawn { scene: scene.clone(), position: self.point }),
+                UtilitySkill::Teleportation { distance } => {
+                    let direction = self.point - self.origin;
+                    if direction.length() > 0.0 {
+                        self.teleport = Some(self.origin + direction.normalized() * direction.length().min(*distance));
+                    }
+                }
+            },
+            SkillType::CrowdControl(skill) => {
+                effects.extend(skill.condition().map(Effect::Condition));
+                match *skill {
+                    CrowdControlSkill::Knockback { distance } => self.knockback += distance,
+                    CrowdControlSkill::Taunt { duration } => effects.push(Effect::Taunt(effect::Taunt { duration })),
+                    _ => {}
+                }
+            }
+            SkillType::Elemental(skill) => match *skill {
+                ElementalSkill::Fire { damage: amount, .. } | ElementalSkill::Lightning { damage: amount, .. } =>
+                    effects.push(damage(amount)),
+                ElementalSkill::Ice { damage: amount, .. } => {
+                    effects.push(damage(amount));
+                    effects.push(Effect::Condition(Condition::for_seconds(ConditionType::Slowed, ROUND_SECONDS)));
+                }
+                ElementalSkill::Earth { damage: amount, entangle_duration } => {
+                    effects.push(damage(amount));
+                    effects.push(Effect::Condition(Condition::for_seconds(ConditionType::Restrained, entangle_duration as f64)));
+                }
+                ElementalSkill::Dark { damage: amount, drain_health } => {
+                    effects.push(damage(amount));
+                    if drain_health && !on_caster {
+                        self.on_caster.push(Effect::Heal(Heal { amount: amount * self.targets.len() as i32 }));
+                    }
+                }
+                ElementalSkill::Arcane { damage: amount, warp_distance } => {
+                    effects.push(damage(amount));
+                    self.knockback += warp_distance;
+                }
+            },
+        }
+        if on_caster {
+            self.on_caster.extend(effects);
+        } else {
+            self.on_targets.extend(effects);
+        }
+        Ok(())
+    }
+}
+
+/// The skills a character knows and their cool downs.
+#[derive(Debug, Clone, Default)]
+pub struct SkillBook {
+    skills: Vec<Skill>,
+}
+
+impl SkillBook {
+    /// Every skill starts out ready.
+    pub fn new(skills: Vec<Skill>) -> Self {
+        let mut book = SkillBook::default();
+        skills.into_iter().for_each(|skill| book.learn(skill));
+        book
+    }
+
+    pub fn learn(&mut self, mut skill: Skill) {
+        skill.elapsed = skill.cooldown;
+        self.skills.retain(|s| !s.name.eq_ignore_ascii_case(&skill.name));
+        self.skills.push(skill);
+    }
+
+    pub fn skills(&self) -> &[Skill] {
+        &self.skills
+    }
+
+    /// Looks a skill up by name, ignoring case.
+    pub fn skill(&self, name: &str) -> Option<&Skill> {
+        self.skills.iter().find(|s| s.name.eq_ignore_ascii_case(name))
+    }
+
+    pub fn tick(&mut self, delta: f64) {
+        for skill in &mut self.skills {
+            skill.elapsed += delta;
+        }
+    }
+
+    /// Casts the skill called `name` from `caster` at `aim`, hitting characters among `candidates`.
+    /// The cool down only starts over when the cast goes through.
+    pub fn cast<T: Target, R: Rng + ?Sized>(&mut self, name: &str, caster: Vector2, aim: &AttackTarget<T>, candidates: &[T], rng: &mut R) -> Result<SkillCast<T>, SkillError> {
+        let skill = self.skills.iter_mut()
+            .find(|s| s.name.eq_ignore_ascii_case(name))
+            .ok_or_else(|| SkillError::UnknownSkill(name.to_string()))?;
+        if !skill.is_ready() {
+            return Err(SkillError::CoolingDown { remaining: skill.remaining_cooldown() })
+        }
+        let (point, targets) = skill.targeting.resolve(caster, aim, candidates)?;
+        let mut cast = SkillCast::new(skill, caster, point, targets);
+        let on_caster = skill.targeting == Targeting::Caster;
+        if !on_caster {
+            for skill_type in &skill.skill_types {
+                match *skill_type {
+                    SkillType::Offensive(OffensiveSkill::AreaOfEffect { radius, .. }) => cast.splash(candidates, radius),
+                    SkillType::Elemental(ElementalSkill::Fire { area, .. }) => cast.splash(candidates, area),
+                    SkillType::Elemental(ElementalSkill::Lightning { chain_targets, .. }) => cast.chain(candidates, chain_targets.max(0) as usize),
+                    _ => {}
+                }
+            }
+        }
+        for skill_type in &skill.skill_types {
+            cast.apply(skill_type, on_caster, rng)?;
+        }
+        skill.elapsed = 0.0;
+        Ok(cast)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use rand::rngs::StdRng;
+    use rand::SeedableRng;
+    use crate::ai::fixture::Tagged;
+    use super::*;
+
+    fn skill(name: &str, targeting: Targeting, skill_types: Vec<SkillType>) -> Skill {
+        Skill {
+            name: name.to_string(),
+            description: String::new(),
+            skill_types,
+            targeting,
+            cooldown: 5.0,
+            cast_time: 0.5,
+            elapsed: 0.0,
+        }
+    }
+
+    fn ids(cast: &SkillCast<Tagged>) -> Vec<u32> {
+        cast.targets.iter().map(|t| t.0).collect()
+    }
+
+    #[test]
+    fn test_targeting() {
+        let caster = Vector2::ZERO;
+        let candidates = [
+            Tagged(1, Vector2::new(50.0, 0.0)),
+            Tagged(2, Vector2::new(150.0, 5.0)),
+            Tagged(3, Vector2::new(0.0, 60.0)),
+            Tagged(4, Vector2::new(160.0, 0.0)),
+        ];
+        let targets = |targeting: Targeting, aim: AttackTarget<Tagged>| targeting.resolve(caster, &aim, &candidates)
+            .map(|(_, targets)| targets.iter().map(|t| t.0).collect::<Vec<_>>());
+
+        assert_eq!(targets(Targeting::Caster, AttackTarget::Direction(Vector2::RIGHT)), Ok(vec![]));
+        let single = Targeting::Single { range: 100.0 };
+        assert_eq!(targets(single, AttackTarget::Direction(Vector2::RIGHT)), Ok(vec![1]));
+        assert_eq!(targets(single, AttackTarget::Direction(Vector2::DOWN)), Ok(vec![3]));
+        assert_eq!(targets(single, AttackTarget::Direction(Vector2::LEFT)), Err(SkillError::NoTarget));
+        assert_eq!(targets(single, AttackTarget::LockOn(candidates[1].clone())), Err(SkillError::OutOfRange { distance: candidates[1].1.length(), range: 100.0 }));
+
+        let line = Targeting::Direction { range: 155.0, width: 20.0 };
+        assert_eq!(targets(line, AttackTarget::Direction(Vector2::RIGHT)), Ok(vec![1, 2]));
+        assert_eq!(targets(line, AttackTarget::Direction(Vector2::LEFT)), Ok(vec![]));
+
+        let area = Targeting::Area { range: 200.0, radius: 15.0 };
+        assert_eq!(targets(area, AttackTarget::Point(Vector2::new(155.0, 0.0))), Ok(vec![2, 4]));
+        assert_eq!(targets(area, AttackTarget::Point(Vector2::new(300.0, 0.0))), Err(SkillError::OutOfRange { distance: 300.0, range: 200.0 }));
+    }
+
+    #[test]
+    fn test_cooldown() {
+        let mut rng = StdRng::seed_from_u64(1);
+        let heal = skill("Second Wind", Targeting::Caster, vec![SkillType::Defensive(DefensiveSkill::Healing { heal_amount: 10 })]);
+        let mut book = SkillBook::new(vec![heal]);
+        let aim = AttackTarget::<Tagged>::Direction(Vector2::ZERO);
+
+        let cast = book.cast("second wind", Vector2::ZERO, &aim, &[], &mut rng).unwrap();
+        assert!(cast.targets.is_empty());
+        assert!(matches!(cast.on_caster[..], [Effect::Heal(Heal { amount: 10 })]));
+        assert_eq!(book.cast("Second Wind", Vector2::ZERO, &aim, &[], &mut rng).unwrap_err(), SkillError::CoolingDown { remaining: 5.0 });
+        book.tick(3.0);
+        assert_eq!(book.skill("Second Wind").unwrap().remaining_cooldown(), 2.0);
+        book.tick(2.0);
+        assert!(book.cast("Second Wind", Vector2::ZERO, &aim, &[], &mut rng).is_ok());
+        assert_eq!(book.cast("Fireball", Vector2::ZERO, &aim, &[], &mut rng).unwrap_err(), SkillError::UnknownSkill("Fireball".to_string()));
+
+        // better no cast than one that does nothing, and no cool down for it either
+        book.learn(skill("Iron Skin", Targeting::Caster, vec![SkillType::Defensive(DefensiveSkill::Shielding { shield_strength: 5 })]));
+        assert!(matches!(book.cast("Iron Skin", Vector2::ZERO, &aim, &[], &mut rng), Err(SkillError::Unsupported(_))));
+        assert!(book.skill("Iron Skin").unwrap().is_ready());
+    }
+
+    #[test]
+    fn test_allegiance() {
+        use crate::dnd::alignment::{Alignment, Ethical, Moral};
+        use crate::dnd::catalog::Catalog;
+        use crate::dnd::reaction::Disposition;
+
+        let mut rng = StdRng::seed_from_u64(1);
+        let catalog = Catalog::builtin();
+        let goblin = Disposition::new(Alignment::new(Moral::Evil, Ethical::Neutral), Some("Goblins".to_string()));
+        let knight = Disposition::new(Alignment::new(Moral::Good, Ethical::Lawful), Some("Kingdom".to_string()));
+        let around = [(Tagged(1, Vector2::new(30.0, 0.0)), goblin.clone()), (Tagged(2, Vector2::new(60.0, 0.0)), knight)];
+        let candidates = |skill: &Skill| -> Vec<Tagged> {
+            around.iter()
+                .filter(|(_, other)| skill.reaches(goblin.attitude_in(&catalog, other)))
+                .map(|(dummy, _)| dummy.clone())
+                .collect()
+        };
+        let dart = catalog.skill("Poison Dart").unwrap().clone();
+        let mend = skill("Mend", Targeting::Area { range: 100.0, radius: 20.0 }, vec![SkillType::Defensive(DefensiveSkill::Healing { heal_amount: 5 })]);
+        let mut book = SkillBook::new(vec![dart.clone(), mend.clone()]);
+
+        // the dart flies past the goblin in the way, the mending reaches it alone
+        let cast = book.cast("Poison Dart", Vector2::ZERO, &AttackTarget::Direction(Vector2::RIGHT), &candidates(&dart), &mut rng).unwrap();
+        assert_eq!(ids(&cast), vec![2]);
+        let cast = book.cast("Mend", Vector2::ZERO, &AttackTarget::Point(Vector2::new(45.0, 0.0)), &candidates(&mend), &mut rng).unwrap();
+        assert_eq!(ids(&cast), vec![1]);
+    }
+
+    #[test]
+    fn test_effects() {
+        let mut rng = StdRng::seed_from_u64(1);
+        let candidates = [
+            Tagged(1, Vector2::new(50.0, 0.0)),
+            Tagged(2, Vector2::new(60.0, 0.0)),
+            Tagged(3, Vector2::new(100.0, 0.0)),
+        ];
+        let mut book = SkillBook::new(vec![
+            skill("Fireball", Targeting::Single { range: 100.0 }, vec![SkillType::Elemental(ElementalSkill::Fire { damage: 8, area: 20.0 })]),
+            skill("Chain Lightning", Targeting::Single { range: 100.0 }, vec![SkillType::Elemental(ElementalSkill::Lightning { damage: 6, chain_targets: 1 })]),
+            skill("Drain", Targeting::Single { range: 100.0 }, vec![SkillType::Elemental(ElementalSkill::Dark { damage: 4, drain_health: true })]),
+            skill("Shield Bash", Targeting::Single { range: 100.0 }, vec![
+                SkillType::Offensive(OffensiveSkill::DirectDamage { damage: 3 }),
+                SkillType::CrowdControl(CrowdControlSkill::Knockback { distance: 30.0 }),
+            ]),
+            skill("Taunt", Targeting::Single { range: 100.0 }, vec![SkillType::CrowdControl(CrowdControlSkill::Taunt { duration: 4 })]),
+            skill("Hamstring", Targeting::Single { range: 100.0 }, vec![SkillType::CrowdControl(CrowdControlSkill::Slow { duration: 3 })]),
+            skill("Blink", Targeting::Area { range: 200.0, radius: 0.0 }, vec![SkillType::Utility(UtilitySkill::Teleportation { distance: 80.0 })]),
+            skill("Bear Trap", Targeting::Area { range: 200.0, radius: 0.0 }, vec![SkillType::Utility(UtilitySkill::TrapSetting { trap_id: "res://trap.tscn".to_string() })]),
+        ]);
+        let aim = AttackTarget::LockOn(candidates[0].clone());
+        let mut cast = |name: &str, aim: &AttackTarget<Tagged>| book.cast(name, Vector2::ZERO, aim, &candidates, &mut rng).unwrap();
+
+        let fireball = cast("Fireball", &aim);
+        assert_eq!(ids(&fireball), vec![1, 2]);
+        assert!(matches!(fireball.on_targets[..], [Effect::Damage(Damage { amount: 8 })]));
+
+        assert_eq!(ids(&cast("Chain Lightning", &aim)), vec![1, 2]);
+
+        let drain = cast("Drain", &aim);
+        assert!(matches!(drain.on_caster[..], [Effect::Heal(Heal { amount: 4 })]));
+
+        let bash = cast("Shield Bash", &aim);
+        assert_eq!(bash.knockback, 30.0);
+        assert!(matches!(&bash.on_targets[..], [Effect::Damage(Damage { amount: 3 }), Effect::Condition(c)] if c.kind == ConditionType::Prone));
+        assert!(matches!(cast("Taunt", &aim).on_targets[..], [Effect::Taunt(effect::Taunt { duration: 4 })]));
+        assert!(matches!(&cast("Hamstring", &aim).on_targets[..], [Effect::Condition(c)] if c.kind == ConditionType::Slowed && c.remaining == Some(3.0)));
+
+        let point = AttackTarget::Point(Vector2::new(0.0, 100.0));
+        assert_eq!(cast("Blink", &point).teleport, Some(Vector2::new(0.0, 80.0)));
+        assert_eq!(cast("Bear Trap", &point).spawns, vec![Spawn { scene: "res://trap.tscn".to_string(), position: Vector2::new(0.0, 100.0) }]);
+    }
+}
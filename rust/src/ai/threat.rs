@@ -0,0 +1,188 @@
+use godot::prelude::*;
+use crate::ai::behavior::Target;
+
+/// Threat per point of damage dealt.
+pub const DAMAGE_THREAT: f32 = 1.0;
+/// Threat per hit point healed, healers draw attention as well.
+pub const HEALING_THREAT: f32 = 0.5;
+/// Share of its threat a character loses per second.
+pub const DEFAULT_DECAY: f32 = 0.1;
+/// Threat per second from standing right next to the unit.
+pub const DEFAULT_PROXIMITY_THREAT: f32 = 2.0;
+/// How much more threat than the current target another character needs to take over, as a
+/// share of the current target's threat.
+pub const DEFAULT_SWITCH_MARGIN: f32 = 0.1;
+/// Threat below which a character is forgotten.
+const MIN_THREAT: f32 = 0.01;
+
+/// Who a unit holds a grudge against and how much. Damage, healing and coming close all add
+/// threat, which fades over time; a taunt forces the target for a while.
+pub struct ThreatTable<T = Gd<Node2D>> {
+    entries: Vec<(T, f32)>,
+    pub decay: f32,
+    pub proximity_threat: f32,
+    /// Distance at which coming close stops adding threat, in pixels.
+    pub proximity_range: real,
+    pub switch_margin: f32,
+    /// Who taunted the unit and until when.
+    taunt: Option<(T, f64)>,
+    target: Option<T>,
+}
+
+impl<T: Target> ThreatTable<T> {
+    pub fn new(proximity_range: real) -> Self {
+        ThreatTable {
+            entries: Vec::new(),
+            decay: DEFAULT_DECAY,
+            proximity_threat: DEFAULT_PROXIMITY_THREAT,
+            proximity_range,
+            switch_margin: DEFAULT_SWITCH_MARGIN,
+            taunt: None,
+            target: None,
+        }
+    }
+
+    pub fn threat(&self, character: &T) -> f32 {
+        self.entries.iter().find(|(c, _)| c.is_same(character)).map_or(0.0, |&(_, threat)| threat)
+    }
+
+    /// Everyone on the table, most threatening first.
+    pub fn entries(&self) -> Vec<(&T, f32)> {
+        let mut entries: Vec<(&T, f32)> = self.entries.iter().map(|(c, threat)| (c, *threat)).collect();
+        entries.sort_by(|a, b| b.1.total_cmp(&a.1));
+        entries
+    }
+
+    /// Whom the unit goes after, as of the last update.
+    pub fn target(&self) -> Option<&T> {
+        self.target.as_ref()
+    }
+
+    pub fn add(&mut self, character: &T, threat: f32) {
+        match self.entries.iter_mut().find(|(c, _)| c.is_same(character)) {
+            Some((_, total)) => *total = (*total + threat).max(0.0),
+            None if threat > 0.0 => self.entries.push((character.clone(), threat)),
+            None => {}
+        }
+    }
+
+    pub fn add_damage(&mut self, attacker: &T, damage: i32) {
+        self.add(attacker, damage.max(0) as f32 * DAMAGE_THREAT);
+    }
+
+    pub fn add_healing(&mut self, healer: &T, healing: i32) {
+        self.add(healer, healing.max(0) as f32 * HEALING_THREAT);
+    }
+
+    /// Makes `character` the target until `until`, whatever the threat says.
+    pub fn taunt(&mut self, character: &T, until: f64) {
+        self.add(character, MIN_THREAT);
+        self.taunt = Some((character.clone(), until));
+        self.target = Some(character.clone());
+    }
+
+    /// Stops tracking the characters `keep` rejects, along with any taunt or target among them.
+    pub fn retain(&mut self, keep: impl Fn(&T) -> bool) {
+        self.entries.retain(|(c, _)| keep(c));
+        if self.taunt.as_ref().is_some_and(|(c, _)| !keep(c)) {
+            self.taunt = None;
+        }
+        if self.target.as_ref().is_some_and(|c| !keep(c)) {
+            self.target = None;
+        }
+    }
+
+    /// Lets threat fade for `delta` seconds, adds threat for the characters `nearby` and picks
+    /// the target anew. The target only changes to someone with clearly more threat, so that
+    /// close calls do not make the unit run back and forth.
+    pub fn update(&mut self, position: Vector2, nearby: &[T], time: f64, delta: f64) {
+        let kept = (-(self.decay as f64) * delta).exp() as f32;
+        self.entries.iter_mut().for_each(|(_, threat)| *threat *= kept);
+        if self.proximity_range > 0.0 {
+            for character in nearby {
+                let closeness = (1.0 - character.position().distance_to(position) / self.proximity_range).clamp(0.0, 1.0);
+                self.add(character, self.proximity_threat * closeness * delta as f32);
+            }
+        }
+        if self.taunt.as_ref().is_some_and(|&(_, until)| time >= until) {
+            self.taunt = None;
+        }
+        let taunter = self.taunt.as_ref().map(|(c, _)| c.clone());
+        self.entries.retain(|(c, threat)| *threat >= MIN_THREAT || taunter.as_ref().is_some_and(|t| t.is_same(c)));
+
+        if let Some(taunter) = taunter {
+            self.target = Some(taunter);
+            return
+        }
+        let top = self.entries.iter().max_by(|a, b| a.1.total_cmp(&b.1)).map(|(c, threat)| (c.clone(), *threat));
+        let current = self.target.as_ref().map(|target| self.threat(target)).filter(|&threat| threat > 0.0);
+        self.target = match (top, current) {
+            (Some((top, threat)), Some(current)) if threat <= current * (1.0 + self.switch_margin) => self.target.take(),
+            (top, _) => top.map(|(c, _)| c),
+        };
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::ai::fixture::Tagged;
+    use super::*;
+
+    fn target(table: &ThreatTable<Tagged>) -> Option<u32> {
+        table.target().map(|t| t.0)
+    }
+
+    #[test]
+    fn test_threat() {
+        let fighter = Tagged(1, Vector2::new(10.0, 0.0));
+        let archer = Tagged(2, Vector2::new(90.0, 0.0));
+        let healer = Tagged(3, Vector2::new(200.0, 0.0));
+        let mut table = ThreatTable::new(100.0);
+        let nearby = [fighter.clone(), archer.clone(), healer.clone()];
+
+        // whoever comes closest is noticed first
+        table.update(Vector2::ZERO, &nearby, 0.0, 1.0);
+        assert_eq!(target(&table), Some(1));
+        assert_eq!(table.threat(&healer), 0.0);
+
+        table.add_damage(&archer, 5);
+        table.update(Vector2::ZERO, &nearby, 1.0, 1.0);
+        assert_eq!(target(&table), Some(2));
+
+        // healing draws attention too, but it takes clearly more to pull the goblin off
+        table.add_healing(&healer, 9);
+        table.update(Vector2::ZERO, &nearby, 2.0, 0.0);
+        assert_eq!(target(&table), Some(2));
+        table.add_healing(&healer, 4);
+        table.update(Vector2::ZERO, &nearby, 3.0, 0.0);
+        assert_eq!(target(&table), Some(3));
+        assert_eq!(table.entries().iter().map(|(c, _)| c.0).collect::<Vec<_>>(), vec![3, 2, 1]);
+
+        // the dead are forgotten
+        table.retain(|c| c.0 != 3);
+        table.update(Vector2::ZERO, &nearby, 4.0, 0.0);
+        assert_eq!(target(&table), Some(2));
+    }
+
+    #[test]
+    fn test_taunt_and_decay() {
+        let fighter = Tagged(1, Vector2::new(500.0, 0.0));
+        let archer = Tagged(2, Vector2::new(500.0, 0.0));
+        let mut table = ThreatTable::new(100.0);
+
+        table.add_damage(&archer, 10);
+        table.taunt(&fighter, 5.0);
+        table.update(Vector2::ZERO, &[], 1.0, 1.0);
+        assert_eq!(target(&table), Some(1));
+        table.update(Vector2::ZERO, &[], 5.0, 1.0);
+        assert_eq!(target(&table), Some(2));
+
+        // out of sight, out of mind
+        let before = table.threat(&archer);
+        table.update(Vector2::ZERO, &[], 6.0, 1.0);
+        assert!(table.threat(&archer) < before);
+        table.update(Vector2::ZERO, &[], 106.0, 100.0);
+        assert_eq!(target(&table), None);
+        assert!(table.entries().is_empty());
+    }
+}
@@ -0,0 +1,749 @@
+use std::cell::RefCell;
+use std::collections::HashMap;
+use std::fmt::{Display, Formatter};
+use godot::prelude::*;
+use serde::{Deserialize, Serialize};
+use crate::ai::behavior::{Behavior, Environment, State, Strategy, Target};
+use crate::ai::command::{Attack, AttackTarget, Command, Move};
+
+/// How close to a position counts as there, in pixels, unless the tree says otherwise.
+pub const DEFAULT_ARRIVAL_DISTANCE: real = 4.0;
+
+/// What ticking a node came to.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Status {
+    Success,
+    Failure,
+    /// Not done yet, ticks resume where it left off.
+    Running,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Value<T = Gd<Node2D>> {
+    Flag(bool),
+    Number(f64),
+    Position(Vector2),
+    Path(Vec<Vector2>),
+    Target(T),
+}
+
+/// Memory the leaves of a tree share with each other and with the game, e.g. where home is.
+#[derive(Debug, Clone)]
+pub struct Blackboard<T = Gd<Node2D>> {
+    values: HashMap<String, Value<T>>,
+}
+
+impl<T> Default for Blackboard<T> {
+    fn default() -> Self {
+        Blackboard { values: HashMap::new() }
+    }
+}
+
+impl<T> Blackboard<T> {
+    pub fn set(&mut self, key: &str, value: Value<T>) {
+        self.values.insert(key.to_string(), value);
+    }
+
+    pub fn get(&self, key: &str) -> Option<&Value<T>> {
+        self.values.get(key)
+    }
+
+    pub fn remove(&mut self, key: &str) -> Option<Value<T>> {
+        self.values.remove(key)
+    }
+
+    /// Unset flags are down.
+    pub fn flag(&self, key: &str) -> bool {
+        matches!(self.get(key), Some(Value::Flag(true)))
+    }
+
+    pub fn position(&self, key: &str) -> Option<Vector2> {
+        match self.get(key) {
+            Some(Value::Position(position)) => Some(*position),
+            _ => None,
+        }
+    }
+
+    pub fn path(&self, key: &str) -> &[Vector2] {
+        match self.get(key) {
+            Some(Value::Path(path)) => path,
+            _ => &[],
+        }
+    }
+
+    pub fn target(&self, key: &str) -> Option<&T> {
+        match self.get(key) {
+            Some(Value::Target(target)) => Some(target),
+            _ => None,
+        }
+    }
+}
+
+/// What a leaf gets to see during a tick.
+pub struct Context<'a, T = Gd<Node2D>> {
+    pub state: &'a State,
+    pub env: &'a Environment<T>,
+    pub blackboard: &'a mut Blackboard<T>,
+    pub arrival_distance: real,
+    command: Option<Command<T>>,
+}
+
+impl<T> Context<'_, T> {
+    /// Hands a command to the character. The first one emitted during a tick is the one carried
+    /// out, commands from branches that end up failing are taken back.
+    pub fn emit(&mut self, command: Command<T>) {
+        self.command.get_or_insert(command);
+    }
+}
+
+/// A leaf written in code rather than described in data.
+pub trait Leaf<T = Gd<Node2D>> {
+    fn tick(&mut self, ctx: &mut Context<T>) -> Status;
+
+    /// Forgets any progress when the leaf is interrupted while running.
+    fn reset(&mut self) {}
+}
+
+/// Conditions a tree can test. They succeed or fail on the spot.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub enum Check {
+    EnemyInSight,
+    EnemyInAttackRange,
+    AllyInSight,
+    AttackReady,
+    /// Hit points below this fraction of the maximum.
+    HealthBelow(f32),
+    /// The flag stored under the key is up.
+    Flag(String),
+    /// Within `distance` of the position stored under `key`.
+    Near { key: String, distance: real },
+}
+
+impl Check {
+    fn test<T: Target>(&self, ctx: &Context<T>) -> bool {
+        match self {
+            Check::EnemyInSight => !ctx.env.characters_in_sight.is_empty(),
+            Check::EnemyInAttackRange => !ctx.env.characters_in_attack_range.is_empty(),
+            Check::AllyInSight => !ctx.env.allies_in_sight.is_empty(),
+            Check::AttackReady => ctx.state.attack_ready,
+            Check::HealthBelow(fraction) => ctx.state.attributes.hit_point_fraction() < *fraction,
+            Check::Flag(key) => ctx.blackboard.flag(key),
+            Check::Near { key, distance } => ctx.blackboard.position(key)
+                .is_some_and(|position| position.distance_to(ctx.state.position) <= *distance),
+        }
+    }
+}
+
+/// Things a tree can do, all but the waiting ones emit a [`Command`].
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub enum Task {
+    /// Goes after the closest enemy in sight, stored as `target`. Succeeds once it is in attack
+    /// range, fails without anybody in sight.
+    Chase,
+    /// Attacks the most threatening enemy in attack range, else the closest, holding while the
+    /// attack is not ready. Fails without anybody in range.
+    Attack,
+    /// Uses a skill on the closest enemy in sight.
+    Skill(String),
+    /// Walks to where the enemy that last got out of sight was headed. Succeeds once there, fails
+    /// with nobody remembered.
+    Investigate,
+    /// Walks to the position stored under the key.
+    MoveTo(String),
+    /// Walks the path stored under the key round and round, it never finishes.
+    Patrol(String),
+    Hold,
+    Stop,
+    /// Seconds to do nothing.
+    Wait(f64),
+    SetFlag(String, bool),
+}
+
+/// Remembers how far a [`Task`] has got.
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct Progress {
+    waypoint: usize,
+    started: Option<f64>,
+}
+
+impl Task {
+    fn tick<T: Target>(&self, progress: &mut Progress, ctx: &mut Context<T>) -> Status {
+        match self {
+            Task::Chase => {
+                let Some(enemy) = ctx.env.characters_in_sight.first() else {
+                    return Status::Failure
+                };
+                ctx.blackboard.set("target", Value::Target(enemy.clone()));
+                if ctx.env.characters_in_attack_range.iter().any(|c| c.is_same(enemy)) {
+                    return Status::Success
+                }
+                ctx.emit(Command::Move(Move::To(enemy.position())));
+                Status::Running
+            }
+            Task::Attack => {
+                let Some(enemy) = ctx.env.attack_target() else {
+                    return Status::Failure
+                };
+                if !ctx.state.attack_ready {
+                    ctx.emit(Command::Hold);
+                    return Status::Running
+                }
+                ctx.emit(Command::Attack(Attack::lock_on(enemy.clone())));
+                Status::Success
+            }
+            Task::Skill(name) => {
+                let Some(enemy) = ctx.env.characters_in_sight.first() else {
+                    return Status::Failure
+                };
+                ctx.emit(Command::Skill { name: name.clone(), target: AttackTarget::LockOn(enemy.clone()) });
+                Status::Success
+            }
+            Task::Investigate => {
+                let Some(memory) = ctx.env.characters_remembered.first() else {
+                    return Status::Failure
+                };
+                let last_known = memory.expected_position(ctx.env.time);
+                if last_known.distance_to(ctx.state.position) <= ctx.arrival_distance {
+                    return Status::Success
+                }
+                ctx.emit(Command::Move(Move::To(last_known)));
+                Status::Running
+            }
+            Task::MoveTo(key) => {
+                let Some(destination) = ctx.blackboard.position(key) else {
+                    return Status::Failure
+                };
+                if destination.distance_to(ctx.state.position) <= ctx.arrival_distance {
+                    return Status::Success
+                }
+                ctx.emit(Command::Move(Move::To(destination)));
+                Status::Running
+            }
+            Task::Patrol(key) => {
+                let path = ctx.blackboard.path(key);
+                if path.is_empty() {
+                    return Status::Failure
+                }
+                let mut next = progress.waypoint % path.len();
+                if path[next].distance_to(ctx.state.position) <= ctx.arrival_distance {
+                    next = (next + 1) % path.len();
+                }
+                progress.waypoint = next;
+                let waypoint = path[next];
+                ctx.emit(Command::Move(Move::To(waypoint)));
+                Status::Running
+            }
+            Task::Hold => {
+                ctx.emit(Command::Hold);
+                Status::Success
+            }
+            Task::Stop => {
+                ctx.emit(Command::Stop);
+                Status::Success
+            }
+            Task::Wait(seconds) => {
+                let started = *progress.started.get_or_insert(ctx.env.time);
+                if ctx.env.time - started < *seconds {
+                    return Status::Running
+                }
+                progress.started = None;
+                Status::Success
+            }
+            Task::SetFlag(key, value) => {
+                ctx.blackboard.set(key, Value::Flag(*value));
+                Status::Success
+            }
+        }
+    }
+}
+
+/// A tree as written down in a data file.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub enum TreeData {
+    /// Runs its children one after another, failing as soon as one fails.
+    Sequence(Vec<TreeData>),
+    /// A sequence that starts over from the first child every tick, so that the checks in front
+    /// can stop a running child once they no longer hold.
+    ReactiveSequence(Vec<TreeData>),
+    /// Tries its children in order until one does not fail, starting over from the first every
+    /// tick so that a more important branch can take over from a running one.
+    Selector(Vec<TreeData>),
+    /// Runs all children at once until `success` of them succeeded, or too many failed for that.
+    Parallel { success: usize, children: Vec<TreeData> },
+    /// Swaps success and failure.
+    Inverter(Box<TreeData>),
+    /// Fails for `seconds` after the child succeeded.
+    Cooldown { seconds: f64, child: Box<TreeData> },
+    /// Fails the child once it has been running for `seconds`.
+    Timeout { seconds: f64, child: Box<TreeData> },
+    Check(Check),
+    Task(Task),
+}
+
+impl TreeData {
+    fn validate(&self) -> Result<(), TreeError> {
+        match self {
+            TreeData::Sequence(children) | TreeData::ReactiveSequence(children) | TreeData::Selector(children) if children.is_empty() =>
+                Err(TreeError::Invalid("a sequence or selector without children")),
+            TreeData::Parallel { success, children } if *success == 0 || *success > children.len() =>
+                Err(TreeError::Invalid("a parallel that can never succeed")),
+            TreeData::Cooldown { seconds, .. } | TreeData::Timeout { seconds, .. } if seconds.is_nan() || *seconds < 0.0 =>
+                Err(TreeError::Invalid("a negative cool down or time out")),
+            TreeData::Sequence(children) | TreeData::ReactiveSequence(children) | TreeData::Selector(children) | TreeData::Parallel { children, .. } =>
+                children.iter().try_for_each(TreeData::validate),
+            TreeData::Inverter(child) | TreeData::Cooldown { child, .. } | TreeData::Timeout { child, .. } => child.validate(),
+            TreeData::Check(_) | TreeData::Task(_) => Ok(()),
+        }
+    }
+
+    fn build<T: Target>(&self) -> Node<T> {
+        let build_all = |children: &[TreeData]| children.iter().map(TreeData::build).collect();
+        match self {
+            TreeData::Sequence(children) => Node::sequence(build_all(children)),
+            TreeData::ReactiveSequence(children) => Node::reactive_sequence(build_all(children)),
+            TreeData::Selector(children) => Node::selector(build_all(children)),
+            TreeData::Parallel { success, children } => Node::parallel(*success, build_all(children)),
+            TreeData::Inverter(child) => Node::inverter(child.build()),
+            TreeData::Cooldown { seconds, child } => Node::cooldown(*seconds, child.build()),
+            TreeData::Timeout { seconds, child } => Node::timeout(*seconds, child.build()),
+            TreeData::Check(check) => Node::Check(check.clone()),
+            TreeData::Task(task) => Node::task(task.clone()),
+        }
+    }
+}
+
+#[derive(Debug)]
+pub enum TreeError {
+    Parse(ron::error::SpannedError),
+    Invalid(&'static str),
+}
+
+impl Display for TreeError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            TreeError::Parse(err) => write!(f, "failed to parse behavior tree: {}", err),
+            TreeError::Invalid(what) => write!(f, "behavior tree has {}", what),
+        }
+    }
+}
+
+impl std::error::Error for TreeError {}
+
+/// A node of a running tree. Composites and decorators keep track of where they are, build them
+/// with the functions below.
+pub enum Node<T = Gd<Node2D>> {
+    Sequence { children: Vec<Node<T>>, running: usize },
+    ReactiveSequence { children: Vec<Node<T>>, running: Option<usize> },
+    Selector { children: Vec<Node<T>>, running: Option<usize> },
+    Parallel { success: usize, children: Vec<Node<T>>, results: Vec<Option<Status>> },
+    Inverter(Box<Node<T>>),
+    Cooldown { seconds: f64, child: Box<Node<T>>, ready_at: f64 },
+    Timeout { seconds: f64, child: Box<Node<T>>, started: Option<f64> },
+    Check(Check),
+    Task { task: Task, progress: Progress },
+    Leaf(Box<dyn Leaf<T>>),
+}
+
+impl<T: Target> Node<T> {
+    pub fn sequence(children: Vec<Node<T>>) -> Self {
+        Node::Sequence { children, running: 0 }
+    }
+
+    pub fn reactive_sequence(children: Vec<Node<T>>) -> Self {
+        Node::ReactiveSequence { children, running: None }
+    }
+
+    pub fn selector(children: Vec<Node<T>>) -> Self {
+        Node::Selector { children, running: None }
+    }
+
+    pub fn parallel(success: usize, children: Vec<Node<T>>) -> Self {
+        Node::Parallel { success, results: vec![None; children.len()], children }
+    }
+
+    pub fn inverter(child: Node<T>) -> Self {
+        Node::Inverter(Box::new(child))
+    }
+
+    pub fn cooldown(seconds: f64, child: Node<T>) -> Self {
+        Node::Cooldown { seconds, child: Box::new(child), ready_at: f64::NEG_INFINITY }
+    }
+
+    pub fn timeout(seconds: f64, child: Node<T>) -> Self {
+        Node::Timeout { seconds, child: Box::new(child), started: None }
+    }
+
+    pub fn task(task: Task) -> Self {
+        Node::Task { task, progress: Progress::default() }
+    }
+
+    pub fn leaf(leaf: impl Leaf<T> + 'static) -> Self {
+        Node::Leaf(Box::new(leaf))
+    }
+
+    pub fn tick(&mut self, ctx: &mut Context<T>) -> Status {
+        match self {
+            Node::Sequence { children, running } => {
+                for (i, child) in children.iter_mut().enumerate().skip(*running) {
+                    match child.tick(ctx) {
+                        Status::Success => {}
+                        Status::Running => {
+                            *running = i;
+                            return Status::Running
+                        }
+                        Status::Failure => {
+                            *running = 0;
+                            return Status::Failure
+                        }
+                    }
+                }
+                *running = 0;
+                Status::Success
+            }
+            Node::ReactiveSequence { children, running } => {
+                for i in 0..children.len() {
+                    let status = children[i].tick(ctx);
+                    if status == Status::Success {
+                        continue
+                    }
+                    // a check in front no longer holds, or another child took over
+                    if let Some(previous) = running.filter(|&previous| previous != i) {
+                        children[previous].reset();
+                    }
+                    *running = (status == Status::Running).then_some(i);
+                    return status
+                }
+                *running = None;
+                Status::Success
+            }
+            Node::Selector { children, running } => {
+                for i in 0..children.len() {
+                    let emitted = ctx.command.is_some();
+                    let status = children[i].tick(ctx);
+                    if status == Status::Failure {
+                        if !emitted {
+                            ctx.command = None;
+                        }
+                        continue
+                    }
+                    // a more important branch took over
+                    if let Some(previous) = running.filter(|&previous| previous != i) {
+                        children[previous].reset();
+                    }
+                    *running = (status == Status::Running).then_some(i);
+                    return status
+                }
+                *running = None;
+                Status::Failure
+            }
+            Node::Parallel { success, children, results } => {
+                for (child, result) in children.iter_mut().zip(results.iter_mut()) {
+                    if result.is_none() {
+                        let status = child.tick(ctx);
+                        *result = (status != Status::Running).then_some(status);
+                    }
+                }
+                let succeeded = results.iter().filter(|&&r| r == Some(Status::Success)).count();
+                let failed = results.iter().filter(|&&r| r == Some(Status::Failure)).count();
+                let status = if succeeded >= *success {
+                    Status::Success
+                } else if children.len() - failed < *success {
+                    Status::Failure
+                } else {
+                    return Status::Running
+                };
+                self.reset();
+                status
+            }
+            Node::Inverter(child) => match child.tick(ctx) {
+                Status::Success => Status::Failure,
+                Status::Failure => Status::Success,
+                Status::Running => Status::Running,
+            },
+            Node::Cooldown { seconds, child, ready_at } => {
+                if ctx.env.time < *ready_at {
+                    return Status::Failure
+                }
+                let status = child.tick(ctx);
+                if status == Status::Success {
+                    *ready_at = ctx.env.time + *seconds;
+                }
+                status
+            }
+            Node::Timeout { seconds, child, started } => {
+                let since = *started.get_or_insert(ctx.env.time);
+                if ctx.env.time - since > *seconds {
+                    child.reset();
+                    *started = None;
+                    return Status::Failure
+                }
+                let status = child.tick(ctx);
+                if status != Status::Running {
+                    *started = None;
+                }
+                status
+            }
+            Node::Check(check) => if check.test(ctx) { Status::Success } else { Status::Failure },
+            Node::Task { task, progress } => task.tick(progress, ctx),
+            Node::Leaf(leaf) => leaf.tick(ctx),
+        }
+    }
+
+    /// Forgets what was running below. Cool downs keep counting.
+    pub fn reset(&mut self) {
+        match self {
+            Node::Sequence { children, running } => {
+                children.iter_mut().for_each(Node::reset);
+                *running = 0;
+            }
+            Node::ReactiveSequence { children, running } | Node::Selector { children, running } => {
+                children.iter_mut().for_each(Node::reset);
+                *running = None;
+            }
+            Node::Parallel { children, results, .. } => {
+                children.iter_mut().for_each(Node::reset);
+                results.iter_mut().for_each(|result| *result = None);
+            }
+            Node::Inverter(child) | Node::Cooldown { child, .. } => child.reset(),
+            Node::Timeout { child, started, .. } => {
+                child.reset();
+                *started = None;
+            }
+            Node::Check(_) => {}
+            // patrols pick up at the waypoint they were headed for
+            Node::Task { progress, .. } => progress.started = None,
+            Node::Leaf(leaf) => leaf.reset(),
+        }
+    }
+}
+
+/// A behavior tree with its blackboard, ticked once per frame or turn.
+pub struct BehaviorTree<T = Gd<Node2D>> {
+    root: Node<T>,
+    pub blackboard: Blackboard<T>,
+    pub arrival_distance: real,
+    status: Option<Status>,
+    command: Command<T>,
+}
+
+impl<T: Target> BehaviorTree<T> {
+    pub fn new(root: Node<T>) -> Self {
+        BehaviorTree {
+            root,
+            blackboard: Blackboard::default(),
+            arrival_distance: DEFAULT_ARRIVAL_DISTANCE,
+            status: None,
+            command: Command::ContinueLast,
+        }
+    }
+
+    pub fn from_data(data: &TreeData) -> Result<Self, TreeError> {
+        data.validate()?;
+        Ok(BehaviorTree::new(data.build()))
+    }
+
+    pub fn from_ron(src: &str) -> Result<Self, TreeError> {
+        let data: TreeData = ron::from_str(src).map_err(TreeError::Parse)?;
+        BehaviorTree::from_data(&data)
+    }
+
+    pub fn with_arrival_distance(mut self, arrival_distance: real) -> Self {
+        self.arrival_distance = arrival_distance;
+        self
+    }
+
+    /// What the root came to on the last tick.
+    pub fn status(&self) -> Option<Status> {
+        self.status
+    }
+
+    /// Runs the tree once and returns the command it came up with.
+    pub fn tick(&mut self, state: &State, env: &Environment<T>) -> Command<T> {
+        let mut ctx = Context {
+            state,
+            env,
+            blackboard: &mut self.blackboard,
+            arrival_distance: self.arrival_distance,
+            command: None,
+        };
+        self.status = Some(self.root.tick(&mut ctx));
+        self.command = ctx.command.unwrap_or(Command::ContinueLast);
+        self.command.clone()
+    }
+}
+
+impl<'a, T: Target + 'a> Behavior<(&'a State, &'a Environment<T>), Command<T>> for BehaviorTree<T> {
+    fn update(&mut self, (state, env): (&'a State, &'a Environment<T>), _delta: f64) {
+        self.tick(state, env);
+    }
+
+    fn action(&self) -> Command<T> {
+        self.command.clone()
+    }
+}
+
+/// Lets a tree take part in an [`Intelligence`](crate::ai::behavior::Intelligence). The tree
+/// makes its own choices, searching for lost enemies included, so it is always moderately keen.
+impl<T: Target> Strategy<T> for RefCell<BehaviorTree<T>> {
+    fn name(&self) -> &'static str {
+        "behavior tree"
+    }
+
+    fn score(&self, _state: &State, _env: &Environment<T>) -> f32 {
+        0.5
+    }
+
+    fn evaluate(&self, state: &State, env: &Environment<T>) -> Command<T> {
+        self.borrow_mut().tick(state, env)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::cell::Cell;
+    use std::rc::Rc;
+    use crate::ai::behavior::Attributes;
+    use crate::ai::fixture::Dummy;
+    use crate::ai::perception::Memory;
+    use crate::dnd::ability::Ability;
+    use super::*;
+
+    /// Counts its ticks, running for the first `runs` of them.
+    struct Counter {
+        ticks: Rc<Cell<u32>>,
+        runs: u32,
+    }
+
+    impl Leaf<Dummy> for Counter {
+        fn tick(&mut self, _ctx: &mut Context<Dummy>) -> Status {
+            self.ticks.set(self.ticks.get() + 1);
+            if self.ticks.get() <= self.runs { Status::Running } else { Status::Success }
+        }
+    }
+
+    fn state(position: Vector2, hit_points: u32) -> State {
+        let attributes = Attributes { ability: Ability::default(), hit_points, max_hit_points: 10, mana_points: 0, buffs: Vec::new() };
+        State { position, attributes, attack_ready: true }
+    }
+
+    fn env(time: f64, sight: &[Dummy], range: &[Dummy]) -> Environment<Dummy> {
+        Environment {
+            time,
+            delta: 0.1,
+            characters_in_attack_range: range.to_vec(),
+            characters_in_sight: sight.to_vec(),
+            allies_in_sight: Vec::new(),
+            characters_remembered: Vec::new(),
+            threat_target: None,
+        }
+    }
+
+    const GOBLIN: &str = include_str!("../../../godot/data/goblin_tree.ron");
+
+    #[test]
+    fn test_goblin_tree() {
+        let mut tree = BehaviorTree::<Dummy>::from_ron(GOBLIN).unwrap();
+        let home = Vector2::ZERO;
+        let waypoints = vec![Vector2::new(100.0, 0.0), Vector2::new(100.0, 100.0)];
+        tree.blackboard.set("home", Value::Position(home));
+        tree.blackboard.set("waypoints", Value::Path(waypoints.clone()));
+        let enemy = Dummy(Vector2::new(50.0, 50.0));
+        let close = [enemy.clone()];
+
+        // nobody around, walk the round
+        assert_eq!(tree.tick(&state(home, 10), &env(0.0, &[], &[])), Command::Move(Move::To(waypoints[0])));
+        assert_eq!(tree.tick(&state(waypoints[0], 10), &env(0.1, &[], &[])), Command::Move(Move::To(waypoints[1])));
+        // on sight throw a dart, chase, then attack
+        let dart = Command::Skill { name: "Poison Dart".to_string(), target: AttackTarget::LockOn(enemy.clone()) };
+        assert_eq!(tree.tick(&state(home, 10), &env(0.2, &close, &[])), dart);
+        assert_eq!(tree.tick(&state(home, 10), &env(0.25, &close, &[])), Command::Move(Move::To(enemy.0)));
+        assert_eq!(tree.blackboard.target("target"), Some(&enemy));
+        assert_eq!(tree.tick(&state(home, 10), &env(0.3, &close, &close)), Command::Attack(Attack::lock_on(enemy.clone())));
+        let mut tired = state(home, 10);
+        tired.attack_ready = false;
+        assert_eq!(tree.tick(&tired, &env(0.4, &close, &close)), Command::Hold);
+        // badly hurt, run home
+        let hurt = state(Vector2::new(50.0, 0.0), 2);
+        assert_eq!(tree.tick(&hurt, &env(0.5, &close, &close)), Command::Move(Move::To(home)));
+        // healed on the way, back to the fight
+        assert_eq!(tree.tick(&state(Vector2::new(25.0, 0.0), 10), &env(0.6, &close, &close)), Command::Attack(Attack::lock_on(enemy.clone())));
+        // hurt again, home it holds while the enemy is in sight
+        assert_eq!(tree.tick(&hurt, &env(0.7, &close, &close)), Command::Move(Move::To(home)));
+        assert_eq!(tree.tick(&state(home, 2), &env(0.8, &close, &[])), Command::Hold);
+        // safe, back to the round where it left off
+        assert_eq!(tree.tick(&state(home, 2), &env(0.9, &[], &[])), Command::Move(Move::To(waypoints[1])));
+        // lost sight of somebody, look where they went and wait there until they are forgotten
+        let mut lost = env(1.0, &[], &[]);
+        lost.characters_remembered = vec![Memory { character: enemy.clone(), position: enemy.0, velocity: Vector2::ZERO, seen_at: 0.9, in_sight: false }];
+        assert_eq!(tree.tick(&state(home, 10), &lost), Command::Move(Move::To(enemy.0)));
+        assert_eq!(tree.tick(&state(enemy.0, 10), &lost), Command::Hold);
+        assert_eq!(tree.tick(&state(enemy.0, 10), &env(11.0, &[], &[])), Command::Move(Move::To(waypoints[1])));
+    }
+
+    #[test]
+    fn test_composites() {
+        let s = state(Vector2::ZERO, 10);
+        let ticks = Rc::new(Cell::new(0));
+        let counter = |runs| Node::leaf(Counter { ticks: ticks.clone(), runs });
+
+        // a sequence resumes at its running child
+        let mut tree = BehaviorTree::new(Node::sequence(vec![Node::task(Task::Stop), counter(1), Node::task(Task::Hold)]));
+        assert_eq!(tree.tick(&s, &env(0.0, &[], &[])), Command::Stop);
+        assert_eq!(tree.status(), Some(Status::Running));
+        assert_eq!(tree.tick(&s, &env(0.1, &[], &[])), Command::Hold);
+        assert_eq!(tree.status(), Some(Status::Success));
+
+        // a reactive sequence checks again in front of its running child
+        ticks.set(0);
+        let mut tree = BehaviorTree::new(Node::reactive_sequence(vec![Node::Check(Check::EnemyInSight), counter(5)]));
+        let enemy = [Dummy(Vector2::new(10.0, 0.0))];
+        tree.tick(&s, &env(0.0, &enemy, &[]));
+        assert_eq!(tree.status(), Some(Status::Running));
+        tree.tick(&s, &env(0.1, &[], &[]));
+        assert_eq!(tree.status(), Some(Status::Failure));
+        assert_eq!(ticks.get(), 1);
+
+        // commands of failed branches are taken back
+        let mut tree = BehaviorTree::new(Node::selector(vec![
+            Node::sequence(vec![Node::task(Task::Stop), Node::Check(Check::EnemyInSight)]),
+            Node::task(Task::Hold),
+        ]));
+        assert_eq!(tree.tick(&s, &env(0.0, &[], &[])), Command::Hold);
+
+        // a parallel waits for enough of its children
+        ticks.set(0);
+        let mut tree = BehaviorTree::new(Node::parallel(2, vec![counter(1), Node::Check(Check::AttackReady), Node::Check(Check::EnemyInSight)]));
+        tree.tick(&s, &env(0.0, &[], &[]));
+        assert_eq!(tree.status(), Some(Status::Running));
+        tree.tick(&s, &env(0.1, &[], &[]));
+        assert_eq!(tree.status(), Some(Status::Success));
+
+        let mut tree = BehaviorTree::new(Node::inverter(Node::Check(Check::EnemyInSight)));
+        tree.tick(&s, &env(0.0, &[], &[]));
+        assert_eq!(tree.status(), Some(Status::Success));
+    }
+
+    #[test]
+    fn test_decorators() {
+        let s = state(Vector2::ZERO, 10);
+        let mut tree = BehaviorTree::new(Node::selector(vec![
+            Node::cooldown(1.0, Node::task(Task::Skill("Fireball".to_string()))),
+            Node::task(Task::Hold),
+        ]));
+        let enemy = [Dummy(Vector2::new(10.0, 0.0))];
+        let skill = Command::Skill { name: "Fireball".to_string(), target: AttackTarget::LockOn(enemy[0].clone()) };
+        assert_eq!(tree.tick(&s, &env(0.0, &enemy, &[])), skill);
+        assert_eq!(tree.tick(&s, &env(0.5, &enemy, &[])), Command::Hold);
+        assert_eq!(tree.tick(&s, &env(1.0, &enemy, &[])), skill);
+
+        let mut tree = BehaviorTree::new(Node::timeout(1.0, Node::task(Task::Wait(5.0))));
+        tree.tick(&s, &env(0.0, &[], &[]));
+        assert_eq!(tree.status(), Some(Status::Running));
+        tree.tick(&s, &env(1.5, &[], &[]));
+        assert_eq!(tree.status(), Some(Status::Failure));
+
+        let invalid = "Parallel(success: 3, children: [Task(Hold)])";
+        assert!(matches!(BehaviorTree::<Dummy>::from_ron(invalid), Err(TreeError::Invalid(_))));
+        assert!(matches!(BehaviorTree::<Dummy>::from_ron("Sequence(Hold)"), Err(TreeError::Parse(_))));
+    }
+}
@@ -4,6 +4,8 @@ use godot::engine::global::PropertyHint;
 use godot::prelude::*;
 use godot::register::property::PropertyHintInfo;
 
+use crate::tools::inventory::Inventory;
+
 #[derive(Debug)]
 pub enum FaceDirection {
 	Left,
@@ -51,6 +53,45 @@ impl Export for Action {
 	}
 }
 
+/// The strategy a character follows when nobody is at the controls, picked in the inspector.
+#[derive(GodotConvert, Debug, Clone, Copy, Eq, PartialEq)]
+#[godot(via = GString)]
+pub enum StrategyKind {
+	/// Guards where it started and chases nobody too far from it.
+	Sentry,
+	/// Goes after the enemy it finds most threatening until it forgets about them.
+	Aggroed,
+	/// Walks its waypoints and fights whoever crosses its way.
+	Patrol,
+	/// Follows a behavior tree loaded from a data file.
+	Tree,
+	/// Plans its own way to chop trees and carry the wood home, raiding whoever comes by.
+	Worker,
+}
+
+impl Var for StrategyKind {
+	fn get_property(&self) -> Self::Via {
+		self.to_godot()
+	}
+
+	fn set_property(&mut self, value: Self::Via) {
+		*self = Self::from_godot(value)
+	}
+
+	fn property_hint() -> PropertyHintInfo {
+		PropertyHintInfo {
+			hint: PropertyHint::ENUM,
+			hint_string: "Sentry,Aggroed,Patrol,Tree,Worker".into(),
+		}
+	}
+}
+
+impl Export for StrategyKind {
+	fn default_export_info() -> PropertyHintInfo {
+		Self::property_hint()
+	}
+}
+
 
 impl Display for Action {
 	fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
@@ -63,6 +104,33 @@ impl Display for Action {
 	}
 }
 
+/// How close to a destination counts as there, in pixels.
+pub const ARRIVAL_DISTANCE: real = 4.0;
+
+/// Where a character is headed, as set by the last movement [`Command`](crate::ai::command::Command).
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub enum Heading {
+	#[default]
+	Still,
+	To(Vector2),
+	Direction(Vector2),
+}
+
+impl Heading {
+	/// The way to go from `position` this frame. Arriving at a destination stands still again.
+	pub fn direction(&mut self, position: Vector2) -> Vector2 {
+		match *self {
+			Heading::Still => Vector2::ZERO,
+			Heading::Direction(direction) => direction,
+			Heading::To(destination) if destination.distance_to(position) <= ARRIVAL_DISTANCE => {
+				*self = Heading::Still;
+				Vector2::ZERO
+			}
+			Heading::To(destination) => destination - position,
+		}
+	}
+}
+
 #[derive(Debug)]
 pub struct AttackCoolDown {
 	time: f64,
@@ -91,6 +159,31 @@ impl AttackCoolDown {
 	}
 }
 
+/// The pack for an inventory UI, one dictionary per stack with its `name`, `kind`, `count` and
+/// `weight` in pounds.
+pub fn inventory_stacks(inventory: &Inventory) -> Array<Dictionary> {
+	let mut stacks = Array::new();
+	for stack in inventory.stacks() {
+		let mut entry = Dictionary::new();
+		entry.set("name", GString::from(stack.item.name.as_str()));
+		entry.set("kind", GString::from(stack.item.kind.to_string()));
+		entry.set("count", stack.count);
+		entry.set("weight", stack.weight());
+		stacks.push(entry);
+	}
+	stacks
+}
+
+/// The names of the equipped items by slot, like `"main hand": "Longsword"`. Empty slots are left
+/// out.
+pub fn inventory_equipment(inventory: &Inventory) -> Dictionary {
+	let mut equipment = Dictionary::new();
+	for (slot, item) in inventory.equipped() {
+		equipment.set(GString::from(slot.to_string()), GString::from(item.name.as_str()));
+	}
+	equipment
+}
+
 #[cfg(test)]
 mod test {
 	use super::*;
@@ -106,4 +199,4 @@ mod test {
 		cool_down.update(1.0);
 		assert_eq!(cool_down.ready(), true);
 	}
-}
\ No newline at end of file
+}
@@ -1,21 +1,49 @@
-use std::cell::OnceCell;
+use std::cell::{OnceCell, RefCell};
+use std::error::Error;
 
 use godot::engine::{AnimationTree, Area2D, CharacterBody2D, CollisionShape2D, ICharacterBody2D, Label, NavigationAgent2D, NavigationServer2D, Sprite2D};
 use godot::prelude::*;
 
-use crate::characters::common::{Action, AttackCoolDown, FaceDirection};
-use crate::dnd::enums::WeaponType;
+use crate::ai::behavior;
+use crate::ai::behavior::DEFAULT_HYSTERESIS;
+use crate::ai::behavior::{Aggroed, Attributes, Environment, Intelligence, Investigate, Patrol, Retreat, Sentry, Strategy, Target, DEFAULT_SEARCH_TIME};
+use crate::ai::command::{Attack, AttackTarget, Command, Executor, Move};
+use crate::ai::goap;
+use crate::ai::goap::{Fact, Planner, Situation, WorldState};
+use crate::ai::perception::{PerceptionMemory, DEFAULT_FORGET_AFTER};
+use crate::ai::threat::ThreatTable;
+use crate::ai::tree::{BehaviorTree, Value};
+use crate::characters::common::{inventory_equipment, inventory_stacks, Action, AttackCoolDown, FaceDirection, Heading, StrategyKind, ARRIVAL_DISTANCE};
+use crate::dnd::ability::Ability;
+use crate::dnd::alignment::{Alignment, Ethical, Moral};
+use crate::dnd::attack::{roll_attack, AttackOptions};
+use crate::dnd::catalog::catalog;
+use crate::dnd::check::{CheckContext, CheckKind};
+use crate::dnd::condition::{ApplyOutcome, ConditionType, Conditions};
+use crate::dnd::dice::{Dice, DiceExpr};
+use crate::dnd::encounter::Combatant;
+use crate::dnd::enums::{Proficiencies, Proficiency, WeaponType};
+use crate::dnd::reaction::Disposition;
 use crate::interactable::effect::{Effect, Effects};
-use crate::interactable::effect::Damage;
 use crate::interactable::hit_box::HitBox;
 use crate::interactable::navigator::Navigator;
 use crate::interactable::sight::SightArea2D;
+use crate::interactable::skill_caster::SkillCaster;
+use crate::tools::inventory::{EquipmentSlot, Inventory};
 use crate::tools::weapon::{SimpleMeleeWeapon, Weapon};
 
+/// Standing lost with the goblins for killing one of them.
+const GOBLIN_KILL_REPUTATION: i32 = -10;
+/// Walking speed in feet, before encumbrance.
+const GOBLIN_SPEED: u8 = 30;
+const GOBLIN_HIT_POINTS: i32 = 100;
+
 #[derive(Debug)]
 struct State {
 	hp: i32,
     attack_cool_down: AttackCoolDown,
+	conditions: Conditions,
+	heading: Heading,
 }
 
 #[derive(Debug)]
@@ -26,12 +54,12 @@ impl Weapon for Torch {
         WeaponType::SimpleMelee
     }
 
-    fn damage(&self) -> u32 {
-        todo!()
+    fn damage(&self) -> DiceExpr {
+        DiceExpr::new(Dice::new(1, 4))
     }
 
     fn range(&self) -> u32 {
-        todo!()
+        5
     }
 }
 
@@ -48,7 +76,54 @@ pub struct Goblin {
 	action: Action,
 	#[export]
 	face_direction_name: GString,
+	#[export]
+	xp_reward: u32,
+	#[export]
+	armor_class: u8,
+	#[export]
+	strategy: StrategyKind,
+	/// How far from its post a sentry chases anyone, in pixels.
+	#[export]
+	follow_range: real,
+	/// How close the goblin has to be to swing its torch, in pixels.
+	#[export]
+	attack_range: real,
+	/// The round a patrolling goblin walks, relative to where it starts.
+	#[export]
+	patrol_points: PackedVector2Array,
+	/// Data file of the behavior tree a goblin with the Tree strategy follows.
+	#[export(file = "*.ron")]
+	behavior_tree: GString,
+	/// Hit point fraction below which an outnumbered goblin runs home.
+	#[export]
+	retreat_below: f32,
+	/// Seconds the goblin searches where an enemy got out of sight.
+	#[export]
+	search_time: f64,
+	/// Seconds until the goblin forgets an enemy out of sight.
+	#[export]
+	forget_after: f64,
+	/// How much better another strategy has to score to take over, see [`Intelligence`].
+	#[export]
+	hysteresis: f32,
 	state: State,
+	intelligence: Intelligence,
+	/// Plans the work of a worker goblin.
+	planner: Planner,
+	/// Wood a worker has brought home.
+	wood_stored: u32,
+	/// Whom an aggroed goblin goes after, the most threatening enemy of its [`ThreatTable`].
+	aggro: Option<Gd<Node2D>>,
+	threat: ThreatTable,
+	/// Enemies seen lately, see [`PerceptionMemory`].
+	memory: PerceptionMemory,
+	/// Where the goblin started out.
+	home: Vector2,
+	time: f64,
+	ability: Ability,
+	proficiencies: Proficiencies,
+	disposition: Disposition,
+	inventory: Inventory,
 	navigator: OnceCell<Navigator>,
     weapon: Torch,
 	hit_center_point: Vector2,
@@ -58,12 +133,232 @@ pub struct Goblin {
 
 #[godot_api]
 impl Goblin {
+	#[signal]
+	fn died(xp_reward: u32);
+
+	#[signal]
+	fn condition_applied(condition: GString);
+
+	#[signal]
+	fn condition_removed(condition: GString);
+
+	#[func]
+	fn get_conditions(&self) -> PackedStringArray {
+		let mut names = PackedStringArray::new();
+		for condition in self.state.conditions.active() {
+			names.push(condition.kind.to_string().into());
+		}
+		names
+	}
+
+	#[func]
+	fn get_alignment(&self) -> GString {
+		self.disposition.alignment.to_string().into()
+	}
+
+	#[func]
+	fn get_faction(&self) -> GString {
+		self.disposition.faction.clone().unwrap_or_default().into()
+	}
+
+	#[func]
+	fn get_reputation(&self, faction: GString) -> i32 {
+		self.disposition.reputation.get(&faction.to_string())
+	}
+
+	/// Why the goblin changed its mind lately, oldest first.
+	#[func]
+	fn get_decisions(&self) -> PackedStringArray {
+		let mut decisions = PackedStringArray::new();
+		for decision in self.intelligence.decisions() {
+			decisions.push(decision.to_string().into());
+		}
+		decisions
+	}
+
+	/// The goal a worker goblin works towards followed by the steps of its plan.
+	#[func]
+	fn get_plan(&self) -> PackedStringArray {
+		let mut plan = PackedStringArray::new();
+		for step in self.planner.goal().into_iter().chain(self.planner.steps()) {
+			plan.push(step.into());
+		}
+		plan
+	}
+
+	/// How much threat the goblin holds against each enemy it remembers, by node name.
+	#[func]
+	fn get_threat(&self) -> Dictionary {
+		let mut threat = Dictionary::new();
+		for (character, amount) in self.threat.entries() {
+			threat.set(character.get_name(), amount);
+		}
+		threat
+	}
+
+	/// Lets the goblin know `healer` patched someone up. Healing draws threat, but only from
+	/// enemies the goblin can see.
+	#[func]
+	fn notice_healing(&mut self, healer: Gd<Node2D>, amount: i32) {
+		let sight = self.base().get_node_as::<SightArea2D>("SightArea2D");
+		let healer_node = healer.clone().upcast::<Node>();
+		if sight.bind().hostile_owners(&self.disposition).contains(&healer_node) {
+			self.threat.add_healing(&healer, amount);
+		}
+	}
+
+	/// Where each enemy the goblin remembers was last seen, by node name.
+	#[func]
+	fn get_last_seen(&self) -> Dictionary {
+		let mut last_seen = Dictionary::new();
+		for memory in self.memory.memories() {
+			last_seen.set(memory.character.get_name(), memory.position);
+		}
+		last_seen
+	}
+
+	#[func]
+	fn get_wood_stored(&self) -> u32 {
+		self.wood_stored
+	}
+
+	/// Picks up `count` of an item from the catalog, e.g. wood off a chopped tree. Returns false for
+	/// unknown items.
+	#[func]
+	fn add_item(&mut self, name: GString, count: u32) -> bool {
+		let Some(item) = catalog().item(&name.to_string()) else {
+			tracing::debug!("no such item: {}", name);
+			return false
+		};
+		self.inventory.add(item, count);
+		true
+	}
+
+	/// What the goblin carries, for looting. See [`inventory_stacks`].
+	#[func]
+	fn get_inventory(&self) -> Array<Dictionary> {
+		inventory_stacks(&self.inventory)
+	}
+
+	#[func]
+	fn get_equipment(&self) -> Dictionary {
+		inventory_equipment(&self.inventory)
+	}
+
+	#[func]
+	fn get_carried_weight(&self) -> f32 {
+		self.inventory.weight()
+	}
+
+	#[func]
+	fn get_encumbrance(&self) -> GString {
+		self.inventory.encumbrance(self.ability.strength).to_string().into()
+	}
+
+	#[func]
+	fn has_condition(&self, name: GString) -> bool {
+		name.to_string().parse::<ConditionType>()
+			.is_ok_and(|condition| self.state.conditions.has(condition))
+	}
+
+	#[func]
+	fn hurt(&mut self, effects: Gd<Effects>) {
+		if self.action == Action::Dead {
+			return
+		}
+		let effects = effects.bind();
+		if effects.source == Some(self.base().clone().upcast::<Node>()) {
+			return
+		}
+		let armor_class = self.armor_class;
+		let mut conditions = Vec::new();
+		let damage: i32 = effects.effects.iter()
+			.map(|eff| match eff {
+				Effect::Damage(damage) => damage.amount,
+				Effect::Attack(attack) => {
+					let result = attack.against(armor_class);
+					tracing::debug!("attacked: {}", result);
+					conditions.extend(result.condition());
+					result.damage
+				}
+				Effect::SavingThrow(save) => {
+					let context = CheckContext::new(&self.ability, &self.proficiencies, 1);
+					let kind = CheckKind::SavingThrow(save.ability);
+					let mode = self.state.conditions.check_mode(kind)
+						.combine(self.inventory.encumbrance(self.ability.strength).check_mode(kind));
+					let outcome = save.resolve(&context, mode, &mut rand::thread_rng());
+					tracing::debug!("saving throw: {}", outcome.check);
+					conditions.extend(outcome.condition);
+					outcome.damage
+				}
+				_ => 0,
+			})
+			.sum();
+		conditions.extend(effects.effects.iter().filter_map(|eff| match eff {
+			Effect::Condition(condition) => Some(*condition),
+			_ => None,
+		}));
+		let source = effects.source.clone()
+			.filter(|source| source.is_class("Node2D".into()))
+			.map(|source| source.cast::<Node2D>());
+		if let Some(source) = &source {
+			self.threat.add_damage(source, damage);
+			for eff in &effects.effects {
+				if let Effect::Taunt(taunt) = eff {
+					self.threat.taunt(source, self.time + taunt.duration as f64);
+				}
+			}
+		}
+		for condition in conditions {
+			let outcome = self.state.conditions.apply(condition);
+			tracing::debug!("{} applied: {:?}", condition.kind, outcome);
+			if outcome == ApplyOutcome::Applied {
+				let name = condition.kind.to_string().to_variant();
+				self.base_mut().emit_signal("condition_applied".into(), &[name]);
+			}
+		}
+		let healing: i32 = effects.effects.iter()
+			.map(|eff| match eff {
+				Effect::Heal(heal) => heal.amount.max(0),
+				_ => 0,
+			})
+			.sum();
+		self.state.hp = (self.state.hp + healing).min(GOBLIN_HIT_POINTS) - damage;
+		if self.state.hp > 0 {
+			return
+		}
+
+		self.action = Action::Dead;
+		self.base_mut().set_velocity(Vector2::ZERO);
+		let xp_reward = self.xp_reward;
+		self.base_mut().emit_signal("died".into(), &[xp_reward.to_variant()]);
+		// whoever landed the killing blow gets the experience, if it keeps track of any
+		if let Some(mut killer) = effects.source.clone() {
+			if killer.has_method("gain_experience".into()) {
+				killer.call("gain_experience".into(), &[xp_reward.to_variant()]);
+			}
+			// the rest of the tribe will remember
+			if let Some(faction) = self.disposition.faction.as_deref() {
+				if killer.has_method("change_reputation".into()) {
+					let args = [GString::from(faction).to_variant(), GOBLIN_KILL_REPUTATION.to_variant()];
+					killer.call("change_reputation".into(), &args);
+				}
+			}
+		}
+	}
 
 	#[func]
 	fn on_attack_end(&mut self) {
 		self.action = Action::Idle;
 	}
 
+	#[func]
+	fn on_animation_finished(&mut self, name: GString) {
+		if name.to_string().starts_with(&Action::Attack.to_string()) {
+			self.on_attack_end();
+		}
+	}
+
 	#[func]
 	fn on_animation_changed(&mut self, old_name: Variant, _new_name: Variant) {
 		tracing::debug!("animation changed: {:?} -> {:?}", old_name, _new_name);
@@ -74,11 +369,8 @@ impl Goblin {
 		let Some(owner) = body.get_owner() else {
 			return
 		};
+		// the strategy picks it up from the sight area on the next tick
 		tracing::debug!("enemy entered: {:?}", owner);
-		if !self.get_navigator_mut().is_following() {
-			self.get_navigator_mut().follow(owner.cast());
-		}
-		// TODO: send to AI to decide what to do
 	}
 
 	#[func]
@@ -86,10 +378,8 @@ impl Goblin {
 		let Some(owner) = body.get_owner() else {
 			return
 		};
+		// not forgotten, see `PerceptionMemory`
 		tracing::debug!("enemy exited: {:?}", owner);
-		if self.get_navigator_mut().get_target() == Some(owner.cast()) {
-			self.get_navigator_mut().stop_following();
-		}
 	}
 
 	fn get_animation_tree(&self) -> Gd<AnimationTree> {
@@ -120,15 +410,283 @@ impl Goblin {
 		self.action = Action::Walk;
 	}
 
-	fn attack(&mut self) {
+	/// The strategy the exported [`StrategyKind`] stands for. An aggroed goblin guards its spot
+	/// until it has somebody to go after.
+	fn main_strategy(&self) -> Box<dyn Strategy> {
+		let sentry = Sentry { stay_position: self.home, follow_range: self.follow_range };
+		match (self.strategy, self.aggro.clone()) {
+			(StrategyKind::Aggroed, Some(target)) => Box::new(Aggroed { target, attack_range: self.attack_range }),
+			// workers follow their planner instead, see `work`
+			(StrategyKind::Sentry | StrategyKind::Aggroed | StrategyKind::Worker, _) => Box::new(sentry),
+			(StrategyKind::Patrol, _) => Box::new(Patrol::new(self.waypoints(), ARRIVAL_DISTANCE)),
+			(StrategyKind::Tree, _) => match self.load_behavior_tree() {
+				Ok(tree) => Box::new(RefCell::new(tree)),
+				Err(err) => {
+					tracing::warn!("goblin falls back to guarding, cannot load {}: {}", self.behavior_tree, err);
+					Box::new(sentry)
+				}
+			},
+		}
+	}
+
+	/// The patrol round in world coordinates.
+	fn waypoints(&self) -> Vec<Vector2> {
+		self.patrol_points.to_vec().into_iter().map(|point| self.home + point).collect()
+	}
+
+	/// Reads the exported behavior tree and tells it where home and the patrol round are.
+	fn load_behavior_tree(&self) -> Result<BehaviorTree, Box<dyn Error>> {
+		let src = crate::read_resource(&self.behavior_tree.to_string())?;
+		let mut tree = BehaviorTree::from_ron(&src)?.with_arrival_distance(ARRIVAL_DISTANCE);
+		tree.blackboard.set("home", Value::Position(self.home));
+		tree.blackboard.set("waypoints", Value::Path(self.waypoints()));
+		Ok(tree)
+	}
+
+	/// The main strategy, with running home taking precedence when it comes to that.
+	fn intelligence(&self) -> Intelligence {
+		let retreat = Retreat { to: self.home, below: self.retreat_below };
+		let mut investigate = Investigate::new(self.search_time, self.attack_range * 2.0, ARRIVAL_DISTANCE);
+		// a sentry keeps to its post even while searching
+		if self.strategy == StrategyKind::Sentry {
+			investigate = investigate.with_leash(self.home, self.follow_range);
+		}
+		Intelligence::new(vec![self.main_strategy(), Box::new(investigate)])
+			.with_strategy(1, Box::new(retreat))
+			.with_hysteresis(self.hysteresis)
+	}
+
+	/// What the strategy gets to know about the goblin itself.
+	fn ai_state(&self) -> behavior::State {
+		behavior::State {
+			position: self.base().get_global_position(),
+			attributes: Attributes {
+				ability: self.ability.clone(),
+				hit_points: self.state.hp.max(0) as u32,
+				max_hit_points: GOBLIN_HIT_POINTS as u32,
+				mana_points: 0,
+				buffs: Vec::new(),
+			},
+			attack_ready: self.state.attack_cool_down.ready() && self.state.conditions.can_attack(),
+		}
+	}
+
+	/// Hostile and friendly characters in sight, closest first, and the enemies that got out of
+	/// sight lately. Updates the goblin's memory on the way.
+	fn environment(&mut self, delta: f64) -> Environment {
+		let position = self.base().get_global_position();
+		let sight = self.base().get_node_as::<SightArea2D>("SightArea2D");
+		let by_distance = |owners: Vec<Gd<Node>>| {
+			let mut characters: Vec<Gd<Node2D>> = owners.into_iter()
+				.filter(|owner| owner.is_class("Node2D".into()))
+				.map(|owner| owner.cast::<Node2D>())
+				.collect();
+			characters.sort_by(|a, b| {
+				a.get_global_position().distance_to(position).total_cmp(&b.get_global_position().distance_to(position))
+			});
+			characters
+		};
+		let characters_in_sight = by_distance(sight.bind().hostile_owners(&self.disposition));
+		let allies_in_sight = by_distance(sight.bind().friendly_owners(&self.disposition));
+		self.memory.retain(|character| character.is_instance_valid());
+		self.memory.update(&characters_in_sight, self.time);
+		Environment {
+			time: self.time,
+			delta,
+			characters_in_attack_range: characters_in_sight.iter()
+				.filter(|character| character.get_global_position().distance_to(position) <= self.attack_range)
+				.cloned()
+				.collect(),
+			characters_in_sight,
+			allies_in_sight,
+			characters_remembered: self.memory.lost(),
+			// filled in by `update_aggro`
+			threat_target: None,
+		}
+	}
+
+	/// Keeps the threat table up to date and tells the strategies whom it finds most threatening.
+	/// An aggroed goblin goes after them, so a pack spreads over those who hurt it and those who
+	/// come close; the others pick them out among those in attack range.
+	fn update_aggro(&mut self, env: &mut Environment) {
+		self.threat.retain(|character| character.is_instance_valid());
+		let position = self.base().get_global_position();
+		self.threat.update(position, &env.characters_in_sight, env.time, env.delta);
+		env.threat_target = self.threat.target().cloned();
+		if self.strategy != StrategyKind::Aggroed {
+			return
+		}
+		let aggro = self.threat.target().cloned();
+		let unchanged = match (&aggro, &self.aggro) {
+			(Some(aggro), Some(current)) => current.is_instance_valid() && aggro.is_same(current),
+			(None, None) => true,
+			_ => false,
+		};
+		if !unchanged {
+			self.aggro = aggro;
+			let strategy = self.main_strategy();
+			self.intelligence.set_strategy(0, strategy);
+		}
+	}
+
+	/// What a worker knows for planning: whether it carries wood, where home and the closest tree
+	/// still standing are, and who is around. Raids go after the most threatening enemy in sight,
+	/// else the closest.
+	fn work_situation(&self, env: &Environment) -> (WorldState, Situation) {
+		let position = self.base().get_global_position();
+		let sight = self.base().get_node_as::<SightArea2D>("SightArea2D");
+		let tree = sight.bind().owners_of_class("PineTree").into_iter()
+			.filter(|tree| !tree.clone().call("is_stump".into(), &[]).booleanize())
+			.map(|tree| tree.cast::<Node2D>().get_global_position())
+			.min_by(|a, b| a.distance_to(position).total_cmp(&b.distance_to(position)));
+		let near = |point: Vector2| point.distance_to(position) <= self.attack_range;
+		let mut world = WorldState::default();
+		world.set(Fact::HasWood, self.inventory.count("Wood") > 0);
+		world.set(Fact::TreeInSight, tree.is_some());
+		world.set(Fact::NearTree, tree.is_some_and(near));
+		world.set(Fact::NearHome, near(self.home));
+		world.set(Fact::EnemyVisible, !env.characters_in_sight.is_empty());
+		world.set(Fact::NearEnemy, !env.characters_in_attack_range.is_empty());
+		let situation = Situation {
+			position,
+			home: self.home,
+			tree,
+			enemy: env.threat_target.as_ref()
+				.filter(|threat| env.characters_in_sight.iter().any(|character| character.is_same(threat)))
+				.or(env.characters_in_sight.first())
+				.cloned(),
+			attack_ready: self.ai_state().attack_ready,
+		};
+		(world, situation)
+	}
+
+	/// The next step of a worker's plan. Wood carried home goes into the store.
+	fn work(&mut self, env: &Environment) -> Command {
+		let (world, situation) = self.work_situation(env);
+		if world.holds(Fact::HasWood) && world.holds(Fact::NearHome) {
+			let wood = self.inventory.count("Wood");
+			if self.inventory.remove("Wood", wood).is_ok() {
+				self.wood_stored += wood;
+			}
+		}
+		self.planner.update(&world, &situation)
+	}
+
+	/// Walks one frame along the heading of the last command, finding a way around obstacles to
+	/// a destination.
+	fn advance(&mut self) {
+		let self_pos = self.base().get_global_position();
+		let direction = match self.state.heading {
+			Heading::To(_) if self.get_navigator().is_target_reached() => {
+				self.state.heading = Heading::Still;
+				Vector2::ZERO
+			}
+			Heading::To(_) => self.get_navigator_mut().get_next_position() - self_pos,
+			_ => self.state.heading.direction(self_pos),
+		};
+		if direction.length() > 0.0 {
+			let speed = self.speed * self.state.conditions.speed_multiplier() as real * self.speed_scale();
+			self.face(direction);
+			self.transition_to_walk();
+			self.base_mut().set_velocity(direction.normalized() * speed);
+			self.base_mut().move_and_slide();
+		} else {
+			self.transition_to_idle();
+			self.base_mut().set_velocity(Vector2::ZERO);
+		}
+	}
+
+	fn face(&mut self, direction: Vector2) {
+		let mut animation_tree = self.get_animation_tree();
+		animation_tree.set("parameters/walk/blend_position".into(), direction.x.to_variant());
+		animation_tree.set("parameters/idle/blend_position".into(), direction.x.to_variant());
+		animation_tree.set("parameters/attack/blend_position".into(), direction.to_variant());
+		if direction.x < 0.0 {
+			self.turn_left();
+		} else if direction.x > 0.0 {
+			self.turn_right();
+		}
+	}
+
+	/// A torch in hand and a few coins in the pouch.
+	fn starting_inventory() -> Inventory {
+		let item = |name| catalog().item(name).unwrap_or_else(|| panic!("catalog has no {}", name));
+		let mut inventory = Inventory::default();
+		inventory.add(item("Torch"), 1);
+		inventory.add(item("Gold Piece"), 12);
+		inventory.equip(EquipmentSlot::MainHand, "Torch").expect("a torch is held in the main hand");
+		inventory
+	}
+
+	/// Walking speed left under the current load, in feet.
+	fn walking_speed(&self) -> u8 {
+		self.inventory.encumbrance(self.ability.strength).speed(GOBLIN_SPEED)
+	}
+
+	/// How much of its pace the load leaves the goblin.
+	fn speed_scale(&self) -> real {
+		self.walking_speed() as real / GOBLIN_SPEED as real
+	}
+
+	/// The goblin as a turn-based combatant standing at `position`, in feet. It guards the spot
+	/// and chases nobody further than 30 feet from it.
+	pub fn combatant(&self, team: u32, position: Vector2) -> Combatant {
+		Combatant {
+			name: "Goblin".to_string(),
+			team,
+			ability: self.ability.clone(),
+			proficiencies: self.proficiencies,
+			level: 1,
+			armor_class: self.armor_class,
+			health: self.state.hp.max(0) as u32,
+			max_health: GOBLIN_HIT_POINTS as u32,
+			speed: self.walking_speed() as u32,
+			position,
+			weapon: Box::new(Torch {}),
+			off_hand: None,
+			conditions: self.state.conditions.clone(),
+			strategy: Box::new(Sentry { stay_position: position, follow_range: 30.0 }),
+		}
+	}
+
+	/// Takes over the conditions the goblin was left with after a turn-based encounter.
+	pub fn set_conditions(&mut self, conditions: Conditions) {
+		self.state.conditions = conditions;
+	}
+
+	/// Rolls the next swing of the torch and hands it to the hit box.
+	fn roll_attack(&mut self) {
+		let context = CheckContext::new(&self.ability, &self.proficiencies, 1);
+		let mode = self.state.conditions.attack_mode()
+			.combine(self.inventory.encumbrance(self.ability.strength).attack_mode());
+		let options = AttackOptions::default().with_mode(mode);
+		let attack = roll_attack(&context, &self.weapon, options, &mut rand::thread_rng())
+			.expect("torch is swung within reach");
+		let mut hit_box = self.base().get_node_as::<HitBox>("Sprite2D/HitBox");
+		hit_box.bind_mut().set_effects(Effects::new(vec![Effect::Attack(attack)]));
+	}
+
+	fn swing(&mut self) {
+		if !self.state.conditions.can_attack() {
+			return
+		}
+		self.roll_attack();
 		self.action = Action::Attack;
 	}
 
 	fn up_attack(&mut self) {
+		if !self.state.conditions.can_attack() {
+			return
+		}
+		self.roll_attack();
 		self.action = Action::Attack;
 	}
 
 	fn down_attack(&mut self) {
+		if !self.state.conditions.can_attack() {
+			return
+		}
+		self.roll_attack();
 		self.action = Action::Attack;
 	}
 }
@@ -141,10 +699,43 @@ impl ICharacterBody2D for Goblin {
 			speed: 100 as real,
 			action: Action::Idle,
 			face_direction_name: FaceDirection::Right.to_string().into(),
+			xp_reward: 50,
+			armor_class: 15,
+			strategy: StrategyKind::Sentry,
+			follow_range: 240 as real,
+			attack_range: 40 as real,
+			patrol_points: PackedVector2Array::new(),
+			behavior_tree: "res://data/goblin_tree.ron".into(),
+			retreat_below: 0.25,
+			search_time: DEFAULT_SEARCH_TIME,
+			forget_after: DEFAULT_FORGET_AFTER,
+			hysteresis: DEFAULT_HYSTERESIS,
 			state: State {
-				hp: 100,
+				hp: GOBLIN_HIT_POINTS,
 				attack_cool_down: AttackCoolDown::new(1.0),
+				conditions: Conditions::default(),
+				heading: Heading::Still,
+			},
+			intelligence: Intelligence::new(Vec::new()),
+			planner: Planner::new(goap::goblin_actions(), goap::goblin_goals()),
+			wood_stored: 0,
+			aggro: None,
+			threat: ThreatTable::new(240 as real),
+			memory: PerceptionMemory::default(),
+			home: Vector2::ZERO,
+			time: 0.0,
+			ability: Ability {
+				strength: 8,
+				dexterity: 14,
+				constitution: 10,
+				intelligence: 10,
+				wisdom: 8,
+				charisma: 8,
+				hit_points: 7,
 			},
+			proficiencies: Proficiencies::from_proficiencies(vec![Proficiency::Weapon(WeaponType::SimpleMelee)]),
+			disposition: Disposition::new(Alignment::new(Moral::Evil, Ethical::Neutral), Some("Goblins".to_string())),
+			inventory: Goblin::starting_inventory(),
             weapon: Torch {},
 			navigator: OnceCell::new(),
 			hit_center_point: Vector2::ZERO,
@@ -159,55 +750,85 @@ impl ICharacterBody2D for Goblin {
 		self.navigator.set(Navigator::new(navigation_agent))
 			.expect("NavigationAgent2D is already initialized");
 
-        let mut hit_box = self.base_mut().get_node_as::<HitBox>("Sprite2D/HitBox");
-        let effects = Effects::new(vec![Effect::Damage(Damage { amount: 10 })]);
-        hit_box.bind_mut().set_effects(effects);
+		self.home = self.base().get_global_position();
+		self.threat.proximity_range = self.follow_range;
+		self.memory.forget_after = self.forget_after;
+		self.intelligence = self.intelligence();
+		self.roll_attack();
 	}
 
 	fn process(&mut self, delta: f64) {
+		if self.action == Action::Dead {
+			return
+		}
 		self.state.attack_cool_down.update(delta);
-		// self.process_input()
-		let sight = self.base_mut().get_node_as::<SightArea2D>("SightArea2D");
-		if !self.get_navigator().is_following() {
-			for area in sight.get_overlapping_areas().iter_shared() {
-				let Some(owner) = area.get_owner() else {
-					tracing::debug!("Dangling node: {:?}", area);
-					continue
-				};
-				// tracing::debug!("insight: {:?}", owner);
-				self.get_navigator_mut().follow(owner.cast());
-				break
-			}
-		} else if !sight.has_overlapping_areas() {
-			self.get_navigator_mut().stop_following();
-			// tracing::debug!("lost sight");
-		}
-
-		if !self.get_navigator().is_target_reached() {
-			let self_pos = self.base().get_global_position();
-			let next_pos = self.get_navigator_mut().get_next_position();
-			let direction = next_pos - self_pos;
-			if direction.length() > 0.0 {
-				let speed = self.speed;
-				let mut animation_tree = self.base().get_node_as::<AnimationTree>("AnimationTree");
-				animation_tree.set("parameters/walk/blend_position".into(), direction.x.to_variant());
-				animation_tree.set("parameters/idle/blend_position".into(), direction.x.to_variant());
-				animation_tree.set("parameters/attack/blend_position".into(), direction.to_variant());
-
-				self.transition_to_walk();
-				self.base_mut().set_velocity(direction.normalized() * speed);
-				self.base_mut().move_and_slide();
-			} else {
-				self.transition_to_idle();
-				self.base_mut().set_velocity(Vector2::ZERO);
-			}
-		} else {
+		for expired in self.state.conditions.tick(delta) {
+			self.base_mut().emit_signal("condition_removed".into(), &[expired.to_string().to_variant()]);
+		}
+		if !self.state.conditions.can_act() || !self.state.conditions.can_move() {
 			self.transition_to_idle();
 			self.base_mut().set_velocity(Vector2::ZERO);
+			return
+		}
+		// self.process_input()
+		self.time += delta;
+		if self.is_accept_input() {
+			let mut env = self.environment(delta);
+			self.update_aggro(&mut env);
+			let command = if self.strategy == StrategyKind::Worker {
+				self.work(&env)
+			} else {
+				let state = self.ai_state();
+				self.intelligence.evaluate(&state, &env)
+			};
+			self.execute(command);
+		}
+		if self.action != Action::Attack {
+			self.advance();
 		}
 
 		let mut debug = self.base().get_node_as::<Label>("Debug");
-		debug.set_text(format!("follow: {:?}, state: {:?}", self.navigator, self.state).into());
+		let current = match self.strategy {
+			StrategyKind::Worker => self.planner.goal(),
+			_ => self.intelligence.current(),
+		}.unwrap_or("nothing");
+		debug.set_text(format!("{:?} ({}), aggro: {:?}, state: {:?}", self.strategy, current, self.aggro, self.state).into());
+	}
+}
+
+impl Executor for Goblin {
+	fn move_to(&mut self, destination: Vector2) {
+		self.state.heading = Heading::To(destination);
+		self.get_navigator_mut().navigate_to(destination);
+	}
+
+	fn walk(&mut self, direction: Vector2) {
+		self.state.heading = Heading::Direction(direction);
+	}
+
+	fn attack(&mut self, attack: Attack) {
+		let direction = match attack.target {
+			AttackTarget::LockOn(target) => target.get_global_position() - self.base().get_global_position(),
+			AttackTarget::Direction(direction) => direction,
+			AttackTarget::Point(point) => point - self.base().get_global_position(),
+		};
+		self.state.heading = Heading::Still;
+		self.base_mut().set_velocity(Vector2::ZERO);
+		if direction.length() > 0.0 {
+			self.face(direction);
+		}
+		self.attack_pressed();
+	}
+
+	fn use_skill(&mut self, name: &str, target: AttackTarget) {
+		if let Some(mut caster) = self.base().try_get_node_as::<SkillCaster>("SkillCaster") {
+			// failures are reported through the caster's cast_failed signal
+			let _ = caster.bind_mut().cast(name, target, &self.state.conditions);
+		}
+	}
+
+	fn stop(&mut self) {
+		self.state.heading = Heading::Still;
 	}
 }
 
@@ -230,7 +851,7 @@ impl InputAction for Goblin {
 	fn attack_pressed(&mut self) {
 		if self.state.attack_cool_down.ready() {
 			self.state.attack_cool_down.reset();
-			self.attack();
+			self.swing();
 		}
 	}
 
@@ -239,35 +860,24 @@ impl InputAction for Goblin {
 			return
 		}
 		let input = Input::singleton();
-		if input.is_action_pressed("attack".into()) {
-			self.attack_pressed();
-			return
+		let command = if input.is_action_pressed("attack".into()) {
+			Command::Attack(Attack::towards(Vector2::ZERO))
 		} else {
-			let mut velocity = Vector2::ZERO;
+			let mut direction = Vector2::ZERO;
 			if input.is_action_pressed("move_right".into()) {
-				self.turn_right();
-				velocity += Vector2::RIGHT;
+				direction += Vector2::RIGHT;
 			}
 			if input.is_action_pressed("move_left".into()) {
-				self.turn_left();
-				velocity += Vector2::LEFT;
+				direction += Vector2::LEFT;
 			}
 			if input.is_action_pressed("move_down".into()) {
-				velocity += Vector2::DOWN;
+				direction += Vector2::DOWN;
 			}
 			if input.is_action_pressed("move_up".into()) {
-				velocity += Vector2::UP;
+				direction += Vector2::UP;
 			}
-
-			if velocity.length() > 0.0 {
-				velocity = velocity.normalized() * self.speed;
-				self.transition_to_walk();
-			} else {
-				self.transition_to_idle();
-			}
-
-			self.base_mut().set_velocity(velocity);
-			self.base_mut().move_and_slide();
-		}
+			Command::Move(Move::Direction(direction))
+		};
+		self.execute(command);
 	}
 }
@@ -1,12 +1,33 @@
 use godot::engine::{AnimationNodeStateMachinePlayback, AnimationTree, CharacterBody2D, ICharacterBody2D, Label};
 use godot::prelude::*;
 
-use crate::characters::common::{Action, AttackCoolDown};
+use crate::ai::behavior::Sentry;
+use crate::ai::command::{Attack, AttackTarget, Command, Executor, Move};
+use crate::characters::common::{inventory_equipment, inventory_stacks, Action, AttackCoolDown, Heading};
+use crate::dnd::attack::{roll_attack, AttackOptions, AttackOutcome};
+use crate::dnd::alignment::{Alignment, Ethical, Moral};
+use crate::dnd::catalog::catalog;
+use crate::dnd::check::CheckKind;
+use crate::dnd::condition::{ApplyOutcome, Condition, ConditionType, Conditions, ROUND_SECONDS};
+use crate::dnd::death::LifeEvent;
+use crate::dnd::encounter::Combatant;
+use crate::dnd::enums::{ClassType, RaceType};
+use crate::dnd::footman::{Character, Footman};
+use crate::interactable::effect::{Effect, Effects};
+use crate::interactable::hit_box::HitBox;
+use crate::interactable::skill_caster::SkillCaster;
+use crate::tools::inventory::{carrying_capacity, EquipmentSlot, InventoryError};
+use crate::tools::weapon::{Grip, WeaponData};
+
+/// Input actions that cast the warrior's skills, in the order its skill caster knows them.
+const SKILL_ACTIONS: [&str; 3] = ["skill_1", "skill_2", "skill_3"];
 
 #[derive(Debug)]
 struct State {
-	hp: i32,
 	attack_cool_down: AttackCoolDown,
+	/// Time until the next death saving throw while dying.
+	death_save_in: f64,
+	heading: Heading,
 }
 
 #[derive(GodotClass)]
@@ -19,12 +40,302 @@ pub struct Warrior {
 	action: Action,
 	speed: real,
 	state: State,
+	character: Footman,
 	base: Base<CharacterBody2D>,
 }
 
 
 #[godot_api]
 impl Warrior {
+	#[signal]
+	fn level_up_pending(level: u32);
+	#[signal]
+	fn downed();
+	#[signal]
+	fn stabilized();
+	#[signal]
+	fn revived();
+	#[signal]
+	fn died();
+	#[signal]
+	fn death_save(success: bool, successes: u8, failures: u8);
+	#[signal]
+	fn inventory_changed();
+	#[signal]
+	fn condition_applied(condition: GString);
+	#[signal]
+	fn condition_removed(condition: GString);
+
+	#[func]
+	fn get_conditions(&self) -> PackedStringArray {
+		let mut names = PackedStringArray::new();
+		for condition in self.conditions().active() {
+			names.push(condition.kind.to_string().into());
+		}
+		names
+	}
+
+	#[func]
+	fn has_condition(&self, name: GString) -> bool {
+		name.to_string().parse::<ConditionType>()
+			.is_ok_and(|condition| self.conditions().has(condition))
+	}
+
+	#[func]
+	fn hurt(&mut self, effects: Gd<Effects>) {
+		if !self.character.is_alive() {
+			return
+		}
+		let effects = effects.bind();
+		if effects.source == Some(self.base().clone().upcast::<Node>()) {
+			return
+		}
+		let mut rng = rand::thread_rng();
+		for effect in effects.effects.iter() {
+			let (damage, critical) = match effect {
+				Effect::Damage(damage) => (damage.amount, false),
+				Effect::Attack(attack) => {
+					let result = attack.against(self.character.armor_class());
+					tracing::debug!("attacked: {}", result);
+					if let Some(condition) = result.condition() {
+						self.apply_condition(condition);
+					}
+					(result.damage, result.outcome == AttackOutcome::CriticalHit)
+				}
+				Effect::SavingThrow(save) => {
+					let mode = self.character.check_mode(CheckKind::SavingThrow(save.ability));
+					let outcome = save.resolve(&self.character.check_context(), mode, &mut rng);
+					tracing::debug!("saving throw: {}", outcome.check);
+					if let Some(condition) = outcome.condition {
+						self.apply_condition(condition);
+					}
+					(outcome.damage, false)
+				}
+				Effect::Condition(condition) => {
+					self.apply_condition(*condition);
+					continue
+				}
+				Effect::Heal(heal) => {
+					let event = self.character.heal(heal.amount.max(0) as u32);
+					self.on_life_event(event);
+					continue
+				}
+				_ => continue,
+			};
+			let taken = self.character.take_damage(damage.max(0) as u32, critical, &mut rng);
+			self.on_life_event(taken.event);
+			if !self.character.is_alive() {
+				return
+			}
+		}
+	}
+
+	fn conditions(&self) -> &Conditions {
+		self.character.conditions().expect("footmen keep track of their conditions")
+	}
+
+	fn apply_condition(&mut self, condition: Condition) {
+		let outcome = self.character.conditions_mut().apply(condition);
+		tracing::debug!("{} applied: {:?}", condition.kind, outcome);
+		if outcome == ApplyOutcome::Applied {
+			let name = condition.kind.to_string().to_variant();
+			self.base_mut().emit_signal("condition_applied".into(), &[name]);
+		}
+	}
+
+	#[func]
+	fn heal(&mut self, amount: u32) {
+		let event = self.character.heal(amount);
+		self.on_life_event(event);
+	}
+
+	/// Spends up to `hit_dice` hit dice, returns whether the rest was possible.
+	#[func]
+	fn short_rest(&mut self, hit_dice: u32) -> bool {
+		match self.character.short_rest(hit_dice, &mut rand::thread_rng()) {
+			Ok(rest) => {
+				tracing::debug!("short rest: {:?}", rest);
+				true
+			}
+			Err(err) => {
+				tracing::debug!("no short rest: {}", err);
+				false
+			}
+		}
+	}
+
+	#[func]
+	fn long_rest(&mut self) -> bool {
+		match self.character.long_rest() {
+			Ok(rest) => {
+				tracing::debug!("long rest: {:?}", rest);
+				true
+			}
+			Err(err) => {
+				tracing::debug!("no long rest: {}", err);
+				false
+			}
+		}
+	}
+
+	/// Rolls a death saving throw every round while the character is dying.
+	fn process_dying(&mut self, delta: f64) {
+		if !self.character.life().is_dying() {
+			self.state.death_save_in = ROUND_SECONDS;
+			return
+		}
+		self.state.death_save_in -= delta;
+		if self.state.death_save_in > 0.0 {
+			return
+		}
+		self.state.death_save_in += ROUND_SECONDS;
+		let Some(save) = self.character.death_save(&mut rand::thread_rng()) else {
+			return
+		};
+		tracing::debug!("{}", save);
+		let args = [save.success.to_variant(), save.saves.successes.to_variant(), save.saves.failures.to_variant()];
+		self.base_mut().emit_signal("death_save".into(), &args);
+		self.on_life_event(save.event);
+	}
+
+	fn on_life_event(&mut self, event: Option<LifeEvent>) {
+		let Some(event) = event else {
+			return
+		};
+		tracing::debug!("warrior {}", event);
+		match event {
+			LifeEvent::Downed | LifeEvent::Died => {
+				self.action = Action::Dead;
+				self.base_mut().set_velocity(Vector2::ZERO);
+			}
+			LifeEvent::Revived => {
+				self.action = Action::Idle;
+				let mut animation_tree = self.base().get_node_as::<AnimationTree>("AnimationTree");
+				let mut state_machine: Gd<AnimationNodeStateMachinePlayback> = animation_tree.get("parameters/playback".into()).to();
+				state_machine.start(Action::Idle.to_godot().into());
+			}
+			LifeEvent::Stabilized => {}
+		}
+		self.base_mut().emit_signal(event.to_string().into(), &[]);
+	}
+
+	#[func]
+	fn gain_experience(&mut self, xp: u32) {
+		if self.character.gain_experience(xp) == 0 {
+			return
+		}
+		if let Some(pending) = self.character.pending_level_up() {
+			tracing::debug!("level up pending: {:?}", pending);
+			self.base_mut().emit_signal("level_up_pending".into(), &[pending.level.to_variant()]);
+		}
+	}
+
+	/// Levels by class name, the starting class first.
+	#[func]
+	fn get_class_levels(&self) -> Dictionary {
+		let mut levels = Dictionary::new();
+		for (class, level) in self.character.class_levels() {
+			levels.set(class.to_string(), level);
+		}
+		levels
+	}
+
+	#[func]
+	fn get_alignment(&self) -> GString {
+		self.character.alignment().to_string().into()
+	}
+
+	#[func]
+	fn get_faction(&self) -> GString {
+		self.character.faction().unwrap_or_default().into()
+	}
+
+	#[func]
+	fn get_reputation(&self, faction: GString) -> i32 {
+		self.character.reputation().get(&faction.to_string())
+	}
+
+	#[func]
+	fn change_reputation(&mut self, faction: GString, delta: i32) -> i32 {
+		let standing = self.character.reputation_mut().change(&faction.to_string(), delta);
+		tracing::debug!("reputation with {}: {}", faction, standing);
+		standing
+	}
+
+	/// One dictionary per stack in the pack, see [`inventory_stacks`].
+	#[func]
+	fn get_inventory(&self) -> Array<Dictionary> {
+		inventory_stacks(self.character.inventory())
+	}
+
+	#[func]
+	fn get_equipment(&self) -> Dictionary {
+		inventory_equipment(self.character.inventory())
+	}
+
+	#[func]
+	fn get_carried_weight(&self) -> f32 {
+		self.character.inventory().weight()
+	}
+
+	#[func]
+	fn get_carrying_capacity(&self) -> f32 {
+		carrying_capacity(self.character.ability().strength)
+	}
+
+	#[func]
+	fn get_encumbrance(&self) -> GString {
+		self.character.encumbrance().to_string().into()
+	}
+
+	/// Picks up `count` of an item from the catalog, returns false for unknown items.
+	#[func]
+	fn add_item(&mut self, name: GString, count: u32) -> bool {
+		let Some(item) = catalog().item(&name.to_string()) else {
+			tracing::debug!("no such item: {}", name);
+			return false
+		};
+		self.character.add_item(item, count);
+		self.on_inventory_changed(Ok(()))
+	}
+
+	#[func]
+	fn drop_item(&mut self, name: GString, count: u32) -> bool {
+		let result = self.character.remove_item(&name.to_string(), count).map(|_| ());
+		self.on_inventory_changed(result)
+	}
+
+	/// Equips an item from the pack in the slot named like `"main hand"` or `"accessory 1"`.
+	#[func]
+	fn equip_item(&mut self, slot: GString, name: GString) -> bool {
+		let Ok(slot) = slot.to_string().parse::<EquipmentSlot>() else {
+			tracing::debug!("no such slot: {}", slot);
+			return false
+		};
+		let result = self.character.equip_item(slot, &name.to_string()).map(|_| ());
+		self.on_inventory_changed(result)
+	}
+
+	#[func]
+	fn unequip_item(&mut self, slot: GString) -> bool {
+		let Ok(slot) = slot.to_string().parse::<EquipmentSlot>() else {
+			tracing::debug!("no such slot: {}", slot);
+			return false
+		};
+		let result = self.character.unequip_item(slot).map(|_| ());
+		self.on_inventory_changed(result)
+	}
+
+	fn on_inventory_changed(&mut self, result: Result<(), InventoryError>) -> bool {
+		if let Err(err) = result {
+			tracing::debug!("inventory unchanged: {}", err);
+			return false
+		}
+		self.roll_attack();
+		self.base_mut().emit_signal("inventory_changed".into(), &[]);
+		true
+	}
+
 	#[func]
 	fn on_animation_finished(&mut self, name: GString) {
 		if name == Action::Attack.to_godot() {
@@ -32,6 +343,62 @@ impl Warrior {
 		}
 	}
 
+	/// The warrior as a turn-based combatant standing at `position`, in feet. It goes after
+	/// whichever enemy is closest.
+	pub fn combatant(&self, team: u32, position: Vector2) -> Combatant {
+		let strategy = Sentry { stay_position: position, follow_range: real::MAX };
+		Combatant::from_character(&self.character, team, Box::new(self.weapon()), Box::new(strategy))
+			.at(position)
+	}
+
+	/// Takes over the conditions the warrior was left with after a turn-based encounter.
+	pub fn set_conditions(&mut self, conditions: Conditions) {
+		*self.character.conditions_mut() = conditions;
+	}
+
+	/// The weapon in the main hand. Empty-handed, whatever is at hand fights like a club.
+	fn weapon(&self) -> WeaponData {
+		self.character.weapon()
+			.or_else(|| catalog().weapon("Club"))
+			.expect("catalog has a club")
+			.clone()
+	}
+
+	/// Speed left after armor and encumbrance, relative to the race's.
+	fn speed_scale(&self) -> real {
+		self.character.speed() as real / self.character.race().speed.max(1) as real
+	}
+
+	/// Rolls the next swing with the character's own modifiers and hands it to the hit box.
+	fn roll_attack(&mut self) {
+		// a free off hand goes on the hilt too
+		let grip = match self.character.inventory().equipped_in(EquipmentSlot::OffHand) {
+			Some(_) => Grip::OneHanded,
+			None => Grip::TwoHanded,
+		};
+		let options = AttackOptions::default().with_grip(grip).with_mode(self.character.attack_mode());
+		let attack = roll_attack(&self.character.check_context(), &self.weapon(), options, &mut rand::thread_rng())
+			.expect("melee swings are always within reach");
+		let mut hit_box = self.base().get_node_as::<HitBox>("Sprite2D/HitBox");
+		hit_box.bind_mut().set_effects(Effects::new(vec![Effect::Attack(attack)]));
+	}
+
+	/// Walks one frame along the heading of the last command.
+	fn advance(&mut self) {
+		let position = self.base().get_global_position();
+		let direction = self.state.heading.direction(position);
+		let velocity = if direction.length() > 0.0 {
+			self.action = Action::Walk;
+			direction.normalized() * self.speed * self.speed_scale() * self.conditions().speed_multiplier() as real
+		} else {
+			self.action = Action::Idle;
+			Vector2::ZERO
+		};
+		self.base_mut().set_velocity(velocity);
+		self.set_direction(velocity);
+		self.base_mut().move_and_slide();
+	}
+
 	fn set_direction(&mut self, dir: Vector2) {
 		let mut animation_tree = self.base().get_node_as::<AnimationTree>("AnimationTree");
 		animation_tree.set("parameters/Attack/blend_position".into(), dir.to_variant());
@@ -50,20 +417,81 @@ impl ICharacterBody2D for Warrior {
 			speed: 100 as real,
 			action: Action::Idle,
 			state: State {
-				hp: 100,
 				attack_cool_down: AttackCoolDown::new(1.0),
+				death_save_in: ROUND_SECONDS,
+				heading: Heading::Still,
 			},
+			character: Footman::builder("Warrior", RaceType::Human, ClassType::Fighter)
+				.alignment(Alignment::new(Moral::Good, Ethical::Lawful))
+				.faction("Kingdom")
+				.equip(EquipmentSlot::MainHand, catalog().item("Longsword").expect("catalog has a longsword").clone())
+				.equip(EquipmentSlot::Armor, catalog().item("Scale Mail").expect("catalog has scale mail").clone())
+				.item(catalog().item("Potion of Healing").expect("catalog has healing potions").clone(), 2)
+				.build(),
 			base,
 		}
 	}
 
-	fn ready(&mut self) {}
+	fn ready(&mut self) {
+		self.roll_attack();
+	}
 
 	fn process(&mut self, delta: f64) {
 		self.state.attack_cool_down.update(delta);
+		self.process_dying(delta);
+		for expired in self.character.conditions_mut().tick(delta) {
+			self.base_mut().emit_signal("condition_removed".into(), &[expired.to_string().to_variant()]);
+		}
 		let mut debug = self.base().get_node_as::<Label>("Debug");
-		debug.set_text(format!("state: {:?}", self.state).into());
-		self.process_input()
+		debug.set_text(format!("state: {:?}, hp: {}/{} ({:?}), level: {}, xp: {}, ac: {}, load: {} lb ({})", self.state,
+			self.character.health(), self.character.max_health(), self.character.life(),
+			self.character.level(), self.character.experience().xp(), self.character.armor_class(),
+			self.character.inventory().weight(), self.character.encumbrance()).into());
+		if !self.conditions().can_act() || !self.conditions().can_move() {
+			if self.action == Action::Walk {
+				self.action = Action::Idle;
+			}
+			self.base_mut().set_velocity(Vector2::ZERO);
+			return
+		}
+		self.process_input();
+		if self.is_accept_input() {
+			self.advance();
+		}
+	}
+}
+
+impl Executor for Warrior {
+	fn move_to(&mut self, destination: Vector2) {
+		self.state.heading = Heading::To(destination);
+	}
+
+	fn walk(&mut self, direction: Vector2) {
+		self.state.heading = Heading::Direction(direction);
+	}
+
+	fn attack(&mut self, attack: Attack) {
+		let direction = match attack.target {
+			AttackTarget::LockOn(target) => target.get_global_position() - self.base().get_global_position(),
+			AttackTarget::Direction(direction) => direction,
+			AttackTarget::Point(point) => point - self.base().get_global_position(),
+		};
+		self.state.heading = Heading::Still;
+		if direction.length() > 0.0 {
+			self.set_direction(direction);
+		}
+		self.attack_pressed();
+	}
+
+	fn use_skill(&mut self, name: &str, target: AttackTarget) {
+		if let Some(mut caster) = self.base().try_get_node_as::<SkillCaster>("SkillCaster") {
+			// failures are reported through the caster's cast_failed signal
+			let _ = caster.bind_mut().cast(name, target, self.conditions());
+		}
+	}
+
+	fn stop(&mut self) {
+		self.state.heading = Heading::Still;
 	}
 }
 
@@ -84,8 +512,9 @@ impl InputAction for Warrior {
 	}
 
 	fn attack_pressed(&mut self) {
-		if self.state.attack_cool_down.ready() {
+		if self.state.attack_cool_down.ready() && self.conditions().can_attack() {
 			self.state.attack_cool_down.reset();
+			self.roll_attack();
 			let mut animation_tree = self.base().get_node_as::<AnimationTree>("AnimationTree");
 			let mut state_machine: Gd<AnimationNodeStateMachinePlayback> = animation_tree.get("parameters/playback".into()).to();
 			state_machine.travel(Action::Attack.to_godot().into());
@@ -97,34 +526,31 @@ impl InputAction for Warrior {
 			return
 		}
 		let input = Input::singleton();
-		if input.is_action_pressed("attack".into()) {
-			self.attack_pressed();
-			return
+		let skill = SKILL_ACTIONS.iter()
+			.position(|action| input.is_action_just_pressed((*action).into()))
+			.and_then(|slot| self.base().try_get_node_as::<SkillCaster>("SkillCaster")?.bind().skill_name(slot));
+		let command = if input.is_action_pressed("attack".into()) {
+			// whichever way the warrior already faces
+			Command::Attack(Attack::towards(Vector2::ZERO))
+		} else if let Some(name) = skill {
+			// aimed with the mouse
+			Command::Skill { name, target: AttackTarget::Point(self.base().get_global_mouse_position()) }
 		} else {
-			let mut velocity = Vector2::ZERO;
+			let mut direction = Vector2::ZERO;
 			if input.is_action_pressed("move_right".into()) {
-				velocity += Vector2::RIGHT;
+				direction += Vector2::RIGHT;
 			}
 			if input.is_action_pressed("move_left".into()) {
-				velocity += Vector2::LEFT;
+				direction += Vector2::LEFT;
 			}
 			if input.is_action_pressed("move_down".into()) {
-				velocity += Vector2::DOWN;
+				direction += Vector2::DOWN;
 			}
 			if input.is_action_pressed("move_up".into()) {
-				velocity += Vector2::UP;
-			}
-
-			if velocity.length() > 0.0 {
-				velocity = velocity.normalized() * self.speed;
-				self.action = Action::Walk;
-			} else {
-				self.action = Action::Idle;
+				direction += Vector2::UP;
 			}
-
-			self.base_mut().set_velocity(velocity);
-			self.set_direction(velocity);
-			self.base_mut().move_and_slide();
-		}
+			Command::Move(Move::Direction(direction))
+		};
+		self.execute(command);
 	}
 }
\ No newline at end of file
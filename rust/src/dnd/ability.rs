@@ -1,7 +1,9 @@
 use std::ops::Add;
 use std::fmt::Display;
+use serde::{Deserialize, Serialize};
+use crate::dnd::enums::AbilityType;
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
 pub struct Ability {
     pub(crate) strength: u8,
     pub(crate) dexterity: u8,
@@ -35,3 +37,63 @@ impl Display for Ability {
                self.wisdom, self.charisma, self.hit_points)
     }
 }
+
+
+impl Ability {
+    pub fn score(&self, ability: AbilityType) -> u8 {
+        match ability {
+            AbilityType::Strength => self.strength,
+            AbilityType::Dexterity => self.dexterity,
+            AbilityType::Constitution => self.constitution,
+            AbilityType::Intelligence => self.intelligence,
+            AbilityType::Wisdom => self.wisdom,
+            AbilityType::Charisma => self.charisma,
+        }
+    }
+
+    pub fn score_mut(&mut self, ability: AbilityType) -> &mut u8 {
+        match ability {
+            AbilityType::Strength => &mut self.strength,
+            AbilityType::Dexterity => &mut self.dexterity,
+            AbilityType::Constitution => &mut self.constitution,
+            AbilityType::Intelligence => &mut self.intelligence,
+            AbilityType::Wisdom => &mut self.wisdom,
+            AbilityType::Charisma => &mut self.charisma,
+        }
+    }
+
+    pub fn modifier(&self, ability: AbilityType) -> i32 {
+        modifier(self.score(ability))
+    }
+}
+
+/// The standard `(score - 10) / 2` modifier, rounded down so that a score of 9 gives -1.
+pub fn modifier(score: u8) -> i32 {
+    (score as i32 - 10).div_euclid(2)
+}
+
+pub fn proficiency_bonus(level: u32) -> i32 {
+    2 + (level.clamp(1, 20) as i32 - 1) / 4
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_modifier() {
+        assert_eq!(modifier(1), -5);
+        assert_eq!(modifier(8), -1);
+        assert_eq!(modifier(9), -1);
+        assert_eq!(modifier(10), 0);
+        assert_eq!(modifier(11), 0);
+        assert_eq!(modifier(15), 2);
+        assert_eq!(modifier(20), 5);
+    }
+
+    #[test]
+    fn test_proficiency_bonus() {
+        let bonuses: Vec<_> = [1, 4, 5, 8, 9, 12, 13, 16, 17, 20].iter().map(|&l| proficiency_bonus(l)).collect();
+        assert_eq!(bonuses, vec![2, 2, 3, 3, 4, 4, 5, 5, 6, 6]);
+    }
+}
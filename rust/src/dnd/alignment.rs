@@ -1,12 +1,25 @@
 use std::fmt::{Display, Formatter};
+use std::str::FromStr;
+use serde::{Deserialize, Serialize};
 
-#[derive(Debug, Clone, PartialEq, Eq)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
 pub enum Moral {
     Good,
     Neutral,
     Evil,
 }
 
+impl Moral {
+    /// Position on the good-evil axis, from 1 for good to -1 for evil.
+    pub fn axis(&self) -> i32 {
+        match self {
+            Moral::Good => 1,
+            Moral::Neutral => 0,
+            Moral::Evil => -1,
+        }
+    }
+}
+
 impl Display for Moral {
     fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
         match self {
@@ -17,13 +30,24 @@ impl Display for Moral {
     }
 }
 
-#[derive(Debug, Clone, PartialEq, Eq)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
 pub enum Ethical {
     Lawful,
     Neutral,
     Chaotic,
 }
 
+impl Ethical {
+    /// Position on the law-chaos axis, from 1 for lawful to -1 for chaotic.
+    pub fn axis(&self) -> i32 {
+        match self {
+            Ethical::Lawful => 1,
+            Ethical::Neutral => 0,
+            Ethical::Chaotic => -1,
+        }
+    }
+}
+
 impl Display for Ethical {
     fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
         match self {
@@ -34,15 +58,19 @@ impl Display for Ethical {
     }
 }
 
-#[derive(Debug, Clone, PartialEq, Eq)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
 pub struct Alignment {
     moral: Moral,
     ethical: Ethical,
 }
 
+/// The usual wording, e.g. "Chaotic Evil", "Lawful Neutral" or "True Neutral".
 impl Display for Alignment {
     fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
-        write!(f, "{:?} {:?}", self.moral, self.ethical)
+        match (self.ethical, self.moral) {
+            (Ethical::Neutral, Moral::Neutral) => write!(f, "True Neutral"),
+            (ethical, moral) => write!(f, "{} {}", ethical, moral),
+        }
     }
 }
 
@@ -50,4 +78,61 @@ impl Alignment {
     pub fn new(moral: Moral, ethical: Ethical) -> Self {
         Alignment { moral, ethical }
     }
+
+    pub fn moral(&self) -> Moral {
+        self.moral
+    }
+
+    pub fn ethical(&self) -> Ethical {
+        self.ethical
+    }
+
+    /// Steps between two alignments on the 3x3 grid, from 0 for the same alignment to 4 for
+    /// opposite corners like lawful good and chaotic evil.
+    pub fn distance(&self, other: &Alignment) -> u32 {
+        self.moral.axis().abs_diff(other.moral.axis()) + self.ethical.axis().abs_diff(other.ethical.axis())
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct UnknownAlignment(pub String);
+
+impl Display for UnknownAlignment {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "unknown alignment {:?}", self.0)
+    }
+}
+
+impl std::error::Error for UnknownAlignment {}
+
+/// Parses the `Display` form, e.g. "Chaotic Evil", and takes the words the other way round as
+/// well. A lone "Neutral" is neutral on both axes.
+impl FromStr for Alignment {
+    type Err = UnknownAlignment;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let moral = |word: &str| match word.to_ascii_lowercase().as_str() {
+            "good" => Some(Moral::Good),
+            "neutral" => Some(Moral::Neutral),
+            "evil" => Some(Moral::Evil),
+            _ => None,
+        };
+        let ethical = |word: &str| match word.to_ascii_lowercase().as_str() {
+            "lawful" => Some(Ethical::Lawful),
+            "neutral" => Some(Ethical::Neutral),
+            "chaotic" => Some(Ethical::Chaotic),
+            _ => None,
+        };
+        let words: Vec<&str> = s.split_whitespace().collect();
+        let alignment = match words.as_slice() {
+            [word] if word.eq_ignore_ascii_case("neutral") => Some(Alignment::new(Moral::Neutral, Ethical::Neutral)),
+            [t, n] if t.eq_ignore_ascii_case("true") && n.eq_ignore_ascii_case("neutral") =>
+                Some(Alignment::new(Moral::Neutral, Ethical::Neutral)),
+            [first, second] => ethical(first).zip(moral(second))
+                .or_else(|| ethical(second).zip(moral(first)))
+                .map(|(e, m)| Alignment::new(m, e)),
+            _ => None,
+        };
+        alignment.ok_or_else(|| UnknownAlignment(s.to_string()))
+    }
 }
\ No newline at end of file
@@ -0,0 +1,287 @@
+use std::fmt::{Display, Formatter};
+use rand::Rng;
+use crate::dnd::ability::proficiency_bonus;
+use crate::dnd::check::CheckContext;
+use crate::dnd::condition::Condition;
+use crate::dnd::dice::{DiceExpr, Roll, RollMode};
+use crate::dnd::enums::AbilityType;
+use crate::interactable::effect::Damage;
+use crate::tools::weapon::{Grip, MELEE_REACH, Weapon};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum AttackOutcome {
+    CriticalMiss,
+    Miss,
+    Hit,
+    CriticalHit,
+}
+
+impl AttackOutcome {
+    pub fn is_hit(&self) -> bool {
+        matches!(self, AttackOutcome::Hit | AttackOutcome::CriticalHit)
+    }
+}
+
+/// How the weapon is used for a single attack.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct AttackOptions {
+    pub mode: RollMode,
+    pub grip: Grip,
+    /// Distance to the target in feet, `0` is treated as adjacent.
+    pub distance: u32,
+    /// A bonus action attack with the off hand weapon, which adds no positive modifier to damage.
+    pub off_hand: bool,
+}
+
+impl AttackOptions {
+    pub fn with_mode(mut self, mode: RollMode) -> Self {
+        self.mode = mode;
+        self
+    }
+
+    pub fn with_grip(mut self, grip: Grip) -> Self {
+        self.grip = grip;
+        self
+    }
+
+    pub fn at_distance(mut self, distance: u32) -> Self {
+        self.distance = distance;
+        self
+    }
+
+    pub fn with_off_hand(mut self) -> Self {
+        self.off_hand = true;
+        self
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AttackError {
+    OutOfRange { distance: u32, range: u32 },
+}
+
+impl Display for AttackError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            AttackError::OutOfRange { distance, range } =>
+                write!(f, "target is {} ft away but the weapon only reaches {} ft", distance, range),
+        }
+    }
+}
+
+impl std::error::Error for AttackError {}
+
+/// The attacker's half of an attack: the d20 and the damage for both a hit and a critical hit.
+/// It is rolled when the swing starts and resolved once the target, and so its armor class,
+/// is known.
+#[derive(Debug, Clone, PartialEq)]
+pub struct AttackRoll {
+    pub roll: Roll,
+    pub ability_modifier: i32,
+    pub proficiency_bonus: i32,
+    pub total: i32,
+    pub damage_roll: Roll,
+    pub critical_damage_roll: Roll,
+    /// Put on the target by a hit, e.g. by a spell attack.
+    pub condition: Option<Condition>,
+}
+
+impl AttackRoll {
+    pub fn against(&self, armor_class: u8) -> AttackResult {
+        let outcome = match self.roll.natural() {
+            Some(20) => AttackOutcome::CriticalHit,
+            Some(1) => AttackOutcome::CriticalMiss,
+            _ if self.total >= armor_class as i32 => AttackOutcome::Hit,
+            _ => AttackOutcome::Miss,
+        };
+        let damage = match outcome {
+            AttackOutcome::CriticalHit => self.critical_damage_roll.total.max(0),
+            AttackOutcome::Hit => self.damage_roll.total.max(0),
+            _ => 0,
+        };
+        AttackResult { attack: self.clone(), armor_class, outcome, damage }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct AttackResult {
+    pub attack: AttackRoll,
+    pub armor_class: u8,
+    pub outcome: AttackOutcome,
+    pub damage: i32,
+}
+
+impl AttackResult {
+    pub fn is_hit(&self) -> bool {
+        self.outcome.is_hit()
+    }
+
+    pub fn damage(&self) -> Option<Damage> {
+        self.is_hit().then_some(Damage { amount: self.damage })
+    }
+
+    pub fn condition(&self) -> Option<Condition> {
+        self.attack.condition.filter(|_| self.is_hit())
+    }
+}
+
+impl Display for AttackResult {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{} {:+} (ability) {:+} (proficiency) = {} vs AC {}: {:?}",
+               self.attack.roll.natural().unwrap_or_default(), self.attack.ability_modifier,
+               self.attack.proficiency_bonus, self.attack.total, self.armor_class, self.outcome)?;
+        if self.is_hit() {
+            write!(f, ", {} damage", self.damage)?;
+        }
+        Ok(())
+    }
+}
+
+/// Ability and proficiency modifiers of an attack. Finesse weapons use whichever of strength and
+/// dexterity is better.
+pub fn attack_bonus<W: Weapon + ?Sized>(attacker: &CheckContext, weapon: &W) -> (i32, i32) {
+    let ability_modifier = if weapon.is_finesse() {
+        attacker.ability.modifier(AbilityType::Strength)
+            .max(attacker.ability.modifier(AbilityType::Dexterity))
+    } else {
+        attacker.ability.modifier(weapon.ability())
+    };
+    let proficiency_bonus = if attacker.proficiencies.has_weapon(weapon.r#type()) {
+        proficiency_bonus(attacker.level)
+    } else {
+        0
+    };
+    (ability_modifier, proficiency_bonus)
+}
+
+/// The roll mode the distance imposes: melee weapons attack normally within reach, thrown and
+/// ranged weapons have disadvantage beyond their normal range.
+pub fn range_mode<W: Weapon + ?Sized>(weapon: &W, distance: u32) -> Result<RollMode, AttackError> {
+    if weapon.is_melee() && distance <= weapon.reach() {
+        return Ok(RollMode::Normal)
+    }
+    match weapon.range_band() {
+        Some(band) if distance <= band.normal => Ok(RollMode::Normal),
+        Some(band) if distance <= band.long => Ok(RollMode::Disadvantage),
+        Some(band) => Err(AttackError::OutOfRange { distance, range: band.long }),
+        None if distance <= weapon.range().max(MELEE_REACH) => Ok(RollMode::Normal),
+        None => Err(AttackError::OutOfRange { distance, range: weapon.range().max(MELEE_REACH) }),
+    }
+}
+
+pub fn roll_attack<W, R>(attacker: &CheckContext, weapon: &W, options: AttackOptions, rng: &mut R) -> Result<AttackRoll, AttackError>
+where
+    W: Weapon + ?Sized,
+    R: Rng + ?Sized,
+{
+    let mode = options.mode.combine(range_mode(weapon, options.distance)?);
+    let grip = if weapon.is_two_handed() { Grip::TwoHanded } else { options.grip };
+    let (ability_modifier, proficiency_bonus) = attack_bonus(attacker, weapon);
+    let roll = DiceExpr::d20(mode).roll(rng);
+    let damage_modifier = if options.off_hand { ability_modifier.min(0) } else { ability_modifier };
+    let damage = weapon.damage_with(grip).plus(damage_modifier);
+    Ok(AttackRoll {
+        total: roll.total + ability_modifier + proficiency_bonus,
+        roll,
+        ability_modifier,
+        proficiency_bonus,
+        damage_roll: damage.roll(rng),
+        critical_damage_roll: damage.doubled_dice().roll(rng),
+        condition: None,
+    })
+}
+
+pub fn resolve_attack<W, R>(attacker: &CheckContext, weapon: &W, armor_class: u8, options: AttackOptions, rng: &mut R) -> Result<AttackResult, AttackError>
+where
+    W: Weapon + ?Sized,
+    R: Rng + ?Sized,
+{
+    Ok(roll_attack(attacker, weapon, options, rng)?.against(armor_class))
+}
+
+#[cfg(test)]
+mod test {
+    use rand::rngs::StdRng;
+    use rand::SeedableRng;
+    use crate::dnd::ability::Ability;
+    use crate::dnd::catalog::Catalog;
+    use crate::dnd::dice::Dice;
+    use crate::dnd::enums::{Proficiencies, WeaponType};
+    use super::*;
+
+    struct Mace;
+
+    impl Weapon for Mace {
+        fn r#type(&self) -> WeaponType {
+            WeaponType::SimpleMelee
+        }
+
+        fn damage(&self) -> DiceExpr {
+            DiceExpr::new(Dice::new(1, 6))
+        }
+
+        fn range(&self) -> u32 {
+            5
+        }
+    }
+
+    #[test]
+    fn test_resolve_attack() {
+        let ability = Ability { strength: 16, ..Default::default() };
+        let mut proficiencies = Proficiencies::empty();
+        let attacker = CheckContext::new(&ability, &proficiencies, 1);
+        assert_eq!(attack_bonus(&attacker, &Mace), (3, 0));
+        proficiencies.add_weapon(WeaponType::SimpleMelee);
+        let attacker = CheckContext::new(&ability, &proficiencies, 1);
+        assert_eq!(attack_bonus(&attacker, &Mace), (3, 2));
+
+        let mut rng = StdRng::seed_from_u64(11);
+        let mut seen_critical = false;
+        for _ in 0..500 {
+            let result = resolve_attack(&attacker, &Mace, 15, AttackOptions::default(), &mut rng).unwrap();
+            let natural = result.attack.roll.natural().unwrap();
+            assert_eq!(result.attack.total, natural as i32 + 5);
+            match natural {
+                20 => {
+                    seen_critical = true;
+                    assert_eq!(result.outcome, AttackOutcome::CriticalHit);
+                    assert_eq!(result.attack.critical_damage_roll.dice().count(), 2);
+                    assert!((5..=15).contains(&result.damage));
+                }
+                1 => assert_eq!(result.outcome, AttackOutcome::CriticalMiss),
+                n if n + 5 >= 15 => {
+                    assert_eq!(result.outcome, AttackOutcome::Hit);
+                    assert_eq!(result.damage().unwrap().amount, result.attack.damage_roll.total);
+                }
+                _ => assert_eq!(result.damage(), None),
+            }
+        }
+        assert!(seen_critical);
+    }
+
+    #[test]
+    fn test_weapon_properties() {
+        let catalog = Catalog::builtin();
+        let ability = Ability { strength: 10, dexterity: 16, ..Default::default() };
+        let proficiencies = Proficiencies::empty();
+        let attacker = CheckContext::new(&ability, &proficiencies, 1);
+        assert_eq!(attack_bonus(&attacker, catalog.weapon("Rapier").unwrap()), (3, 0));
+        assert_eq!(attack_bonus(&attacker, catalog.weapon("Longsword").unwrap()), (0, 0));
+
+        let dagger = catalog.weapon("Dagger").unwrap();
+        assert_eq!(range_mode(dagger, 5), Ok(RollMode::Normal));
+        assert_eq!(range_mode(dagger, 20), Ok(RollMode::Normal));
+        assert_eq!(range_mode(dagger, 40), Ok(RollMode::Disadvantage));
+        assert_eq!(range_mode(dagger, 61), Err(AttackError::OutOfRange { distance: 61, range: 60 }));
+        assert!(range_mode(catalog.weapon("Longsword").unwrap(), 10).is_err());
+        assert_eq!(range_mode(catalog.weapon("Glaive").unwrap(), 10), Ok(RollMode::Normal));
+
+        let mut rng = StdRng::seed_from_u64(3);
+        let longsword = catalog.weapon("Longsword").unwrap();
+        let options = AttackOptions::default().with_grip(Grip::TwoHanded);
+        let attack = roll_attack(&attacker, longsword, options, &mut rng).unwrap();
+        assert_eq!(attack.damage_roll.dice().next().unwrap().sides, 10);
+        let attack = roll_attack(&attacker, dagger, AttackOptions::default().at_distance(40), &mut rng).unwrap();
+        assert_eq!(attack.roll.dice().count(), 2);
+    }
+}
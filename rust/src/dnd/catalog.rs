@@ -0,0 +1,488 @@
+use std::collections::BTreeMap;
+use std::fmt::{Display, Formatter};
+use std::path::Path;
+use std::sync::OnceLock;
+use serde::{Deserialize, Serialize};
+use crate::ai::behavior::Skill;
+use crate::dnd::ability::Ability;
+use crate::dnd::enums::{AbilityType, ArmorType, ClassType, Proficiencies, RaceType, ShieldType, Size, SkillType, WeaponType};
+use crate::dnd::reaction::Faction;
+use crate::dnd::spell::{Delivery, MAX_SPELL_LEVEL, SpellData};
+use crate::tools::armor::{ArmorData, ShieldData};
+use crate::tools::inventory::{ItemData, ItemKind};
+use crate::tools::weapon::{Weapon, WeaponData, WeaponProperty};
+
+const BUILTIN: &str = include_str!("../../../godot/data/catalog.ron");
+
+static CATALOG: OnceLock<Catalog> = OnceLock::new();
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct RaceData {
+    pub race: RaceType,
+    pub size: Size,
+    pub speed: u8,
+    pub ability_bonuses: BTreeMap<AbilityType, u8>,
+}
+
+impl RaceData {
+    pub fn ability_modifier(&self) -> Ability {
+        let bonus = |ability| self.ability_bonuses.get(&ability).copied().unwrap_or_default();
+        Ability {
+            strength: bonus(AbilityType::Strength),
+            dexterity: bonus(AbilityType::Dexterity),
+            constitution: bonus(AbilityType::Constitution),
+            intelligence: bonus(AbilityType::Intelligence),
+            wisdom: bonus(AbilityType::Wisdom),
+            charisma: bonus(AbilityType::Charisma),
+            hit_points: 0,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Default, Serialize, Deserialize)]
+pub struct SkillChoices {
+    pub count: u8,
+    pub from: Vec<SkillType>,
+}
+
+impl SkillChoices {
+    pub fn allows(&self, skills: &[SkillType]) -> bool {
+        skills.len() <= self.count as usize && skills.iter().all(|skill| self.from.contains(skill))
+    }
+}
+
+/// What it takes to multiclass into a class, and what a character gets out of it.
+#[derive(Debug, Clone, PartialEq, Eq, Default, Serialize, Deserialize)]
+pub struct MulticlassData {
+    /// Minimum ability scores, meeting any one of the sets is enough. Also needed to leave the
+    /// class for another one.
+    #[serde(default)]
+    pub prerequisites: Vec<BTreeMap<AbilityType, u8>>,
+    #[serde(default)]
+    pub armor: Vec<ArmorType>,
+    #[serde(default)]
+    pub weapons: Vec<WeaponType>,
+    #[serde(default)]
+    pub shields: Vec<ShieldType>,
+    /// Skills picked from the class's skill choices.
+    #[serde(default)]
+    pub skills: u8,
+}
+
+impl MulticlassData {
+    pub fn allows(&self, ability: &Ability) -> bool {
+        self.prerequisites.is_empty() || self.prerequisites.iter()
+            .any(|scores| scores.iter().all(|(&a, &minimum)| ability.score(a) >= minimum))
+    }
+
+    /// The proficiencies gained by multiclassing, never any saving throws.
+    pub fn proficiencies(&self) -> Proficiencies {
+        let mut proficiencies = Proficiencies::empty();
+        self.armor.iter().for_each(|&a| proficiencies.add_armor(a));
+        self.weapons.iter().for_each(|&w| proficiencies.add_weapon(w));
+        self.shields.iter().for_each(|&s| proficiencies.add_shield(s));
+        proficiencies
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ClassData {
+    pub class: ClassType,
+    pub hit_die: u32,
+    pub saving_throws: Vec<AbilityType>,
+    pub armor: Vec<ArmorType>,
+    pub weapons: Vec<WeaponType>,
+    pub shields: Vec<ShieldType>,
+    pub skill_choices: SkillChoices,
+    #[serde(default)]
+    pub multiclass: MulticlassData,
+}
+
+impl ClassData {
+    pub fn proficiencies(&self) -> Proficiencies {
+        let mut proficiencies = Proficiencies::empty();
+        self.saving_throws.iter().for_each(|&a| proficiencies.add_saving_throw(a));
+        self.armor.iter().for_each(|&a| proficiencies.add_armor(a));
+        self.weapons.iter().for_each(|&w| proficiencies.add_weapon(w));
+        self.shields.iter().for_each(|&s| proficiencies.add_shield(s));
+        proficiencies
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Catalog {
+    races: Vec<RaceData>,
+    classes: Vec<ClassData>,
+    #[serde(default)]
+    weapons: Vec<WeaponData>,
+    #[serde(default)]
+    armor: Vec<ArmorData>,
+    #[serde(default)]
+    shields: Vec<ShieldData>,
+    #[serde(default)]
+    spells: Vec<SpellData>,
+    #[serde(default)]
+    factions: Vec<Faction>,
+    #[serde(default)]
+    items: Vec<ItemData>,
+    #[serde(default)]
+    skills: Vec<Skill>,
+}
+
+#[derive(Debug)]
+pub enum CatalogError {
+    Io(std::io::Error),
+    Parse(ron::error::SpannedError),
+    MissingRace(RaceType),
+    MissingClass(ClassType),
+    DuplicateRace(RaceType),
+    DuplicateClass(ClassType),
+    InvalidHitDie { class: ClassType, hit_die: u32 },
+    DuplicateWeapon(String),
+    InvalidWeapon { weapon: String, reason: &'static str },
+    DuplicateArmor(String),
+    MissingShield(ShieldType),
+    DuplicateShield(ShieldType),
+    DuplicateSpell(String),
+    InvalidSpell { spell: String, reason: &'static str },
+    DuplicateFaction(String),
+    UnknownFaction { faction: String, referenced_by: String },
+    DuplicateItem(String),
+    InvalidItem { item: String, reason: &'static str },
+    DuplicateSkill(String),
+    InvalidSkill { skill: String, reason: &'static str },
+}
+
+impl Display for CatalogError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            CatalogError::Io(err) => write!(f, "failed to read catalog: {}", err),
+            CatalogError::Parse(err) => write!(f, "failed to parse catalog: {}", err),
+            CatalogError::MissingRace(race) => write!(f, "catalog has no entry for race {}", race),
+            CatalogError::MissingClass(class) => write!(f, "catalog has no entry for class {}", class),
+            CatalogError::DuplicateRace(race) => write!(f, "catalog defines race {} more than once", race),
+            CatalogError::DuplicateClass(class) => write!(f, "catalog defines class {} more than once", class),
+            CatalogError::InvalidHitDie { class, hit_die } =>
+                write!(f, "class {} has an invalid hit die d{}", class, hit_die),
+            CatalogError::DuplicateWeapon(weapon) => write!(f, "catalog defines weapon {} more than once", weapon),
+            CatalogError::InvalidWeapon { weapon, reason } => write!(f, "weapon {} {}", weapon, reason),
+            CatalogError::DuplicateArmor(armor) => write!(f, "catalog defines armor {} more than once", armor),
+            CatalogError::MissingShield(shield) => write!(f, "catalog has no entry for shield {:?}", shield),
+            CatalogError::DuplicateShield(shield) => write!(f, "catalog defines shield {:?} more than once", shield),
+            CatalogError::DuplicateSpell(spell) => write!(f, "catalog defines spell {} more than once", spell),
+            CatalogError::InvalidSpell { spell, reason } => write!(f, "spell {} {}", spell, reason),
+            CatalogError::DuplicateFaction(faction) => write!(f, "catalog defines faction {} more than once", faction),
+            CatalogError::UnknownFaction { faction, referenced_by } =>
+                write!(f, "faction {} refers to unknown faction {}", referenced_by, faction),
+            CatalogError::DuplicateItem(item) => write!(f, "catalog defines item {} more than once", item),
+            CatalogError::InvalidItem { item, reason } => write!(f, "item {} {}", item, reason),
+            CatalogError::DuplicateSkill(skill) => write!(f, "catalog defines skill {} more than once", skill),
+            CatalogError::InvalidSkill { skill, reason } => write!(f, "skill {} {}", skill, reason),
+        }
+    }
+}
+
+impl std::error::Error for CatalogError {}
+
+impl Catalog {
+    pub fn from_ron(src: &str) -> Result<Self, CatalogError> {
+        let catalog: Catalog = ron::from_str(src).map_err(CatalogError::Parse)?;
+        catalog.validate()?;
+        Ok(catalog)
+    }
+
+    pub fn load(path: impl AsRef<Path>) -> Result<Self, CatalogError> {
+        let src = std::fs::read_to_string(path).map_err(CatalogError::Io)?;
+        Catalog::from_ron(&src)
+    }
+
+    pub fn builtin() -> Self {
+        Catalog::from_ron(BUILTIN).expect("built-in catalog is invalid")
+    }
+
+    fn validate(&self) -> Result<(), CatalogError> {
+        for race in RaceType::ALL {
+            match self.races.iter().filter(|r| r.race == race).count() {
+                0 => return Err(CatalogError::MissingRace(race)),
+                1 => {}
+                _ => return Err(CatalogError::DuplicateRace(race)),
+            }
+        }
+        for class in ClassType::ALL {
+            match self.classes.iter().filter(|c| c.class == class).count() {
+                0 => return Err(CatalogError::MissingClass(class)),
+                1 => {}
+                _ => return Err(CatalogError::DuplicateClass(class)),
+            }
+        }
+        if let Some(c) = self.classes.iter().find(|c| c.hit_die < 2) {
+            return Err(CatalogError::InvalidHitDie { class: c.class, hit_die: c.hit_die })
+        }
+        for (i, weapon) in self.weapons.iter().enumerate() {
+            if self.weapons[..i].iter().any(|w| w.name.eq_ignore_ascii_case(&weapon.name)) {
+                return Err(CatalogError::DuplicateWeapon(weapon.name.clone()))
+            }
+            Catalog::validate_weapon(weapon)
+                .map_err(|reason| CatalogError::InvalidWeapon { weapon: weapon.name.clone(), reason })?;
+        }
+        for (i, armor) in self.armor.iter().enumerate() {
+            if self.armor[..i].iter().any(|a| a.name.eq_ignore_ascii_case(&armor.name)) {
+                return Err(CatalogError::DuplicateArmor(armor.name.clone()))
+            }
+        }
+        for shield in ShieldType::ALL {
+            match self.shields.iter().filter(|s| s.shield == shield).count() {
+                0 => return Err(CatalogError::MissingShield(shield)),
+                1 => {}
+                _ => return Err(CatalogError::DuplicateShield(shield)),
+            }
+        }
+        for (i, spell) in self.spells.iter().enumerate() {
+            if self.spells[..i].iter().any(|s| s.name.eq_ignore_ascii_case(&spell.name)) {
+                return Err(CatalogError::DuplicateSpell(spell.name.clone()))
+            }
+            Catalog::validate_spell(spell)
+                .map_err(|reason| CatalogError::InvalidSpell { spell: spell.name.clone(), reason })?;
+        }
+        for (i, faction) in self.factions.iter().enumerate() {
+            if self.factions[..i].iter().any(|f| f.name.eq_ignore_ascii_case(&faction.name)) {
+                return Err(CatalogError::DuplicateFaction(faction.name.clone()))
+            }
+            if let Some(unknown) = faction.allies.iter().chain(&faction.enemies).find(|name| self.faction(name).is_none()) {
+                return Err(CatalogError::UnknownFaction { faction: unknown.clone(), referenced_by: faction.name.clone() })
+            }
+        }
+        for (i, item) in self.items.iter().enumerate() {
+            if self.items[..i].iter().any(|it| it.name.eq_ignore_ascii_case(&item.name)) {
+                return Err(CatalogError::DuplicateItem(item.name.clone()))
+            }
+            self.validate_item(item)
+                .map_err(|reason| CatalogError::InvalidItem { item: item.name.clone(), reason })?;
+        }
+        for (i, skill) in self.skills.iter().enumerate() {
+            if self.skills[..i].iter().any(|s| s.name.eq_ignore_ascii_case(&skill.name)) {
+                return Err(CatalogError::DuplicateSkill(skill.name.clone()))
+            }
+            Catalog::validate_skill(skill)
+                .map_err(|reason| CatalogError::InvalidSkill { skill: skill.name.clone(), reason })?;
+        }
+        Ok(())
+    }
+
+    fn validate_skill(skill: &Skill) -> Result<(), &'static str> {
+        if skill.cooldown.is_nan() || skill.cooldown < 0.0 {
+            return Err("has a negative cool down")
+        }
+        if skill.cast_time.is_nan() || skill.cast_time < 0.0 {
+            return Err("has a negative cast time")
+        }
+        if skill.skill_types.is_empty() {
+            return Err("does nothing")
+        }
+        Ok(())
+    }
+
+    fn validate_item(&self, item: &ItemData) -> Result<(), &'static str> {
+        if item.weight.is_nan() || item.weight < 0.0 {
+            return Err("has a negative weight")
+        }
+        if item.max_stack == 0 {
+            return Err("cannot be stacked at all")
+        }
+        match item.kind {
+            ItemKind::Weapon if self.weapon(&item.name).is_none() => Err("is not a weapon in the catalog"),
+            ItemKind::Armor if self.armor(&item.name).is_none() => Err("is not armor in the catalog"),
+            _ => Ok(()),
+        }
+    }
+
+    fn validate_spell(spell: &SpellData) -> Result<(), &'static str> {
+        if spell.level > MAX_SPELL_LEVEL {
+            return Err("is above the highest spell level")
+        }
+        if spell.delivery == Delivery::Attack && spell.damage.is_none() {
+            return Err("is a spell attack without damage")
+        }
+        if spell.classes.is_empty() {
+            return Err("is not on any class list")
+        }
+        Ok(())
+    }
+
+    fn validate_weapon(weapon: &WeaponData) -> Result<(), &'static str> {
+        if !weapon.is_melee() && weapon.range_band().is_none() {
+            return Err("is a ranged weapon without a range")
+        }
+        if let Some(band) = weapon.range_band() {
+            if band.normal > band.long {
+                return Err("has a normal range beyond its long range")
+            }
+        }
+        let versatile = weapon.properties.iter().any(|p| matches!(p, WeaponProperty::Versatile(_)));
+        if versatile && weapon.is_two_handed() {
+            return Err("cannot be both versatile and two-handed")
+        }
+        Ok(())
+    }
+
+    pub fn race(&self, race: RaceType) -> &RaceData {
+        self.races.iter()
+            .find(|r| r.race == race)
+            .expect("validated catalog covers every race")
+    }
+
+    pub fn class(&self, class: ClassType) -> &ClassData {
+        self.classes.iter()
+            .find(|c| c.class == class)
+            .expect("validated catalog covers every class")
+    }
+
+    pub fn races(&self) -> &[RaceData] {
+        &self.races
+    }
+
+    pub fn classes(&self) -> &[ClassData] {
+        &self.classes
+    }
+
+    /// Looks a weapon up by name, ignoring case.
+    pub fn weapon(&self, name: &str) -> Option<&WeaponData> {
+        self.weapons.iter().find(|w| w.name.eq_ignore_ascii_case(name))
+    }
+
+    pub fn weapons(&self) -> &[WeaponData] {
+        &self.weapons
+    }
+
+    /// Looks armor up by name, ignoring case.
+    pub fn armor(&self, name: &str) -> Option<&ArmorData> {
+        self.armor.iter().find(|a| a.name.eq_ignore_ascii_case(name))
+    }
+
+    pub fn armors(&self) -> &[ArmorData] {
+        &self.armor
+    }
+
+    pub fn shield(&self, shield: ShieldType) -> &ShieldData {
+        self.shields.iter()
+            .find(|s| s.shield == shield)
+            .expect("validated catalog covers every shield")
+    }
+
+    pub fn shields(&self) -> &[ShieldData] {
+        &self.shields
+    }
+
+    /// Looks a spell up by name, ignoring case.
+    pub fn spell(&self, name: &str) -> Option<&SpellData> {
+        self.spells.iter().find(|s| s.name.eq_ignore_ascii_case(name))
+    }
+
+    pub fn spells(&self) -> &[SpellData] {
+        &self.spells
+    }
+
+    pub fn faction(&self, name: &str) -> Option<&Faction> {
+        self.factions.iter().find(|f| f.name.eq_ignore_ascii_case(name))
+    }
+
+    pub fn factions(&self) -> &[Faction] {
+        &self.factions
+    }
+
+    /// Looks an item up by name, ignoring case.
+    pub fn item(&self, name: &str) -> Option<&ItemData> {
+        self.items.iter().find(|i| i.name.eq_ignore_ascii_case(name))
+    }
+
+    pub fn items(&self) -> &[ItemData] {
+        &self.items
+    }
+
+    /// Looks a skill up by name, ignoring case.
+    pub fn skill(&self, name: &str) -> Option<&Skill> {
+        self.skills.iter().find(|s| s.name.eq_ignore_ascii_case(name))
+    }
+
+    pub fn skills(&self) -> &[Skill] {
+        &self.skills
+    }
+}
+
+/// Makes `catalog` the one used by the rest of the dnd module. Only the first call wins, so this
+/// has to happen before any character is created; the rejected catalog is handed back otherwise.
+pub fn install(catalog: Catalog) -> Result<(), Box<Catalog>> {
+    CATALOG.set(catalog).map_err(Box::new)
+}
+
+pub fn catalog() -> &'static Catalog {
+    CATALOG.get_or_init(Catalog::builtin)
+}
+
+#[cfg(test)]
+mod test {
+    use crate::ai::skill::Targeting;
+    use crate::tools::weapon::{Grip, RangeBand};
+    use super::*;
+
+    #[test]
+    fn test_builtin_catalog() {
+        let catalog = Catalog::builtin();
+        assert_eq!(catalog.races().len(), RaceType::ALL.len());
+        assert_eq!(catalog.classes().len(), ClassType::ALL.len());
+
+        let dwarf = catalog.race(RaceType::Dwarf);
+        assert_eq!((dwarf.size, dwarf.speed), (Size::Medium, 25));
+        assert_eq!(dwarf.ability_modifier().constitution, 2);
+
+        let rogue = catalog.class(ClassType::Rogue);
+        let proficiencies = rogue.proficiencies();
+        assert_eq!(rogue.hit_die, 8);
+        assert!(proficiencies.has_saving_throw(AbilityType::Dexterity));
+        assert!(proficiencies.has_armor(ArmorType::Light));
+        assert!(!proficiencies.has_armor(ArmorType::Heavy));
+        assert!(rogue.skill_choices.allows(&[SkillType::Stealth, SkillType::Perception]));
+        assert!(!rogue.skill_choices.allows(&[SkillType::Arcana]));
+
+        let goblins = catalog.faction("goblins").unwrap();
+        assert!(goblins.is_enemy("Kingdom"));
+        assert!(goblins.is_ally("Goblins"));
+        assert!(!goblins.is_enemy("Merchants"));
+    }
+
+    #[test]
+    fn test_invalid_catalog() {
+        let err = Catalog::from_ron("(races: [], classes: [])").unwrap_err();
+        assert!(matches!(err, CatalogError::MissingRace(RaceType::Dwarf)));
+        assert!(matches!(Catalog::from_ron("(races: [(race: Orc)])"), Err(CatalogError::Parse(_))));
+    }
+
+    #[test]
+    fn test_weapons() {
+        let catalog = Catalog::builtin();
+        let longsword = catalog.weapon("longsword").unwrap();
+        assert_eq!(longsword.category, WeaponType::MartialMelee);
+        assert_eq!(longsword.damage_with(Grip::OneHanded).to_string(), "1d8");
+        assert_eq!(longsword.damage_with(Grip::TwoHanded).to_string(), "1d10");
+
+        let dagger = catalog.weapon("Dagger").unwrap();
+        assert!(dagger.is_finesse());
+        assert_eq!(dagger.range_band(), Some(RangeBand { normal: 20, long: 60 }));
+        assert_eq!(dagger.range(), 5);
+        assert_eq!(catalog.weapon("Longbow").unwrap().range(), 150);
+        assert_eq!(catalog.weapon("Halberd").unwrap().reach(), 10);
+        assert!(catalog.weapon("Lightsaber").is_none());
+    }
+
+    #[test]
+    fn test_skills() {
+        let catalog = Catalog::builtin();
+        let fireball = catalog.skill("fireball").unwrap();
+        assert!(matches!(fireball.targeting, Targeting::Area { .. }));
+        assert!(fireball.cast_time > 0.0);
+        assert!(catalog.skills().iter().all(|s| s.elapsed == 0.0));
+
+        let src = BUILTIN.replace("cooldown: 30.0", "cooldown: -30.0");
+        assert!(matches!(Catalog::from_ron(&src), Err(CatalogError::InvalidSkill { .. })));
+    }
+}
@@ -0,0 +1,131 @@
+use godot::prelude::*;
+use rand::rngs::StdRng;
+use rand::SeedableRng;
+use crate::dnd::ability::Ability;
+use crate::dnd::generation::{ABILITY_ORDER, assign_rolled, point_buy, point_buy_remaining, roll_scores, standard_array, GenerationError};
+
+/// Drives the ability score part of a character creation screen. Scores are passed around as
+/// arrays of six ints in strength, dexterity, constitution, intelligence, wisdom, charisma order.
+#[derive(GodotClass)]
+#[class(base=RefCounted)]
+pub struct CharacterCreation {
+    #[var]
+    last_error: GString,
+    ability: Option<Ability>,
+    rolled: Option<[u8; 6]>,
+    rng: StdRng,
+    base: Base<RefCounted>,
+}
+
+#[godot_api]
+impl CharacterCreation {
+    #[func]
+    fn seed(&mut self, seed: i64) {
+        self.rng = StdRng::seed_from_u64(seed as u64);
+    }
+
+    #[func]
+    fn point_buy(&mut self, scores: PackedInt32Array) -> bool {
+        let result = to_scores(&scores).and_then(point_buy);
+        self.accept(result)
+    }
+
+    #[func]
+    fn point_buy_remaining(&self, scores: PackedInt32Array) -> i32 {
+        to_scores(&scores).ok()
+            .and_then(point_buy_remaining)
+            .unwrap_or(-1)
+    }
+
+    #[func]
+    fn standard_array(&mut self, scores: PackedInt32Array) -> bool {
+        let result = to_scores(&scores).and_then(standard_array);
+        self.accept(result)
+    }
+
+    /// Rolls 4d6 drop lowest six times and returns the totals, which then have to be placed with
+    /// `assign_rolled`.
+    #[func]
+    fn roll(&mut self) -> PackedInt32Array {
+        let rolls = roll_scores(&mut self.rng);
+        for roll in rolls.iter() {
+            tracing::debug!("rolled {}", roll);
+        }
+        let rolled = std::array::from_fn(|i| rolls[i].total as u8);
+        self.rolled = Some(rolled);
+        let totals: Vec<i32> = rolled.iter().map(|&s| s as i32).collect();
+        PackedInt32Array::from(totals.as_slice())
+    }
+
+    #[func]
+    fn assign_rolled(&mut self, scores: PackedInt32Array) -> bool {
+        let Some(rolled) = self.rolled else {
+            self.last_error = "nothing has been rolled yet".into();
+            return false
+        };
+        let result = to_scores(&scores).and_then(|scores| assign_rolled(scores, rolled));
+        self.accept(result)
+    }
+
+    #[func]
+    fn get_scores(&self) -> PackedInt32Array {
+        let Some(ability) = &self.ability else {
+            return PackedInt32Array::new()
+        };
+        let scores: Vec<i32> = ABILITY_ORDER.iter().map(|&a| ability.score(a) as i32).collect();
+        PackedInt32Array::from(scores.as_slice())
+    }
+
+    #[func]
+    fn get_modifiers(&self) -> PackedInt32Array {
+        let Some(ability) = &self.ability else {
+            return PackedInt32Array::new()
+        };
+        let modifiers: Vec<i32> = ABILITY_ORDER.iter().map(|&a| ability.modifier(a)).collect();
+        PackedInt32Array::from(modifiers.as_slice())
+    }
+
+    pub fn get_ability(&self) -> Option<Ability> {
+        self.ability.clone()
+    }
+
+    fn accept(&mut self, result: Result<Ability, GenerationError>) -> bool {
+        match result {
+            Ok(ability) => {
+                self.ability = Some(ability);
+                self.last_error = GString::new();
+                true
+            }
+            Err(err) => {
+                self.last_error = err.to_string().into();
+                false
+            }
+        }
+    }
+}
+
+#[godot_api]
+impl IRefCounted for CharacterCreation {
+    fn init(base: Base<RefCounted>) -> Self {
+        CharacterCreation {
+            last_error: GString::new(),
+            ability: None,
+            rolled: None,
+            rng: StdRng::from_entropy(),
+            base,
+        }
+    }
+}
+
+fn to_scores(scores: &PackedInt32Array) -> Result<[u8; 6], GenerationError> {
+    let scores = scores.to_vec();
+    let invalid = || GenerationError::InvalidScores(scores.clone());
+    if scores.len() != 6 {
+        return Err(invalid())
+    }
+    let mut result = [0u8; 6];
+    for (slot, &score) in result.iter_mut().zip(scores.iter()) {
+        *slot = u8::try_from(score).map_err(|_| invalid())?;
+    }
+    Ok(result)
+}
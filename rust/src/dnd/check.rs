@@ -0,0 +1,194 @@
+use std::fmt::{Display, Formatter};
+use rand::Rng;
+use crate::dnd::ability::{Ability, proficiency_bonus};
+use crate::dnd::dice::{DiceExpr, Roll, RollMode};
+use crate::dnd::enums::{AbilityType, Proficiencies, SkillType};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum CheckKind {
+    Ability(AbilityType),
+    SavingThrow(AbilityType),
+    Skill(SkillType),
+}
+
+impl CheckKind {
+    pub fn ability(&self) -> AbilityType {
+        match self {
+            CheckKind::Ability(ability) | CheckKind::SavingThrow(ability) => *ability,
+            CheckKind::Skill(skill) => skill.ability(),
+        }
+    }
+}
+
+impl Display for CheckKind {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            CheckKind::Ability(ability) => write!(f, "{:?} check", ability),
+            CheckKind::SavingThrow(ability) => write!(f, "{:?} saving throw", ability),
+            CheckKind::Skill(skill) => write!(f, "{:?} ({:?}) check", skill, skill.ability()),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct Check {
+    pub kind: CheckKind,
+    pub dc: i32,
+    pub mode: RollMode,
+}
+
+impl Check {
+    pub fn ability(ability: AbilityType, dc: i32) -> Self {
+        Check { kind: CheckKind::Ability(ability), dc, mode: RollMode::Normal }
+    }
+
+    pub fn saving_throw(ability: AbilityType, dc: i32) -> Self {
+        Check { kind: CheckKind::SavingThrow(ability), dc, mode: RollMode::Normal }
+    }
+
+    pub fn skill(skill: SkillType, dc: i32) -> Self {
+        Check { kind: CheckKind::Skill(skill), dc, mode: RollMode::Normal }
+    }
+
+    pub fn with_mode(mut self, mode: RollMode) -> Self {
+        self.mode = mode;
+        self
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CheckResult {
+    pub check: Check,
+    pub roll: Roll,
+    pub ability_modifier: i32,
+    pub proficiency_bonus: i32,
+    pub total: i32,
+    pub success: bool,
+}
+
+impl Display for CheckResult {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{} DC {}: {} {:+} (ability) {:+} (proficiency) = {} -> {}",
+               self.check.kind, self.check.dc, self.roll.natural().unwrap_or_default(),
+               self.ability_modifier, self.proficiency_bonus, self.total,
+               if self.success { "success" } else { "failure" })
+    }
+}
+
+/// Everything a creature brings to a check: its scores, what it is proficient in and its level.
+#[derive(Debug, Clone, Copy)]
+pub struct CheckContext<'a> {
+    pub ability: &'a Ability,
+    pub proficiencies: &'a Proficiencies,
+    pub level: u32,
+}
+
+impl<'a> CheckContext<'a> {
+    pub fn new(ability: &'a Ability, proficiencies: &'a Proficiencies, level: u32) -> Self {
+        CheckContext { ability, proficiencies, level }
+    }
+
+    pub fn is_proficient(&self, kind: CheckKind) -> bool {
+        match kind {
+            CheckKind::Ability(_) => false,
+            CheckKind::SavingThrow(ability) => self.proficiencies.has_saving_throw(ability),
+            CheckKind::Skill(skill) => self.proficiencies.has_skill(skill),
+        }
+    }
+
+    pub fn proficiency_bonus(&self, kind: CheckKind) -> i32 {
+        if self.is_proficient(kind) {
+            proficiency_bonus(self.level)
+        } else {
+            0
+        }
+    }
+
+    pub fn bonus(&self, kind: CheckKind) -> i32 {
+        self.ability.modifier(kind.ability()) + self.proficiency_bonus(kind)
+    }
+
+    pub fn resolve<R: Rng + ?Sized>(&self, check: Check, rng: &mut R) -> CheckResult {
+        let roll = DiceExpr::d20(check.mode).roll(rng);
+        self.resolve_roll(check, roll)
+    }
+
+    /// Resolves a check against a d20 that was already rolled, e.g. by the UI or over the network.
+    pub fn resolve_roll(&self, check: Check, roll: Roll) -> CheckResult {
+        let ability_modifier = self.ability.modifier(check.kind.ability());
+        let proficiency_bonus = self.proficiency_bonus(check.kind);
+        let total = roll.total + ability_modifier + proficiency_bonus;
+        CheckResult {
+            check,
+            roll,
+            ability_modifier,
+            proficiency_bonus,
+            total,
+            success: total >= check.dc,
+        }
+    }
+
+    /// 10 + all modifiers, with advantage and disadvantage counting as +5 and -5.
+    pub fn passive(&self, skill: SkillType, mode: RollMode) -> i32 {
+        let situational = match mode {
+            RollMode::Normal => 0,
+            RollMode::Advantage => 5,
+            RollMode::Disadvantage => -5,
+        };
+        10 + self.bonus(CheckKind::Skill(skill)) + situational
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use rand::rngs::StdRng;
+    use rand::SeedableRng;
+    use super::*;
+
+    fn rogue() -> (Ability, Proficiencies) {
+        let ability = Ability {
+            strength: 8,
+            dexterity: 16,
+            constitution: 12,
+            intelligence: 13,
+            wisdom: 10,
+            charisma: 14,
+            hit_points: 0,
+        };
+        let mut proficiencies = Proficiencies::empty();
+        proficiencies.add_saving_throw(AbilityType::Dexterity);
+        proficiencies.add_skill(SkillType::Stealth);
+        proficiencies.add_skill(SkillType::Perception);
+        (ability, proficiencies)
+    }
+
+    #[test]
+    fn test_bonus() {
+        let (ability, proficiencies) = rogue();
+        let ctx = CheckContext::new(&ability, &proficiencies, 5);
+        assert_eq!(ctx.bonus(CheckKind::Skill(SkillType::Stealth)), 3 + 3);
+        assert_eq!(ctx.bonus(CheckKind::Skill(SkillType::Athletics)), -1);
+        assert_eq!(ctx.bonus(CheckKind::SavingThrow(AbilityType::Dexterity)), 6);
+        assert_eq!(ctx.bonus(CheckKind::SavingThrow(AbilityType::Strength)), -1);
+        assert_eq!(ctx.bonus(CheckKind::Ability(AbilityType::Dexterity)), 3);
+        assert_eq!(ctx.passive(SkillType::Perception, RollMode::Normal), 13);
+        assert_eq!(ctx.passive(SkillType::Perception, RollMode::Disadvantage), 8);
+    }
+
+    #[test]
+    fn test_resolve() {
+        let (ability, proficiencies) = rogue();
+        let ctx = CheckContext::new(&ability, &proficiencies, 1);
+        let mut rng = StdRng::seed_from_u64(3);
+        let check = Check::skill(SkillType::Stealth, 15).with_mode(RollMode::Advantage);
+        for _ in 0..50 {
+            let result = ctx.resolve(check, &mut rng);
+            let natural = result.roll.natural().unwrap() as i32;
+            assert_eq!(result.ability_modifier, 3);
+            assert_eq!(result.proficiency_bonus, 2);
+            assert_eq!(result.total, natural + 5);
+            assert_eq!(result.success, natural + 5 >= 15);
+            assert_eq!(result.roll.dice().count(), 2);
+        }
+    }
+}
@@ -0,0 +1,346 @@
+use std::collections::BTreeSet;
+use std::fmt::{Display, Formatter};
+use std::str::FromStr;
+use serde::{Deserialize, Serialize};
+use crate::dnd::check::CheckKind;
+use crate::dnd::dice::RollMode;
+use crate::dnd::enums::AbilityType;
+
+/// Length of a combat round in seconds, used to turn round based durations into real time.
+pub const ROUND_SECONDS: f64 = 6.0;
+
+pub const MAX_EXHAUSTION: u32 = 6;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+pub enum ConditionType {
+    Blinded,
+    Charmed,
+    Deafened,
+    Exhaustion,
+    Frightened,
+    Grappled,
+    Incapacitated,
+    Invisible,
+    Paralyzed,
+    Petrified,
+    Poisoned,
+    Prone,
+    Restrained,
+    Silenced,
+    Slowed,
+    Stunned,
+    Unconscious,
+}
+
+/// What happens when a condition is applied while it is already active.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Stacking {
+    /// Keeps a single instance with the longer of both durations.
+    Refresh,
+    /// Adds a level, up to `max`, and keeps the longer duration.
+    Stack { max: u32 },
+}
+
+impl ConditionType {
+    pub const ALL: [ConditionType; 17] = [
+        ConditionType::Blinded,
+        ConditionType::Charmed,
+        ConditionType::Deafened,
+        ConditionType::Exhaustion,
+        ConditionType::Frightened,
+        ConditionType::Grappled,
+        ConditionType::Incapacitated,
+        ConditionType::Invisible,
+        ConditionType::Paralyzed,
+        ConditionType::Petrified,
+        ConditionType::Poisoned,
+        ConditionType::Prone,
+        ConditionType::Restrained,
+        ConditionType::Silenced,
+        ConditionType::Slowed,
+        ConditionType::Stunned,
+        ConditionType::Unconscious,
+    ];
+
+    pub fn stacking(&self) -> Stacking {
+        match self {
+            ConditionType::Exhaustion => Stacking::Stack { max: MAX_EXHAUSTION },
+            _ => Stacking::Refresh,
+        }
+    }
+
+    /// Conditions that include being incapacitated.
+    pub fn incapacitates(&self) -> bool {
+        matches!(
+            self,
+            ConditionType::Incapacitated | ConditionType::Paralyzed | ConditionType::Petrified
+                | ConditionType::Stunned | ConditionType::Unconscious
+        )
+    }
+}
+
+impl Display for ConditionType {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", format!("{:?}", self).to_lowercase())
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct UnknownCondition(pub String);
+
+impl Display for UnknownCondition {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "unknown condition {:?}", self.0)
+    }
+}
+
+impl std::error::Error for UnknownCondition {}
+
+impl FromStr for ConditionType {
+    type Err = UnknownCondition;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        ConditionType::ALL.into_iter()
+            .find(|condition| condition.to_string().eq_ignore_ascii_case(s))
+            .ok_or_else(|| UnknownCondition(s.to_string()))
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct Condition {
+    pub kind: ConditionType,
+    /// Remaining time in seconds, `None` until it is removed explicitly.
+    pub remaining: Option<f64>,
+    /// Exhaustion level, `1` for conditions that do not stack.
+    pub level: u32,
+}
+
+impl Condition {
+    pub fn new(kind: ConditionType) -> Self {
+        Condition { kind, remaining: None, level: 1 }
+    }
+
+    pub fn for_seconds(kind: ConditionType, seconds: f64) -> Self {
+        Condition { remaining: Some(seconds.max(0.0)), ..Condition::new(kind) }
+    }
+
+    pub fn for_rounds(kind: ConditionType, rounds: u32) -> Self {
+        Condition::for_seconds(kind, rounds as f64 * ROUND_SECONDS)
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ApplyOutcome {
+    Applied,
+    Refreshed,
+    Stacked { level: u32 },
+    Immune,
+}
+
+/// The conditions currently affecting a creature and the ones it is immune to.
+#[derive(Debug, Clone, PartialEq, Default, Serialize, Deserialize)]
+pub struct Conditions {
+    active: Vec<Condition>,
+    immunities: BTreeSet<ConditionType>,
+}
+
+impl Conditions {
+    pub fn with_immunities(immunities: impl IntoIterator<Item = ConditionType>) -> Self {
+        Conditions { active: Vec::new(), immunities: immunities.into_iter().collect() }
+    }
+
+    pub fn is_immune(&self, kind: ConditionType) -> bool {
+        self.immunities.contains(&kind)
+    }
+
+    pub fn add_immunity(&mut self, kind: ConditionType) {
+        self.immunities.insert(kind);
+        self.remove(kind);
+    }
+
+    pub fn apply(&mut self, condition: Condition) -> ApplyOutcome {
+        if self.is_immune(condition.kind) {
+            return ApplyOutcome::Immune
+        }
+        let Some(active) = self.active.iter_mut().find(|c| c.kind == condition.kind) else {
+            self.active.push(condition);
+            return ApplyOutcome::Applied
+        };
+        active.remaining = match (active.remaining, condition.remaining) {
+            (Some(a), Some(b)) => Some(a.max(b)),
+            _ => None,
+        };
+        match condition.kind.stacking() {
+            Stacking::Refresh => ApplyOutcome::Refreshed,
+            Stacking::Stack { max } => {
+                active.level = (active.level + condition.level).min(max);
+                ApplyOutcome::Stacked { level: active.level }
+            }
+        }
+    }
+
+    pub fn remove(&mut self, kind: ConditionType) -> Option<Condition> {
+        let index = self.active.iter().position(|c| c.kind == kind)?;
+        Some(self.active.remove(index))
+    }
+
+    /// Advances durations by `delta` seconds and returns the conditions that ran out.
+    pub fn tick(&mut self, delta: f64) -> Vec<ConditionType> {
+        let mut expired = Vec::new();
+        self.active.retain_mut(|condition| {
+            let Some(remaining) = condition.remaining.as_mut() else {
+                return true
+            };
+            *remaining -= delta;
+            if *remaining > 0.0 {
+                return true
+            }
+            expired.push(condition.kind);
+            false
+        });
+        expired
+    }
+
+    pub fn active(&self) -> &[Condition] {
+        &self.active
+    }
+
+    pub fn get(&self, kind: ConditionType) -> Option<&Condition> {
+        self.active.iter().find(|c| c.kind == kind)
+    }
+
+    /// Whether the condition applies, either directly or because another one implies it.
+    pub fn has(&self, kind: ConditionType) -> bool {
+        match kind {
+            ConditionType::Incapacitated => self.active.iter().any(|c| c.kind.incapacitates()),
+            _ => self.get(kind).is_some(),
+        }
+    }
+
+    pub fn exhaustion(&self) -> u32 {
+        self.get(ConditionType::Exhaustion).map_or(0, |c| c.level)
+    }
+
+    pub fn can_act(&self) -> bool {
+        !self.has(ConditionType::Incapacitated) && self.exhaustion() < MAX_EXHAUSTION
+    }
+
+    pub fn can_attack(&self) -> bool {
+        self.can_act() && !self.has(ConditionType::Charmed)
+    }
+
+    pub fn can_use_skills(&self) -> bool {
+        self.can_act() && !self.has(ConditionType::Silenced)
+    }
+
+    pub fn can_move(&self) -> bool {
+        self.speed_multiplier() > 0.0
+    }
+
+    pub fn speed_multiplier(&self) -> f64 {
+        let held = [
+            ConditionType::Grappled, ConditionType::Restrained, ConditionType::Paralyzed,
+            ConditionType::Petrified, ConditionType::Stunned, ConditionType::Unconscious,
+        ];
+        if held.into_iter().any(|kind| self.has(kind)) || self.exhaustion() >= 5 {
+            return 0.0
+        }
+        let mut multiplier = 1.0;
+        if self.has(ConditionType::Slowed) {
+            multiplier *= 0.5;
+        }
+        if self.exhaustion() >= 2 {
+            multiplier *= 0.5;
+        }
+        multiplier
+    }
+
+    /// Roll mode for attacks this creature makes.
+    pub fn attack_mode(&self) -> RollMode {
+        let disadvantage = [
+            ConditionType::Blinded, ConditionType::Frightened, ConditionType::Poisoned,
+            ConditionType::Prone, ConditionType::Restrained,
+        ].into_iter().any(|kind| self.has(kind)) || self.exhaustion() >= 3;
+        let advantage = self.has(ConditionType::Invisible);
+        Self::mode(advantage, disadvantage)
+    }
+
+    /// Roll mode for attacks made against this creature.
+    pub fn defense_mode(&self, melee: bool) -> RollMode {
+        let advantage = [
+            ConditionType::Blinded, ConditionType::Paralyzed, ConditionType::Petrified,
+            ConditionType::Restrained, ConditionType::Stunned, ConditionType::Unconscious,
+        ].into_iter().any(|kind| self.has(kind)) || (melee && self.has(ConditionType::Prone));
+        let disadvantage = self.has(ConditionType::Invisible) || (!melee && self.has(ConditionType::Prone));
+        Self::mode(advantage, disadvantage)
+    }
+
+    pub fn check_mode(&self, kind: CheckKind) -> RollMode {
+        let disadvantage = match kind {
+            CheckKind::Ability(_) | CheckKind::Skill(_) =>
+                self.has(ConditionType::Frightened) || self.has(ConditionType::Poisoned) || self.exhaustion() >= 1,
+            CheckKind::SavingThrow(ability) =>
+                (ability == AbilityType::Dexterity && self.has(ConditionType::Restrained)) || self.exhaustion() >= 3,
+        };
+        Self::mode(false, disadvantage)
+    }
+
+    /// Paralyzed, petrified, stunned and unconscious creatures fail strength and dexterity saves.
+    pub fn auto_fails(&self, kind: CheckKind) -> bool {
+        let helpless = [
+            ConditionType::Paralyzed, ConditionType::Petrified, ConditionType::Stunned, ConditionType::Unconscious,
+        ].into_iter().any(|kind| self.has(kind));
+        helpless && matches!(kind, CheckKind::SavingThrow(AbilityType::Strength | AbilityType::Dexterity))
+    }
+
+    fn mode(advantage: bool, disadvantage: bool) -> RollMode {
+        match (advantage, disadvantage) {
+            (true, false) => RollMode::Advantage,
+            (false, true) => RollMode::Disadvantage,
+            _ => RollMode::Normal,
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_durations_and_stacking() {
+        let mut conditions = Conditions::with_immunities([ConditionType::Charmed]);
+        assert_eq!(conditions.apply(Condition::new(ConditionType::Charmed)), ApplyOutcome::Immune);
+        assert_eq!(conditions.apply(Condition::for_seconds(ConditionType::Stunned, 2.0)), ApplyOutcome::Applied);
+        assert_eq!(conditions.apply(Condition::for_rounds(ConditionType::Stunned, 1)), ApplyOutcome::Refreshed);
+        assert_eq!(conditions.get(ConditionType::Stunned).unwrap().remaining, Some(ROUND_SECONDS));
+        assert!(conditions.has(ConditionType::Incapacitated));
+        assert!(!conditions.can_attack());
+
+        conditions.apply(Condition::new(ConditionType::Exhaustion));
+        assert_eq!(conditions.apply(Condition::new(ConditionType::Exhaustion)), ApplyOutcome::Stacked { level: 2 });
+
+        assert!(conditions.tick(5.0).is_empty());
+        assert_eq!(conditions.tick(1.0), vec![ConditionType::Stunned]);
+        assert!(conditions.can_attack());
+        assert_eq!(conditions.speed_multiplier(), 0.5);
+        assert_eq!("Stunned".parse(), Ok(ConditionType::Stunned));
+    }
+
+    #[test]
+    fn test_roll_modes() {
+        let mut conditions = Conditions::default();
+        conditions.apply(Condition::new(ConditionType::Prone));
+        assert_eq!(conditions.attack_mode(), RollMode::Disadvantage);
+        assert_eq!(conditions.defense_mode(true), RollMode::Advantage);
+        assert_eq!(conditions.defense_mode(false), RollMode::Disadvantage);
+
+        conditions.apply(Condition::new(ConditionType::Silenced));
+        assert!(conditions.can_attack());
+        assert!(!conditions.can_use_skills());
+
+        conditions.apply(Condition::new(ConditionType::Paralyzed));
+        assert!(conditions.auto_fails(CheckKind::SavingThrow(AbilityType::Dexterity)));
+        assert!(!conditions.auto_fails(CheckKind::SavingThrow(AbilityType::Wisdom)));
+        assert!(!conditions.can_move());
+    }
+}
@@ -0,0 +1,257 @@
+use std::fmt::{Display, Formatter};
+use rand::Rng;
+use serde::{Deserialize, Serialize};
+use crate::dnd::check::{Check, CheckContext, CheckResult};
+use crate::dnd::dice::{DiceExpr, Roll, RollMode};
+use crate::dnd::enums::SkillType;
+
+pub const DEATH_SAVE_DC: i32 = 10;
+/// Successes that stabilize a dying creature, and failures that kill it.
+pub const DEATH_SAVES_NEEDED: u8 = 3;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+pub struct DeathSaves {
+    pub successes: u8,
+    pub failures: u8,
+}
+
+impl DeathSaves {
+    pub fn succeed(&mut self, count: u8) {
+        self.successes = (self.successes + count).min(DEATH_SAVES_NEEDED);
+    }
+
+    pub fn fail(&mut self, count: u8) {
+        self.failures = (self.failures + count).min(DEATH_SAVES_NEEDED);
+    }
+
+    pub fn is_stable(&self) -> bool {
+        self.successes >= DEATH_SAVES_NEEDED
+    }
+
+    pub fn is_dead(&self) -> bool {
+        self.failures >= DEATH_SAVES_NEEDED
+    }
+}
+
+/// Where a creature is between fighting fit and dead. Anything but `Conscious` means 0 hit points.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+pub enum LifeState {
+    #[default]
+    Conscious,
+    /// Unconscious and rolling death saving throws every turn.
+    Dying(DeathSaves),
+    /// Unconscious at 0 hit points, but no longer rolling death saving throws.
+    Stable,
+    Dead,
+}
+
+/// A change of [`LifeState`] worth telling the rest of the game about.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LifeEvent {
+    /// Dropped to 0 hit points and fell unconscious.
+    Downed,
+    Stabilized,
+    /// Back on its feet with some hit points.
+    Revived,
+    Died,
+}
+
+impl Display for LifeEvent {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            LifeEvent::Downed => write!(f, "downed"),
+            LifeEvent::Stabilized => write!(f, "stabilized"),
+            LifeEvent::Revived => write!(f, "revived"),
+            LifeEvent::Died => write!(f, "died"),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DeathSave {
+    pub roll: Roll,
+    pub success: bool,
+    /// The tally after this save. A natural 20 clears it, since the creature wakes up.
+    pub saves: DeathSaves,
+    pub event: Option<LifeEvent>,
+}
+
+impl Display for DeathSave {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "death saving throw: {} -> {} ({} successes, {} failures)",
+               self.roll.natural().unwrap_or_default(), if self.success { "success" } else { "failure" },
+               self.saves.successes, self.saves.failures)?;
+        if let Some(event) = self.event {
+            write!(f, ", {}", event)?;
+        }
+        Ok(())
+    }
+}
+
+impl LifeState {
+    pub fn is_conscious(&self) -> bool {
+        *self == LifeState::Conscious
+    }
+
+    pub fn is_dying(&self) -> bool {
+        matches!(self, LifeState::Dying(_))
+    }
+
+    pub fn is_dead(&self) -> bool {
+        *self == LifeState::Dead
+    }
+
+    /// Hit points just ran out. Damage left over after reaching 0 that is at least the hit point
+    /// maximum kills outright, anything less knocks the creature unconscious.
+    pub fn drop_to_zero(&mut self, overflow: u32, max_health: u32) -> LifeEvent {
+        if overflow >= max_health {
+            *self = LifeState::Dead;
+            LifeEvent::Died
+        } else {
+            *self = LifeState::Dying(DeathSaves::default());
+            LifeEvent::Downed
+        }
+    }
+
+    /// Damage taken while already at 0 hit points counts as a failed death saving throw, two if it
+    /// came from a critical hit. Massive damage still kills outright.
+    pub fn damage_at_zero(&mut self, amount: u32, max_health: u32, critical: bool) -> Option<LifeEvent> {
+        let mut saves = match *self {
+            LifeState::Conscious | LifeState::Dead => return None,
+            LifeState::Dying(saves) => saves,
+            // taking damage starts the saving throws all over again
+            LifeState::Stable => DeathSaves::default(),
+        };
+        saves.fail(if critical { 2 } else { 1 });
+        if amount >= max_health || saves.is_dead() {
+            *self = LifeState::Dead;
+            return Some(LifeEvent::Died)
+        }
+        *self = LifeState::Dying(saves);
+        None
+    }
+
+    /// Rolls this turn's death saving throw, `None` unless dying. On a natural 20 the creature
+    /// regains 1 hit point and the state goes back to `Conscious`, the caller has to heal it.
+    pub fn death_save<R: Rng + ?Sized>(&mut self, mode: RollMode, rng: &mut R) -> Option<DeathSave> {
+        let roll = DiceExpr::d20(mode).roll(rng);
+        self.resolve_death_save(roll)
+    }
+
+    /// Applies a death saving throw that was already rolled.
+    pub fn resolve_death_save(&mut self, roll: Roll) -> Option<DeathSave> {
+        let LifeState::Dying(mut saves) = *self else {
+            return None
+        };
+        let success = roll.total >= DEATH_SAVE_DC;
+        let event = if roll.is_critical() {
+            saves = DeathSaves::default();
+            *self = LifeState::Conscious;
+            Some(LifeEvent::Revived)
+        } else {
+            match (success, roll.is_fumble()) {
+                (true, _) => saves.succeed(1),
+                (false, true) => saves.fail(2),
+                (false, false) => saves.fail(1),
+            }
+            if saves.is_dead() {
+                *self = LifeState::Dead;
+                Some(LifeEvent::Died)
+            } else if saves.is_stable() {
+                *self = LifeState::Stable;
+                Some(LifeEvent::Stabilized)
+            } else {
+                *self = LifeState::Dying(saves);
+                None
+            }
+        };
+        Some(DeathSave { roll, success, saves, event })
+    }
+
+    /// Stops the death saving throws, e.g. from a successful Medicine check or Spare the Dying.
+    pub fn stabilize(&mut self) -> Option<LifeEvent> {
+        if !self.is_dying() {
+            return None
+        }
+        *self = LifeState::Stable;
+        Some(LifeEvent::Stabilized)
+    }
+
+    /// Any healing brings a creature at 0 hit points back, the dead stay dead.
+    pub fn heal(&mut self) -> Option<LifeEvent> {
+        match self {
+            LifeState::Dying(_) | LifeState::Stable => {
+                *self = LifeState::Conscious;
+                Some(LifeEvent::Revived)
+            }
+            LifeState::Conscious | LifeState::Dead => None,
+        }
+    }
+}
+
+/// The DC 10 Wisdom (Medicine) check to stabilize a dying creature without magic.
+pub fn medicine_check<R: Rng + ?Sized>(healer: &CheckContext, mode: RollMode, rng: &mut R) -> CheckResult {
+    healer.resolve(Check::skill(SkillType::Medicine, DEATH_SAVE_DC).with_mode(mode), rng)
+}
+
+#[cfg(test)]
+mod test {
+    use crate::dnd::dice::{Dice, DieRoll, Sign, TermRoll};
+    use super::*;
+
+    fn d20(natural: u32) -> Roll {
+        let die = DieRoll { sides: 20, value: natural, kept: true };
+        let term = TermRoll::Dice { sign: Sign::Plus, dice: Dice::d20(), rolls: vec![die], subtotal: natural as i32 };
+        Roll { terms: vec![term], total: natural as i32 }
+    }
+
+    #[test]
+    fn test_drop_to_zero() {
+        let mut life = LifeState::Conscious;
+        assert_eq!(life.drop_to_zero(5, 12), LifeEvent::Downed);
+        assert_eq!(life, LifeState::Dying(DeathSaves::default()));
+
+        let mut life = LifeState::Conscious;
+        assert_eq!(life.drop_to_zero(12, 12), LifeEvent::Died);
+        assert!(life.is_dead());
+
+        let mut life = LifeState::Stable;
+        assert_eq!(life.damage_at_zero(3, 12, false), None);
+        assert_eq!(life, LifeState::Dying(DeathSaves { successes: 0, failures: 1 }));
+        assert_eq!(life.damage_at_zero(3, 12, true), Some(LifeEvent::Died));
+
+        let mut life = LifeState::Dying(DeathSaves::default());
+        assert_eq!(life.damage_at_zero(12, 12, false), Some(LifeEvent::Died));
+    }
+
+    #[test]
+    fn test_death_saves() {
+        let mut life = LifeState::Conscious;
+        assert_eq!(life.resolve_death_save(d20(15)), None);
+
+        let mut life = LifeState::Dying(DeathSaves::default());
+        let save = life.resolve_death_save(d20(10)).unwrap();
+        assert!(save.success);
+        assert_eq!(save.event, None);
+        life.resolve_death_save(d20(1)).unwrap();
+        assert_eq!(life, LifeState::Dying(DeathSaves { successes: 1, failures: 2 }));
+        life.resolve_death_save(d20(12));
+        let save = life.resolve_death_save(d20(19)).unwrap();
+        assert_eq!(save.event, Some(LifeEvent::Stabilized));
+        assert_eq!(life, LifeState::Stable);
+        assert_eq!(life.resolve_death_save(d20(20)), None);
+
+        let mut life = LifeState::Dying(DeathSaves { successes: 0, failures: 2 });
+        let save = life.resolve_death_save(d20(20)).unwrap();
+        assert_eq!(save.event, Some(LifeEvent::Revived));
+        assert!(life.is_conscious());
+
+        let mut life = LifeState::Dying(DeathSaves { successes: 2, failures: 2 });
+        assert_eq!(life.resolve_death_save(d20(9)).unwrap().event, Some(LifeEvent::Died));
+        assert_eq!(life.heal(), None);
+
+        let mut life = LifeState::Dying(DeathSaves::default());
+        assert_eq!(life.stabilize(), Some(LifeEvent::Stabilized));
+        assert_eq!(life.heal(), Some(LifeEvent::Revived));
+    }
+}
@@ -0,0 +1,689 @@
+use std::fmt::{Display, Formatter};
+use std::str::FromStr;
+use rand::Rng;
+use serde::{Deserialize, Serialize};
+
+const MAX_DICE: u32 = 1000;
+const MAX_SIDES: u32 = 1000;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub enum RollMode {
+    #[default]
+    Normal,
+    Advantage,
+    Disadvantage,
+}
+
+impl RollMode {
+    /// Advantage and disadvantage cancel each other out, no matter how many sources grant them.
+    pub fn combine(self, other: RollMode) -> RollMode {
+        match (self, other) {
+            (RollMode::Normal, mode) | (mode, RollMode::Normal) => mode,
+            (a, b) if a == b => a,
+            _ => RollMode::Normal,
+        }
+    }
+}
+
+impl Display for RollMode {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            RollMode::Normal => Ok(()),
+            RollMode::Advantage => write!(f, "adv"),
+            RollMode::Disadvantage => write!(f, "dis"),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Keep {
+    All,
+    Highest(u32),
+    Lowest(u32),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct Dice {
+    pub count: u32,
+    pub sides: u32,
+    pub keep: Keep,
+}
+
+impl Dice {
+    pub fn new(count: u32, sides: u32) -> Self {
+        Dice { count, sides, keep: Keep::All }
+    }
+
+    pub fn d20() -> Self {
+        Dice::new(1, 20)
+    }
+
+    /// Keeps the `n` highest dice, all of them at most.
+    pub fn keep_highest(mut self, n: u32) -> Self {
+        self.keep = Keep::Highest(n.min(self.count));
+        self
+    }
+
+    /// Keeps the `n` lowest dice, all of them at most.
+    pub fn keep_lowest(mut self, n: u32) -> Self {
+        self.keep = Keep::Lowest(n.min(self.count));
+        self
+    }
+
+    pub fn average(&self) -> f64 {
+        let kept = match self.keep {
+            Keep::All => self.count,
+            Keep::Highest(n) | Keep::Lowest(n) => n,
+        };
+        kept as f64 * (self.sides as f64 + 1.0) / 2.0
+    }
+
+    /// Whether advantage and disadvantage apply to it, only a single d20 is rolled twice.
+    fn is_d20(&self) -> bool {
+        self.count == 1 && self.sides == 20 && self.keep == Keep::All
+    }
+
+    fn with_mode(self, mode: RollMode) -> Self {
+        match mode {
+            RollMode::Normal => self,
+            RollMode::Advantage => Dice::new(2, self.sides).keep_highest(1),
+            RollMode::Disadvantage => Dice::new(2, self.sides).keep_lowest(1),
+        }
+    }
+
+    fn roll<R: Rng + ?Sized>(&self, rng: &mut R) -> Vec<DieRoll> {
+        let mut rolls: Vec<DieRoll> = (0..self.count)
+            .map(|_| DieRoll { sides: self.sides, value: rng.gen_range(1..=self.sides), kept: true })
+            .collect();
+
+        let mut order: Vec<usize> = (0..rolls.len()).collect();
+        let drop = match self.keep {
+            Keep::All => 0,
+            Keep::Highest(n) => {
+                order.sort_by_key(|&i| rolls[i].value);
+                rolls.len() - n as usize
+            }
+            Keep::Lowest(n) => {
+                order.sort_by_key(|&i| std::cmp::Reverse(rolls[i].value));
+                rolls.len() - n as usize
+            }
+        };
+        for &i in order.iter().take(drop) {
+            rolls[i].kept = false;
+        }
+        rolls
+    }
+}
+
+impl Display for Dice {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}d{}", self.count, self.sides)?;
+        match self.keep {
+            Keep::All => Ok(()),
+            Keep::Highest(n) => write!(f, "kh{}", n),
+            Keep::Lowest(n) => write!(f, "kl{}", n),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Term {
+    Dice(Dice),
+    Modifier(i32),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Sign {
+    Plus,
+    Minus,
+}
+
+impl Sign {
+    fn apply(&self, value: i32) -> i32 {
+        match self {
+            Sign::Plus => value,
+            Sign::Minus => -value,
+        }
+    }
+}
+
+/// Serialized in its text form, e.g. `"2d6+3"`, so data files stay readable.
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(try_from = "String", into = "String")]
+pub struct DiceExpr {
+    terms: Vec<(Sign, Term)>,
+    mode: RollMode,
+}
+
+impl DiceExpr {
+    pub fn new(dice: Dice) -> Self {
+        DiceExpr {
+            terms: vec![(Sign::Plus, Term::Dice(dice))],
+            mode: RollMode::Normal,
+        }
+    }
+
+    pub fn d20(mode: RollMode) -> Self {
+        DiceExpr::new(Dice::d20()).with_mode(mode)
+    }
+
+    pub fn plus(mut self, modifier: i32) -> Self {
+        if modifier != 0 {
+            let sign = if modifier < 0 { Sign::Minus } else { Sign::Plus };
+            self.terms.push((sign, Term::Modifier(modifier.abs())));
+        }
+        self
+    }
+
+    pub fn plus_dice(mut self, dice: Dice) -> Self {
+        self.terms.push((Sign::Plus, Term::Dice(dice)));
+        self
+    }
+
+    pub fn with_mode(mut self, mode: RollMode) -> Self {
+        self.mode = mode;
+        self
+    }
+
+    pub fn mode(&self) -> RollMode {
+        self.mode
+    }
+
+    pub fn terms(&self) -> &[(Sign, Term)] {
+        &self.terms
+    }
+
+    /// Appends every term of `other`, its roll mode is ignored.
+    pub fn plus_expr(mut self, other: &DiceExpr) -> Self {
+        self.terms.extend(other.terms.iter().copied());
+        self
+    }
+
+    /// Rolls twice as many dice, modifiers are left untouched. This is how critical hits work.
+    pub fn doubled_dice(&self) -> Self {
+        self.multiplied_dice(2)
+    }
+
+    /// Rolls `factor` times as many dice, modifiers are left untouched.
+    pub fn multiplied_dice(&self, factor: u32) -> Self {
+        let terms = self.terms.iter()
+            .map(|(sign, term)| match term {
+                Term::Dice(dice) => {
+                    let keep = match dice.keep {
+                        Keep::All => Keep::All,
+                        Keep::Highest(n) => Keep::Highest(n * factor),
+                        Keep::Lowest(n) => Keep::Lowest(n * factor),
+                    };
+                    (*sign, Term::Dice(Dice { count: dice.count * factor, sides: dice.sides, keep }))
+                }
+                modifier => (*sign, *modifier),
+            })
+            .collect();
+        DiceExpr { terms, mode: self.mode }
+    }
+
+    pub fn min(&self) -> i32 {
+        self.bound(|dice| self.kept(dice) as i32)
+    }
+
+    pub fn max(&self) -> i32 {
+        self.bound(|dice| (self.kept(dice) * dice.sides) as i32)
+    }
+
+    fn kept(&self, dice: &Dice) -> u32 {
+        match dice.keep {
+            Keep::All => dice.count,
+            Keep::Highest(n) | Keep::Lowest(n) => n,
+        }
+    }
+
+    fn bound(&self, dice_value: impl Fn(&Dice) -> i32) -> i32 {
+        self.terms.iter()
+            .map(|(sign, term)| match term {
+                Term::Dice(dice) => sign.apply(dice_value(dice)),
+                Term::Modifier(m) => sign.apply(*m),
+            })
+            .sum()
+    }
+
+    pub fn roll<R: Rng + ?Sized>(&self, rng: &mut R) -> Roll {
+        let d20 = self.terms.iter().position(|(_, term)| matches!(term, Term::Dice(dice) if dice.is_d20()));
+        let terms: Vec<TermRoll> = self.terms.iter().enumerate()
+            .map(|(i, (sign, term))| match term {
+                Term::Dice(dice) => {
+                    let dice = if Some(i) == d20 { dice.with_mode(self.mode) } else { *dice };
+                    let rolls = dice.roll(rng);
+                    let subtotal = rolls.iter()
+                        .filter(|r| r.kept)
+                        .map(|r| r.value as i32)
+                        .sum();
+                    TermRoll::Dice { sign: *sign, dice, rolls, subtotal: sign.apply(subtotal) }
+                }
+                Term::Modifier(m) => TermRoll::Modifier(sign.apply(*m)),
+            })
+            .collect();
+        let total = terms.iter().map(TermRoll::subtotal).sum();
+        Roll { terms, total }
+    }
+}
+
+impl Display for DiceExpr {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        for (i, (sign, term)) in self.terms.iter().enumerate() {
+            match (i, sign) {
+                (0, Sign::Plus) => {}
+                (0, Sign::Minus) => write!(f, "-")?,
+                (_, Sign::Plus) => write!(f, "+")?,
+                (_, Sign::Minus) => write!(f, "-")?,
+            }
+            match term {
+                Term::Dice(dice) => write!(f, "{}", dice)?,
+                Term::Modifier(m) => write!(f, "{}", m)?,
+            }
+        }
+        if self.mode != RollMode::Normal {
+            write!(f, " {}", self.mode)?;
+        }
+        Ok(())
+    }
+}
+
+impl FromStr for DiceExpr {
+    type Err = DiceError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Parser::new(s).parse()
+    }
+}
+
+impl TryFrom<String> for DiceExpr {
+    type Error = DiceError;
+
+    fn try_from(value: String) -> Result<Self, Self::Error> {
+        value.parse()
+    }
+}
+
+impl From<DiceExpr> for String {
+    fn from(expr: DiceExpr) -> Self {
+        expr.to_string()
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct DieRoll {
+    pub sides: u32,
+    pub value: u32,
+    pub kept: bool,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum TermRoll {
+    Dice {
+        sign: Sign,
+        dice: Dice,
+        rolls: Vec<DieRoll>,
+        subtotal: i32,
+    },
+    Modifier(i32),
+}
+
+impl TermRoll {
+    pub fn subtotal(&self) -> i32 {
+        match self {
+            TermRoll::Dice { subtotal, .. } => *subtotal,
+            TermRoll::Modifier(m) => *m,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Roll {
+    pub terms: Vec<TermRoll>,
+    pub total: i32,
+}
+
+impl Roll {
+    pub fn dice(&self) -> impl Iterator<Item = &DieRoll> {
+        self.terms.iter()
+            .flat_map(|term| match term {
+                TermRoll::Dice { rolls, .. } => rolls.as_slice(),
+                TermRoll::Modifier(_) => &[],
+            })
+    }
+
+    pub fn modifier(&self) -> i32 {
+        self.terms.iter()
+            .map(|term| match term {
+                TermRoll::Modifier(m) => *m,
+                _ => 0,
+            })
+            .sum()
+    }
+
+    /// The kept face of the first d20 in the roll, if any.
+    pub fn natural(&self) -> Option<u32> {
+        self.dice()
+            .find(|d| d.sides == 20 && d.kept)
+            .map(|d| d.value)
+    }
+
+    pub fn is_critical(&self) -> bool {
+        self.natural() == Some(20)
+    }
+
+    pub fn is_fumble(&self) -> bool {
+        self.natural() == Some(1)
+    }
+}
+
+impl Display for Roll {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        for (i, term) in self.terms.iter().enumerate() {
+            if i > 0 {
+                write!(f, " ")?;
+            }
+            match term {
+                TermRoll::Dice { sign, rolls, .. } => {
+                    if *sign == Sign::Minus {
+                        write!(f, "-")?;
+                    } else if i > 0 {
+                        write!(f, "+")?;
+                    }
+                    let faces: Vec<String> = rolls.iter()
+                        .map(|r| if r.kept { r.value.to_string() } else { format!("~{}~", r.value) })
+                        .collect();
+                    write!(f, "[{}]", faces.join(", "))?;
+                }
+                TermRoll::Modifier(m) if *m < 0 => write!(f, "-{}", -m)?,
+                TermRoll::Modifier(m) => write!(f, "+{}", m)?,
+            }
+        }
+        write!(f, " = {}", self.total)
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum DiceError {
+    Empty,
+    UnexpectedChar { position: usize, found: char },
+    UnexpectedEnd,
+    NumberTooLarge { position: usize },
+    InvalidDice { dice: String, reason: &'static str },
+    UnknownMode(String),
+    NothingToAdvantage,
+}
+
+impl Display for DiceError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            DiceError::Empty => write!(f, "empty dice expression"),
+            DiceError::UnexpectedChar { position, found } =>
+                write!(f, "unexpected character '{}' at position {}", found, position),
+            DiceError::UnexpectedEnd => write!(f, "unexpected end of dice expression"),
+            DiceError::NumberTooLarge { position } => write!(f, "number too large at position {}", position),
+            DiceError::InvalidDice { dice, reason } => write!(f, "invalid dice '{}': {}", dice, reason),
+            DiceError::UnknownMode(mode) =>
+                write!(f, "unknown roll mode '{}', expected 'adv' or 'dis'", mode),
+            DiceError::NothingToAdvantage =>
+                write!(f, "advantage and disadvantage need a single d20 to roll twice"),
+        }
+    }
+}
+
+impl std::error::Error for DiceError {}
+
+struct Parser<'a> {
+    src: &'a str,
+    pos: usize,
+}
+
+impl<'a> Parser<'a> {
+    fn new(src: &'a str) -> Self {
+        Parser { src, pos: 0 }
+    }
+
+    fn peek(&self) -> Option<char> {
+        self.src[self.pos..].chars().next()
+    }
+
+    fn bump(&mut self) -> Option<char> {
+        let c = self.peek()?;
+        self.pos += c.len_utf8();
+        Some(c)
+    }
+
+    fn skip_whitespace(&mut self) {
+        while self.peek().is_some_and(|c| c.is_whitespace()) {
+            self.bump();
+        }
+    }
+
+    fn unexpected(&self) -> DiceError {
+        match self.peek() {
+            Some(found) => DiceError::UnexpectedChar { position: self.pos, found },
+            None => DiceError::UnexpectedEnd,
+        }
+    }
+
+    fn number(&mut self) -> Result<Option<u32>, DiceError> {
+        let start = self.pos;
+        while self.peek().is_some_and(|c| c.is_ascii_digit()) {
+            self.bump();
+        }
+        if start == self.pos {
+            return Ok(None)
+        }
+        self.src[start..self.pos]
+            .parse::<u32>()
+            .map(Some)
+            .map_err(|_| DiceError::NumberTooLarge { position: start })
+    }
+
+    fn parse(mut self) -> Result<DiceExpr, DiceError> {
+        self.skip_whitespace();
+        if self.peek().is_none() {
+            return Err(DiceError::Empty)
+        }
+
+        let mut terms = Vec::new();
+        let mut sign = match self.peek() {
+            Some('-') => { self.bump(); Sign::Minus }
+            Some('+') => { self.bump(); Sign::Plus }
+            _ => Sign::Plus,
+        };
+        loop {
+            self.skip_whitespace();
+            terms.push((sign, self.term()?));
+            let end_of_term = self.pos;
+            self.skip_whitespace();
+            sign = match self.peek() {
+                Some('+') => Sign::Plus,
+                Some('-') => Sign::Minus,
+                _ => {
+                    self.pos = end_of_term;
+                    break
+                }
+            };
+            self.bump();
+        }
+
+        let mode = self.mode()?;
+        self.skip_whitespace();
+        if self.peek().is_some() {
+            return Err(self.unexpected())
+        }
+
+        let expr = DiceExpr { terms, mode };
+        if mode != RollMode::Normal && !expr.terms.iter().any(|(_, term)| matches!(term, Term::Dice(dice) if dice.is_d20())) {
+            return Err(DiceError::NothingToAdvantage)
+        }
+        Ok(expr)
+    }
+
+    fn term(&mut self) -> Result<Term, DiceError> {
+        let start = self.pos;
+        let count = self.number()?;
+        if !matches!(self.peek(), Some('d' | 'D')) {
+            return match count {
+                Some(n) => i32::try_from(n)
+                    .map(Term::Modifier)
+                    .map_err(|_| DiceError::NumberTooLarge { position: start }),
+                None => Err(self.unexpected()),
+            }
+        }
+        self.bump();
+
+        let count = count.unwrap_or(1);
+        let sides = self.number()?.ok_or_else(|| self.unexpected())?;
+        let keep = match (self.peek(), self.src[self.pos..].get(..2)) {
+            (Some('k' | 'K'), Some(k)) if k.eq_ignore_ascii_case("kh") => {
+                self.pos += 2;
+                Keep::Highest(self.number()?.ok_or_else(|| self.unexpected())?)
+            }
+            (Some('k' | 'K'), Some(k)) if k.eq_ignore_ascii_case("kl") => {
+                self.pos += 2;
+                Keep::Lowest(self.number()?.ok_or_else(|| self.unexpected())?)
+            }
+            _ => Keep::All,
+        };
+
+        let invalid = |reason| DiceError::InvalidDice { dice: self.src[start..self.pos].to_string(), reason };
+        if count == 0 {
+            return Err(invalid("must roll at least one die"))
+        }
+        if count > MAX_DICE {
+            return Err(invalid("too many dice"))
+        }
+        if sides == 0 {
+            return Err(invalid("a die needs at least one side"))
+        }
+        if sides > MAX_SIDES {
+            return Err(invalid("too many sides"))
+        }
+        if let Keep::Highest(n) | Keep::Lowest(n) = keep {
+            if n == 0 || n > count {
+                return Err(invalid("must keep between one and all of the dice rolled"))
+            }
+        }
+        Ok(Term::Dice(Dice { count, sides, keep }))
+    }
+
+    fn mode(&mut self) -> Result<RollMode, DiceError> {
+        let start = self.pos;
+        self.skip_whitespace();
+        let rest = self.src[self.pos..].trim_end();
+        if rest.is_empty() {
+            return Ok(RollMode::Normal)
+        }
+        if start == self.pos || !rest.starts_with(|c: char| c.is_ascii_alphabetic()) {
+            return Err(self.unexpected())
+        }
+        let mode = match rest.to_ascii_lowercase().as_str() {
+            "adv" | "advantage" => RollMode::Advantage,
+            "dis" | "disadv" | "disadvantage" => RollMode::Disadvantage,
+            _ => return Err(DiceError::UnknownMode(rest.to_string())),
+        };
+        self.pos += rest.len();
+        Ok(mode)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use rand::rngs::StdRng;
+    use rand::SeedableRng;
+    use super::*;
+
+    #[test]
+    fn test_parse() {
+        let expr: DiceExpr = "2d6+3".parse().unwrap();
+        assert_eq!(expr, DiceExpr::new(Dice::new(2, 6)).plus(3));
+        assert_eq!(expr.to_string(), "2d6+3");
+
+        let expr: DiceExpr = "1d20 adv".parse().unwrap();
+        assert_eq!(expr, DiceExpr::d20(RollMode::Advantage));
+        assert_eq!(expr.to_string(), "1d20 adv");
+
+        let expr: DiceExpr = " 4d6kh3 ".parse().unwrap();
+        assert_eq!(expr, DiceExpr::new(Dice::new(4, 6).keep_highest(3)));
+
+        let expr: DiceExpr = "d8 - 1 + 1d4".parse().unwrap();
+        assert_eq!(expr, DiceExpr::new(Dice::new(1, 8)).plus(-1).plus_dice(Dice::new(1, 4)));
+        assert_eq!((expr.min(), expr.max()), (1, 11));
+    }
+
+    #[test]
+    fn test_parse_errors() {
+        assert_eq!("".parse::<DiceExpr>(), Err(DiceError::Empty));
+        assert_eq!("2d".parse::<DiceExpr>(), Err(DiceError::UnexpectedEnd));
+        assert_eq!("2x6".parse::<DiceExpr>(), Err(DiceError::UnexpectedChar { position: 1, found: 'x' }));
+        assert!(matches!("0d6".parse::<DiceExpr>(), Err(DiceError::InvalidDice { .. })));
+        assert!(matches!("4d6kh5".parse::<DiceExpr>(), Err(DiceError::InvalidDice { .. })));
+        assert_eq!("1d20 lucky".parse::<DiceExpr>(), Err(DiceError::UnknownMode("lucky".to_string())));
+        assert_eq!("2d6 adv".parse::<DiceExpr>(), Err(DiceError::NothingToAdvantage));
+        assert_eq!("1d6 adv".parse::<DiceExpr>(), Err(DiceError::NothingToAdvantage));
+        // built in code rather than parsed, keeping is kept within the dice rolled
+        assert_eq!(Dice::new(1, 20).keep_highest(2).keep, Keep::Highest(1));
+        assert_eq!(Dice::new(4, 6).keep_lowest(5).keep, Keep::Lowest(4));
+        assert!((1..=20).contains(&DiceExpr::new(Dice::new(1, 20).keep_highest(2)).roll(&mut rand::thread_rng()).total));
+    }
+
+    #[test]
+    fn test_roll_breakdown() {
+        let mut rng = StdRng::seed_from_u64(42);
+        let expr: DiceExpr = "4d6kh3+2".parse().unwrap();
+        for _ in 0..100 {
+            let roll = expr.roll(&mut rng);
+            let dice: Vec<_> = roll.dice().collect();
+            assert_eq!(dice.len(), 4);
+            assert_eq!(dice.iter().filter(|d| d.kept).count(), 3);
+            let lowest = dice.iter().map(|d| d.value).min().unwrap();
+            let dropped = dice.iter().find(|d| !d.kept).unwrap();
+            assert_eq!(dropped.value, lowest);
+            let kept: u32 = dice.iter().filter(|d| d.kept).map(|d| d.value).sum();
+            assert_eq!(roll.total, kept as i32 + 2);
+            assert_eq!(roll.modifier(), 2);
+        }
+    }
+
+    #[test]
+    fn test_advantage() {
+        let mut rng = StdRng::seed_from_u64(7);
+        let adv = DiceExpr::d20(RollMode::Advantage).plus(5);
+        let dis = DiceExpr::d20(RollMode::Disadvantage);
+        for _ in 0..100 {
+            let roll = adv.roll(&mut rng);
+            let faces: Vec<_> = roll.dice().collect();
+            assert_eq!(faces.len(), 2);
+            let best = faces.iter().map(|d| d.value).max().unwrap();
+            assert_eq!(roll.natural(), Some(best));
+            assert_eq!(roll.total, best as i32 + 5);
+
+            let roll = dis.roll(&mut rng);
+            let worst = roll.dice().map(|d| d.value).min().unwrap();
+            assert_eq!(roll.natural(), Some(worst));
+        }
+        // only the d20 is rolled twice
+        let roll = "1d20+1d4 adv".parse::<DiceExpr>().unwrap().roll(&mut rng);
+        assert_eq!(roll.dice().filter(|d| d.sides == 4).count(), 1);
+        assert_eq!(roll.dice().filter(|d| d.sides == 20).count(), 2);
+        assert_eq!(RollMode::Advantage.combine(RollMode::Disadvantage), RollMode::Normal);
+        assert_eq!(RollMode::Normal.combine(RollMode::Advantage), RollMode::Advantage);
+    }
+
+    #[test]
+    fn test_seeded_rolls_are_deterministic() {
+        let expr: DiceExpr = "3d8+1d6-2".parse().unwrap();
+        let a: Vec<_> = (0..10).scan(StdRng::seed_from_u64(1), |rng, _| Some(expr.roll(rng))).collect();
+        let b: Vec<_> = (0..10).scan(StdRng::seed_from_u64(1), |rng, _| Some(expr.roll(rng))).collect();
+        assert_eq!(a, b);
+        assert!(a.iter().all(|r| (expr.min()..=expr.max()).contains(&r.total)));
+    }
+
+    #[test]
+    fn test_doubled_dice() {
+        let expr: DiceExpr = "2d6+3".parse().unwrap();
+        assert_eq!(expr.doubled_dice().to_string(), "4d6+3");
+    }
+}
@@ -0,0 +1,578 @@
+use std::fmt::{Display, Formatter};
+use godot::prelude::{real, Vector2};
+use rand::Rng;
+use crate::ai::behavior::{Attributes, Environment, State, Strategy, Target};
+use crate::ai::command::{AttackTarget, Command, Move};
+use crate::dnd::ability::Ability;
+use crate::dnd::attack::{resolve_attack, AttackError, AttackOptions, AttackResult};
+use crate::dnd::check::CheckContext;
+use crate::dnd::condition::{ConditionType, Conditions, ROUND_SECONDS};
+use crate::dnd::dice::{DiceExpr, Roll, RollMode};
+use crate::dnd::enums::{AbilityType, Proficiencies};
+use crate::dnd::footman::Character;
+use crate::tools::weapon::{Weapon, WeaponProperty, MELEE_REACH};
+
+/// Strategies are asked for commands until the turn runs out, this stops one that never does.
+const MAX_COMMANDS_PER_TURN: usize = 16;
+
+/// Index of a combatant in [`Encounter::combatants`].
+pub type CombatantId = usize;
+
+/// Another combatant as a strategy sees it. Positions are in feet.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Opponent {
+    pub id: CombatantId,
+    pub position: Vector2,
+}
+
+impl Target for Opponent {
+    fn position(&self) -> Vector2 {
+        self.position
+    }
+
+    fn is_same(&self, other: &Self) -> bool {
+        self.id == other.id
+    }
+}
+
+pub struct Combatant {
+    pub name: String,
+    /// Combatants on the same team never attack each other.
+    pub team: u32,
+    pub ability: Ability,
+    pub proficiencies: Proficiencies,
+    pub level: u32,
+    pub armor_class: u8,
+    pub health: u32,
+    pub max_health: u32,
+    /// Feet the combatant can move each turn.
+    pub speed: u32,
+    /// Where the combatant stands, in feet.
+    pub position: Vector2,
+    pub weapon: Box<dyn Weapon>,
+    /// Light weapon attacked with as a bonus action, when the main weapon is light too.
+    pub off_hand: Option<Box<dyn Weapon>>,
+    pub conditions: Conditions,
+    pub strategy: Box<dyn Strategy<Opponent>>,
+}
+
+impl Combatant {
+    pub fn from_character<C: Character + ?Sized>(
+        character: &C,
+        team: u32,
+        weapon: Box<dyn Weapon>,
+        strategy: Box<dyn Strategy<Opponent>>,
+    ) -> Self {
+        Combatant {
+            name: character.name(),
+            team,
+            ability: character.ability().clone(),
+            proficiencies: *character.proficiencies(),
+            level: character.level(),
+            armor_class: character.armor_class(),
+            health: character.health(),
+            max_health: character.max_health(),
+            speed: character.speed() as u32,
+            position: Vector2::ZERO,
+            weapon,
+            off_hand: None,
+            conditions: character.conditions().cloned().unwrap_or_default(),
+            strategy,
+        }
+    }
+
+    pub fn at(mut self, position: Vector2) -> Self {
+        self.position = position;
+        self
+    }
+
+    pub fn with_off_hand(mut self, weapon: Box<dyn Weapon>) -> Self {
+        self.off_hand = Some(weapon);
+        self
+    }
+
+    /// The off hand weapon, if both hands hold a light weapon.
+    pub fn second_weapon(&self) -> Option<&dyn Weapon> {
+        let light = |weapon: &dyn Weapon| weapon.properties().contains(&WeaponProperty::Light);
+        self.off_hand.as_deref().filter(|off_hand| light(self.weapon.as_ref()) && light(*off_hand))
+    }
+
+    pub fn is_alive(&self) -> bool {
+        self.health > 0
+    }
+
+    pub fn check_context(&self) -> CheckContext<'_> {
+        CheckContext::new(&self.ability, &self.proficiencies, self.level)
+    }
+
+    /// Farthest the combatant attacks without disadvantage, in feet.
+    pub fn attack_range(&self) -> u32 {
+        if self.weapon.is_melee() {
+            return self.weapon.reach()
+        }
+        self.weapon.range_band().map_or(self.weapon.range().max(MELEE_REACH), |band| band.normal)
+    }
+}
+
+/// What a combatant has left to spend on its turn.
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub struct TurnBudget {
+    pub action: bool,
+    /// Spent on an attack with the light weapon in the off hand.
+    pub bonus_action: bool,
+    /// Feet of movement left.
+    pub movement: real,
+}
+
+impl TurnBudget {
+    pub fn new(movement: real) -> Self {
+        TurnBudget { action: true, bonus_action: true, movement }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Initiative {
+    pub id: CombatantId,
+    pub roll: Roll,
+    pub total: i32,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum EncounterEvent {
+    Initiative(Initiative),
+    RoundStarted(u32),
+    TurnStarted(CombatantId),
+    /// The combatant is incapacitated and loses its turn.
+    TurnSkipped(CombatantId),
+    Moved { id: CombatantId, from: Vector2, to: Vector2 },
+    Attacked { id: CombatantId, target: CombatantId, result: AttackResult, bonus_action: bool },
+    AttackFailed { id: CombatantId, target: CombatantId, error: AttackError },
+    Defeated(CombatantId),
+    ConditionEnded { id: CombatantId, condition: ConditionType },
+    TurnEnded(CombatantId),
+    /// Only one team is left standing, or nobody is.
+    Ended { winner: Option<u32> },
+}
+
+impl Display for EncounterEvent {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            EncounterEvent::Initiative(initiative) => write!(f, "#{} rolls {} for initiative", initiative.id, initiative.total),
+            EncounterEvent::RoundStarted(round) => write!(f, "round {}", round),
+            EncounterEvent::TurnStarted(id) => write!(f, "#{} starts its turn", id),
+            EncounterEvent::TurnSkipped(id) => write!(f, "#{} cannot act", id),
+            EncounterEvent::Moved { id, from, to } => write!(f, "#{} moves {:.0} ft", id, from.distance_to(*to)),
+            EncounterEvent::Attacked { id, target, result, .. } => write!(f, "#{} attacks #{}: {}", id, target, result),
+            EncounterEvent::AttackFailed { id, target, error } => write!(f, "#{} cannot attack #{}: {:?}", id, target, error),
+            EncounterEvent::Defeated(id) => write!(f, "#{} is defeated", id),
+            EncounterEvent::ConditionEnded { id, condition } => write!(f, "#{} is no longer {}", id, condition),
+            EncounterEvent::TurnEnded(id) => write!(f, "#{} ends its turn", id),
+            EncounterEvent::Ended { winner: Some(team) } => write!(f, "team {} wins", team),
+            EncounterEvent::Ended { winner: None } => write!(f, "nobody is left standing"),
+        }
+    }
+}
+
+enum Step {
+    Continue,
+    EndTurn,
+}
+
+/// A turn-based fight: combatants roll initiative, then take turns in that order, each spending
+/// an action, a bonus action and its movement on the commands its strategy picks.
+pub struct Encounter {
+    combatants: Vec<Combatant>,
+    order: Vec<Initiative>,
+    /// Turns played so far, including those skipped because the combatant was down.
+    turn: usize,
+    budget: TurnBudget,
+}
+
+impl Encounter {
+    pub fn new(combatants: Vec<Combatant>) -> Self {
+        Encounter { combatants, order: Vec::new(), turn: 0, budget: TurnBudget::default() }
+    }
+
+    pub fn combatants(&self) -> &[Combatant] {
+        &self.combatants
+    }
+
+    pub fn combatant(&self, id: CombatantId) -> Option<&Combatant> {
+        self.combatants.get(id)
+    }
+
+    pub fn combatant_mut(&mut self, id: CombatantId) -> Option<&mut Combatant> {
+        self.combatants.get_mut(id)
+    }
+
+    /// The round the next turn is played in, 0 before initiative was rolled.
+    pub fn round(&self) -> u32 {
+        if self.order.is_empty() {
+            return 0
+        }
+        (self.turn / self.order.len()) as u32 + 1
+    }
+
+    /// Initiative in turn order, empty until it was rolled.
+    pub fn order(&self) -> &[Initiative] {
+        &self.order
+    }
+
+    pub fn budget(&self) -> TurnBudget {
+        self.budget
+    }
+
+    /// Every combatant rolls a Dexterity check, ties go to the higher Dexterity score.
+    pub fn roll_initiative<R: Rng + ?Sized>(&mut self, rng: &mut R) -> Vec<EncounterEvent> {
+        self.order = self.combatants.iter().enumerate()
+            .map(|(id, combatant)| {
+                let roll = DiceExpr::d20(RollMode::Normal).roll(rng);
+                let total = roll.total + combatant.ability.modifier(AbilityType::Dexterity);
+                Initiative { id, roll, total }
+            })
+            .collect();
+        let combatants = &self.combatants;
+        self.order.sort_by_key(|initiative| {
+            (std::cmp::Reverse(initiative.total), std::cmp::Reverse(combatants[initiative.id].ability.dexterity), initiative.id)
+        });
+        self.turn = 0;
+        self.order.iter().cloned().map(EncounterEvent::Initiative).collect()
+    }
+
+    /// The team still standing once every other team is down, `None` while the fight goes on or
+    /// when nobody is left.
+    pub fn winner(&self) -> Option<u32> {
+        let mut standing = self.combatants.iter().filter(|c| c.is_alive()).map(|c| c.team);
+        let team = standing.next()?;
+        standing.all(|other| other == team).then_some(team)
+    }
+
+    pub fn is_over(&self) -> bool {
+        let mut standing = self.combatants.iter().filter(|c| c.is_alive()).map(|c| c.team);
+        match standing.next() {
+            Some(team) => standing.all(|other| other == team),
+            None => true,
+        }
+    }
+
+    /// Plays the next combatant's turn, rolling initiative first if nobody did yet.
+    pub fn take_turn<R: Rng + ?Sized>(&mut self, rng: &mut R) -> Vec<EncounterEvent> {
+        let mut events = Vec::new();
+        if self.order.is_empty() {
+            events.extend(self.roll_initiative(rng));
+        }
+        if self.order.is_empty() || self.is_over() {
+            return events
+        }
+        let id = loop {
+            let index = self.turn % self.order.len();
+            if index == 0 {
+                events.push(EncounterEvent::RoundStarted(self.round()));
+            }
+            let id = self.order[index].id;
+            // the dead do not take turns
+            if self.combatants[id].is_alive() {
+                break id
+            }
+            self.turn += 1;
+        };
+        events.push(EncounterEvent::TurnStarted(id));
+        let combatant = &self.combatants[id];
+        let movement = if combatant.conditions.can_move() {
+            combatant.speed as real * combatant.conditions.speed_multiplier() as real
+        } else {
+            0.0
+        };
+        self.budget = TurnBudget::new(movement);
+
+        if self.combatants[id].conditions.can_act() {
+            for _ in 0..MAX_COMMANDS_PER_TURN {
+                let command = self.evaluate(id);
+                if let Step::EndTurn = self.execute(id, command, &mut events, rng) {
+                    break
+                }
+                if self.is_over() {
+                    break
+                }
+            }
+        } else {
+            events.push(EncounterEvent::TurnSkipped(id));
+        }
+
+        for condition in self.combatants[id].conditions.tick(ROUND_SECONDS) {
+            events.push(EncounterEvent::ConditionEnded { id, condition });
+        }
+        events.push(EncounterEvent::TurnEnded(id));
+        self.turn += 1;
+        if self.is_over() {
+            events.push(EncounterEvent::Ended { winner: self.winner() });
+        }
+        events
+    }
+
+    /// Plays turns until one team is left or `max_rounds` are over.
+    pub fn run<R: Rng + ?Sized>(&mut self, max_rounds: u32, rng: &mut R) -> Vec<EncounterEvent> {
+        let mut events = Vec::new();
+        while !self.is_over() && self.round() <= max_rounds {
+            events.extend(self.take_turn(rng));
+        }
+        events
+    }
+
+    /// Living enemies of `id`, closest first.
+    fn enemies(&self, id: CombatantId) -> Vec<Opponent> {
+        let me = &self.combatants[id];
+        let mut enemies: Vec<Opponent> = self.combatants.iter().enumerate()
+            .filter(|(_, other)| other.is_alive() && other.team != me.team)
+            .map(|(id, other)| Opponent { id, position: other.position })
+            .collect();
+        enemies.sort_by(|a, b| {
+            a.position.distance_to(me.position).total_cmp(&b.position.distance_to(me.position))
+        });
+        enemies
+    }
+
+    fn evaluate(&self, id: CombatantId) -> Command<Opponent> {
+        let me = &self.combatants[id];
+        let state = State {
+            position: me.position,
+            attributes: Attributes {
+                ability: me.ability.clone(),
+                hit_points: me.health,
+                max_hit_points: me.max_health,
+                mana_points: 0,
+                buffs: Vec::new(),
+            },
+            attack_ready: me.conditions.can_attack() && (self.budget.action || self.budget.bonus_action),
+        };
+        let characters_in_sight = self.enemies(id);
+        let range = me.attack_range() as real;
+        let env = Environment {
+            time: (self.round() - 1) as f64 * ROUND_SECONDS,
+            delta: ROUND_SECONDS,
+            characters_in_attack_range: characters_in_sight.iter()
+                .filter(|enemy| enemy.position.distance_to(me.position) <= range)
+                .copied()
+                .collect(),
+            characters_in_sight,
+            allies_in_sight: self.combatants.iter().enumerate()
+                .filter(|&(other, c)| other != id && c.is_alive() && c.team == me.team)
+                .map(|(id, c)| Opponent { id, position: c.position })
+                .collect(),
+            // everyone on the battlefield is in plain sight
+            characters_remembered: Vec::new(),
+            threat_target: None,
+        };
+        me.strategy.evaluate(&state, &env)
+    }
+
+    fn execute<R: Rng + ?Sized>(&mut self, id: CombatantId, command: Command<Opponent>, events: &mut Vec<EncounterEvent>, rng: &mut R) -> Step {
+        match command {
+            Command::Move(Move::To(destination)) => self.move_towards(id, destination, events),
+            Command::Move(Move::Direction(direction)) if direction.length() > 0.0 => {
+                let destination = self.combatants[id].position + direction.normalized() * self.budget.movement;
+                self.move_towards(id, destination, events)
+            }
+            Command::Move(Move::Direction(_)) => Step::EndTurn,
+            // the weapon at hand decides whether it is melee or ranged
+            Command::Attack(attack) => match attack.target {
+                AttackTarget::LockOn(target) => self.attack(id, target.id, events, rng),
+                AttackTarget::Direction(direction) => match self.target_in_direction(id, direction) {
+                    Some(target) => self.attack(id, target, events, rng),
+                    None => Step::EndTurn,
+                },
+                AttackTarget::Point(point) => {
+                    let direction = point - self.combatants[id].position;
+                    match self.target_in_direction(id, direction) {
+                        Some(target) => self.attack(id, target, events, rng),
+                        None => Step::EndTurn,
+                    }
+                }
+            },
+            // skills cannot be used in turn-based encounters yet
+            Command::Skill { .. } => Step::EndTurn,
+            Command::ContinueLast | Command::Stop | Command::Hold => Step::EndTurn,
+        }
+    }
+
+    fn move_towards(&mut self, id: CombatantId, destination: Vector2, events: &mut Vec<EncounterEvent>) -> Step {
+        let me = &self.combatants[id];
+        let from = me.position;
+        // walking up to someone stops a foot inside reach instead of on top of them
+        let occupied = self.combatants.iter().enumerate()
+            .any(|(other, c)| other != id && c.is_alive() && c.position.distance_to(destination) < 1.0);
+        let stop = if occupied { (me.attack_range() as real - 1.0).max(0.0) } else { 0.0 };
+        let distance = from.distance_to(destination);
+        let travel = (distance - stop).min(self.budget.movement);
+        if travel < 1.0 {
+            return Step::EndTurn
+        }
+        let to = from + (destination - from) * (travel / distance);
+        self.combatants[id].position = to;
+        self.budget.movement -= travel;
+        events.push(EncounterEvent::Moved { id, from, to });
+        Step::Continue
+    }
+
+    /// The enemy `direction` points at most closely, preferring those within range.
+    fn target_in_direction(&self, id: CombatantId, direction: Vector2) -> Option<CombatantId> {
+        let me = &self.combatants[id];
+        let range = me.attack_range() as real;
+        let alignment = |enemy: &Opponent| {
+            let offset = enemy.position - me.position;
+            let length = offset.length() * direction.length();
+            if length > 0.0 { offset.dot(direction) / length } else { 1.0 }
+        };
+        self.enemies(id).into_iter()
+            .max_by(|a, b| {
+                let a_in_range = a.position.distance_to(me.position) <= range;
+                let b_in_range = b.position.distance_to(me.position) <= range;
+                a_in_range.cmp(&b_in_range).then(alignment(a).total_cmp(&alignment(b)))
+            })
+            .map(|enemy| enemy.id)
+    }
+
+    fn attack<R: Rng + ?Sized>(&mut self, id: CombatantId, target: CombatantId, events: &mut Vec<EncounterEvent>, rng: &mut R) -> Step {
+        let attacker = &self.combatants[id];
+        let defender = &self.combatants[target];
+        if !attacker.conditions.can_attack() || !defender.is_alive() || defender.team == attacker.team {
+            return Step::EndTurn
+        }
+        let (weapon, bonus_action) = if self.budget.action {
+            (attacker.weapon.as_ref(), false)
+        } else if let Some(off_hand) = attacker.second_weapon().filter(|_| self.budget.bonus_action) {
+            (off_hand, true)
+        } else {
+            return Step::EndTurn
+        };
+
+        let distance = attacker.position.distance_to(defender.position).round() as u32;
+        let mode = attacker.conditions.attack_mode()
+            .combine(defender.conditions.defense_mode(weapon.is_melee()));
+        let mut options = AttackOptions::default().with_mode(mode).at_distance(distance);
+        if bonus_action {
+            options = options.with_off_hand();
+        }
+        let result = resolve_attack(&attacker.check_context(), weapon, defender.armor_class, options, rng);
+        let result = match result {
+            Ok(result) => result,
+            Err(error) => {
+                events.push(EncounterEvent::AttackFailed { id, target, error });
+                return Step::EndTurn
+            }
+        };
+
+        if bonus_action {
+            self.budget.bonus_action = false;
+        } else {
+            self.budget.action = false;
+        }
+        let defender = &mut self.combatants[target];
+        defender.health = defender.health.saturating_sub(result.damage.max(0) as u32);
+        if let Some(condition) = result.condition() {
+            defender.conditions.apply(condition);
+        }
+        let defeated = !defender.is_alive();
+        events.push(EncounterEvent::Attacked { id, target, result, bonus_action });
+        if defeated {
+            events.push(EncounterEvent::Defeated(target));
+        }
+        Step::Continue
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use rand::rngs::StdRng;
+    use rand::SeedableRng;
+    use crate::ai::behavior::Sentry;
+    use crate::dnd::catalog::Catalog;
+    use crate::dnd::condition::Condition;
+    use crate::dnd::enums::{ClassType, RaceType};
+    use crate::dnd::footman::Footman;
+    use super::*;
+
+    fn fighter(name: &str, team: u32, weapon: &str, position: Vector2) -> Combatant {
+        let catalog = Catalog::builtin();
+        let character = Footman::builder(name, RaceType::Human, ClassType::Fighter).level(3).build();
+        let weapon = Box::new(catalog.weapon(weapon).unwrap().clone());
+        let strategy = Box::new(Sentry { stay_position: position, follow_range: real::MAX });
+        Combatant::from_character(&character, team, weapon, strategy).at(position)
+    }
+
+    fn off_hand(weapon: &str) -> Box<dyn Weapon> {
+        Box::new(Catalog::builtin().weapon(weapon).unwrap().clone())
+    }
+
+    #[test]
+    fn test_initiative() {
+        let mut rng = StdRng::seed_from_u64(2);
+        let mut encounter = Encounter::new(vec![
+            fighter("Bob", 0, "Longsword", Vector2::ZERO),
+            fighter("Alice", 1, "Longsword", Vector2::new(20.0, 0.0)),
+            fighter("Carol", 1, "Longsword", Vector2::new(0.0, 20.0)),
+        ]);
+        let events = encounter.roll_initiative(&mut rng);
+        assert_eq!(events.len(), 3);
+        assert!(encounter.order().windows(2).all(|pair| pair[0].total >= pair[1].total));
+        assert_eq!(encounter.winner(), None);
+        assert!(!encounter.is_over());
+    }
+
+    #[test]
+    fn test_turns() {
+        let mut rng = StdRng::seed_from_u64(5);
+        let mut encounter = Encounter::new(vec![
+            fighter("Bob", 0, "Shortsword", Vector2::ZERO).with_off_hand(off_hand("Dagger")),
+            fighter("Alice", 1, "Longsword", Vector2::new(100.0, 0.0)).with_off_hand(off_hand("Dagger")),
+        ]);
+        let events = encounter.take_turn(&mut rng);
+        let id = encounter.order()[0].id;
+        assert!(events.contains(&EncounterEvent::RoundStarted(1)));
+        assert!(events.contains(&EncounterEvent::TurnStarted(id)));
+        // 30 feet of movement is not enough to close a 100 feet gap
+        let moved: Vec<_> = events.iter()
+            .filter_map(|event| match event {
+                EncounterEvent::Moved { from, to, .. } => Some(from.distance_to(*to)),
+                _ => None,
+            })
+            .collect();
+        assert_eq!(moved.len(), 1);
+        assert!((moved[0] - 30.0).abs() < 1e-3);
+        assert_eq!(encounter.budget().movement, 0.0);
+        assert!(encounter.budget().action);
+
+        let events = encounter.run(50, &mut rng);
+        let winner = encounter.winner().unwrap();
+        assert_eq!(events.last(), Some(&EncounterEvent::Ended { winner: Some(winner) }));
+        assert!(encounter.combatants().iter().filter(|c| c.team != winner).all(|c| !c.is_alive()));
+        // only a light weapon in each hand earns a second attack with the bonus action
+        for event in &events {
+            if let EncounterEvent::Attacked { id, result, bonus_action: true, .. } = event {
+                assert_eq!(encounter.combatant(*id).unwrap().name, "Bob");
+                assert!(result.attack.damage_roll.total <= 4);
+            }
+        }
+        assert!(events.iter().any(|event| matches!(event, EncounterEvent::Attacked { bonus_action: true, .. })));
+        let attacks_in_a_turn = events.split(|event| matches!(event, EncounterEvent::TurnEnded(_)))
+            .map(|turn| turn.iter().filter(|event| matches!(event, EncounterEvent::Attacked { .. })).count())
+            .max()
+            .unwrap();
+        assert!(attacks_in_a_turn <= 2);
+        assert!(encounter.take_turn(&mut rng).is_empty());
+    }
+
+    #[test]
+    fn test_incapacitated() {
+        let mut rng = StdRng::seed_from_u64(1);
+        let mut stunned = fighter("Bob", 0, "Longsword", Vector2::ZERO);
+        stunned.conditions.apply(Condition::for_rounds(ConditionType::Stunned, 1));
+        let mut encounter = Encounter::new(vec![stunned, fighter("Alice", 1, "Longsword", Vector2::new(4.0, 0.0))]);
+        encounter.roll_initiative(&mut rng);
+        let mut events = encounter.take_turn(&mut rng);
+        events.extend(encounter.take_turn(&mut rng));
+        assert!(events.contains(&EncounterEvent::TurnSkipped(0)));
+        assert!(events.contains(&EncounterEvent::ConditionEnded { id: 0, condition: ConditionType::Stunned }));
+        assert!(!events.iter().any(|event| matches!(event, EncounterEvent::Attacked { id: 0, .. })));
+    }
+}
@@ -1,6 +1,28 @@
+use std::fmt::{Display, Formatter};
 use bitflags::{bitflags, Flags};
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+
+/// Serializes a bit set as the list of names of its flags, e.g. `[Light, Medium]`, so saved
+/// characters stay readable and unknown names are rejected by the enum's own deserializer.
+macro_rules! serde_bits_as_list {
+    ($bits:ty, $item:ty) => {
+        impl Serialize for $bits {
+            fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+                let items: Vec<$item> = self.iter().map(<$item>::from).collect();
+                items.serialize(serializer)
+            }
+        }
+
+        impl<'de> Deserialize<'de> for $bits {
+            fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+                let items = Vec::<$item>::deserialize(deserializer)?;
+                Ok(items.into_iter().map(<$bits>::from).collect())
+            }
+        }
+    };
+}
 
-#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
 pub enum AbilityType {
     Strength,
     Dexterity,
@@ -52,7 +74,9 @@ bitflags! {
     }
 }
 
-#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+serde_bits_as_list!(AbilityBits, AbilityType);
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
 pub enum ArmorType {
     Light,
     Medium,
@@ -92,7 +116,9 @@ bitflags! {
     }
 }
 
-#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+serde_bits_as_list!(ArmorBits, ArmorType);
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
 pub enum WeaponType {
     SimpleMelee,
     SimpleRanged,
@@ -111,6 +137,8 @@ bitflags! {
     }
 }
 
+serde_bits_as_list!(WeaponBits, WeaponType);
+
 #[rustfmt::skip]
 impl From<WeaponBits> for WeaponType {
     fn from(weapon: WeaponBits) -> Self {
@@ -136,7 +164,7 @@ impl From<WeaponType> for WeaponBits {
     }
 }
 
-#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
 pub enum ShieldType {
     Buckler,
     Heater,
@@ -144,6 +172,10 @@ pub enum ShieldType {
     Tower,
 }
 
+impl ShieldType {
+    pub const ALL: [ShieldType; 4] = [ShieldType::Buckler, ShieldType::Heater, ShieldType::Kite, ShieldType::Tower];
+}
+
 #[rustfmt::skip]
 bitflags! {
     #[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
@@ -155,6 +187,8 @@ bitflags! {
     }
 }
 
+serde_bits_as_list!(ShieldBits, ShieldType);
+
 #[rustfmt::skip]
 impl From<ShieldBits> for ShieldType {
     fn from(value: ShieldBits) -> Self {
@@ -180,7 +214,7 @@ impl From<ShieldType> for ShieldBits {
     }
 }
 
-#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
 pub enum SkillType {
     Acrobatics,
     AnimalHandling,
@@ -202,6 +236,32 @@ pub enum SkillType {
     Survival,
 }
 
+impl SkillType {
+    #[rustfmt::skip]
+    pub fn ability(&self) -> AbilityType {
+        match self {
+            SkillType::Athletics      => AbilityType::Strength,
+            SkillType::Acrobatics     => AbilityType::Dexterity,
+            SkillType::SleightOfHand  => AbilityType::Dexterity,
+            SkillType::Stealth        => AbilityType::Dexterity,
+            SkillType::Arcana         => AbilityType::Intelligence,
+            SkillType::History        => AbilityType::Intelligence,
+            SkillType::Investigation  => AbilityType::Intelligence,
+            SkillType::Nature         => AbilityType::Intelligence,
+            SkillType::Religion       => AbilityType::Intelligence,
+            SkillType::AnimalHandling => AbilityType::Wisdom,
+            SkillType::Insight        => AbilityType::Wisdom,
+            SkillType::Medicine       => AbilityType::Wisdom,
+            SkillType::Perception     => AbilityType::Wisdom,
+            SkillType::Survival       => AbilityType::Wisdom,
+            SkillType::Deception      => AbilityType::Charisma,
+            SkillType::Intimidation   => AbilityType::Charisma,
+            SkillType::Performance    => AbilityType::Charisma,
+            SkillType::Persuasion     => AbilityType::Charisma,
+        }
+    }
+}
+
 #[rustfmt::skip]
 bitflags! {
     #[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
@@ -227,6 +287,8 @@ bitflags! {
     }
 }
 
+serde_bits_as_list!(SkillBits, SkillType);
+
 #[rustfmt::skip]
 impl From<SkillBits> for SkillType {
     fn from(value: SkillBits) -> Self {
@@ -280,7 +342,7 @@ impl From<SkillType> for SkillBits {
     }
 }
 
-#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
 pub enum RaceType {
     Dwarf,
     Elf,
@@ -293,6 +355,37 @@ pub enum RaceType {
     Tiefling,
 }
 
+impl RaceType {
+    pub const ALL: [RaceType; 9] = [
+        RaceType::Dwarf,
+        RaceType::Elf,
+        RaceType::Halfling,
+        RaceType::Human,
+        RaceType::Dragonborn,
+        RaceType::Gnome,
+        RaceType::HalfElf,
+        RaceType::HalfOrc,
+        RaceType::Tiefling,
+    ];
+}
+
+#[rustfmt::skip]
+impl Display for RaceType {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            RaceType::Dwarf      => write!(f, "Dwarf"),
+            RaceType::Elf        => write!(f, "Elf"),
+            RaceType::Halfling   => write!(f, "Halfling"),
+            RaceType::Human      => write!(f, "Human"),
+            RaceType::Dragonborn => write!(f, "Dragonborn"),
+            RaceType::Gnome      => write!(f, "Gnome"),
+            RaceType::HalfElf    => write!(f, "Half-Elf"),
+            RaceType::HalfOrc    => write!(f, "Half-Orc"),
+            RaceType::Tiefling   => write!(f, "Tiefling"),
+        }
+    }
+}
+
 #[rustfmt::skip]
 bitflags! {
     #[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
@@ -309,6 +402,8 @@ bitflags! {
     }
 }
 
+serde_bits_as_list!(RaceBits, RaceType);
+
 #[rustfmt::skip]
 impl From<RaceBits> for RaceType {
     fn from(value: RaceBits) -> Self {
@@ -344,7 +439,7 @@ impl From<RaceType> for RaceBits {
     }
 }
 
-#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
 pub enum ClassType {
     Barbarian,
     Bard,
@@ -360,6 +455,29 @@ pub enum ClassType {
     Wizard,
 }
 
+impl ClassType {
+    pub const ALL: [ClassType; 12] = [
+        ClassType::Barbarian,
+        ClassType::Bard,
+        ClassType::Cleric,
+        ClassType::Druid,
+        ClassType::Fighter,
+        ClassType::Monk,
+        ClassType::Paladin,
+        ClassType::Ranger,
+        ClassType::Rogue,
+        ClassType::Sorcerer,
+        ClassType::Warlock,
+        ClassType::Wizard,
+    ];
+}
+
+impl Display for ClassType {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{:?}", self)
+    }
+}
+
 #[rustfmt::skip]
 bitflags! {
     #[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
@@ -379,6 +497,8 @@ bitflags! {
     }
 }
 
+serde_bits_as_list!(ClassBits, ClassType);
+
 #[rustfmt::skip]
 impl From<ClassBits> for ClassType {
     fn from(value: ClassBits) -> Self {
@@ -420,7 +540,34 @@ impl From<ClassType> for ClassBits {
     }
 }
 
-#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+pub enum Size {
+    Tiny,
+    Small,
+    Medium,
+    Large,
+    Huge,
+    Gargantuan,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+pub enum DamageType {
+    Acid,
+    Bludgeoning,
+    Cold,
+    Fire,
+    Force,
+    Lightning,
+    Necrotic,
+    Piercing,
+    Poison,
+    Psychic,
+    Radiant,
+    Slashing,
+    Thunder,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
 pub enum Proficiency {
     Armor(ArmorType),
     Weapon(WeaponType),
@@ -429,7 +576,8 @@ pub enum Proficiency {
     Skill(SkillType)
 }
 
-#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+#[serde(from = "ProficiencyLists", into = "ProficiencyLists")]
 pub struct Proficiencies {
     armor: ArmorBits,
     weapon: WeaponBits,
@@ -499,6 +647,31 @@ impl Proficiencies {
         self.skills.insert(sb);
     }
 
+    #[inline]
+    pub fn has_armor(&self, armor: ArmorType) -> bool {
+        self.armor.contains(armor.into())
+    }
+
+    #[inline]
+    pub fn has_weapon(&self, weapon: WeaponType) -> bool {
+        self.weapon.contains(weapon.into())
+    }
+
+    #[inline]
+    pub fn has_shield(&self, shield: ShieldType) -> bool {
+        self.shield.contains(shield.into())
+    }
+
+    #[inline]
+    pub fn has_saving_throw(&self, ability: AbilityType) -> bool {
+        self.saving_throws.contains(ability.into())
+    }
+
+    #[inline]
+    pub fn has_skill(&self, skill: SkillType) -> bool {
+        self.skills.contains(skill.into())
+    }
+
     #[inline]
     pub fn get_proficiency_list(&self) -> Vec<Proficiency> {
         let mut list = Vec::new();
@@ -554,4 +727,39 @@ impl Proficiencies {
             .map(|f| (*f.value()).into())
             .collect::<Vec<_>>()
     }
-}
\ No newline at end of file
+}
+
+/// The serialized form of [`Proficiencies`], one list of names per kind of proficiency.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct ProficiencyLists {
+    pub armor: Vec<ArmorType>,
+    pub weapons: Vec<WeaponType>,
+    pub shields: Vec<ShieldType>,
+    pub saving_throws: Vec<AbilityType>,
+    pub skills: Vec<SkillType>,
+}
+
+impl From<ProficiencyLists> for Proficiencies {
+    fn from(lists: ProficiencyLists) -> Self {
+        let mut proficiencies = Proficiencies::empty();
+        lists.armor.into_iter().for_each(|a| proficiencies.add_armor(a));
+        lists.weapons.into_iter().for_each(|w| proficiencies.add_weapon(w));
+        lists.shields.into_iter().for_each(|s| proficiencies.add_shield(s));
+        lists.saving_throws.into_iter().for_each(|a| proficiencies.add_saving_throw(a));
+        lists.skills.into_iter().for_each(|s| proficiencies.add_skill(s));
+        proficiencies
+    }
+}
+
+impl From<Proficiencies> for ProficiencyLists {
+    fn from(proficiencies: Proficiencies) -> Self {
+        ProficiencyLists {
+            armor: proficiencies.get_armor_list(),
+            weapons: proficiencies.get_weapon_list(),
+            shields: proficiencies.get_shield_list(),
+            saving_throws: proficiencies.get_saving_throw_list(),
+            skills: proficiencies.get_skill_list(),
+        }
+    }
+}
@@ -1,28 +1,113 @@
-use crate::dnd::ability::Ability;
+use rand::Rng;
+use serde::{Deserialize, Serialize};
+use crate::dnd::ability::{Ability, proficiency_bonus};
 use crate::dnd::alignment::{Alignment, Ethical, Moral};
-use crate::dnd::enums::{Proficiencies, ShieldType, WeaponType};
+use crate::dnd::catalog::{catalog, SkillChoices};
+use crate::dnd::check::{CheckContext, CheckKind, CheckResult};
+use crate::dnd::condition::Conditions;
+use crate::dnd::death::{DeathSave, LifeEvent, LifeState};
+use crate::dnd::dice::{Dice, DiceExpr, RollMode};
+use crate::dnd::enums::{AbilityType, ClassType, Proficiencies, RaceType, ShieldType, Size, SkillType, WeaponType};
+use crate::dnd::progression::{
+    AbilityScoreImprovement, Experience, grants_ability_score_improvement, HitPointChoice, LevelUp,
+    LevelUpChoice, LevelUpDecision, LevelUpError, PendingLevelUp,
+};
+use crate::dnd::reaction::{Disposition, Reputation};
+use crate::dnd::rest::{HitDice, LongRest, RestError, ShortRest};
+use crate::dnd::spell::{Cast, SpellData, SpellError, Spellcaster};
+use crate::tools::armor::{ArmorPenalty, Equipment};
+use crate::tools::inventory::{Encumbrance, EquipmentSlot, Inventory, InventoryError, ItemData, ItemKind};
+use crate::tools::weapon::WeaponData;
 
-#[derive(Debug, Clone)]
-struct CharacterClass {
-    name: String,
-    proficiencies: Proficiencies,
-    ability_modifier: Ability
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CharacterClass {
+    pub class_type: ClassType,
+    pub hit_die: u32,
+    pub proficiencies: Proficiencies,
+    pub skill_choices: SkillChoices,
+    pub ability_modifier: Ability,
 }
 
-#[derive(Debug, Clone)]
-struct CharacterRace {
-    name: String,
-    size: String,
-    speed: u8,
+impl CharacterClass {
+    pub fn name(&self) -> String {
+        self.class_type.to_string()
+    }
+
+    pub fn average_hit_die(&self) -> u32 {
+        self.hit_die / 2 + 1
+    }
+
+    /// The class as taken up by a character that started out in another one, with only the
+    /// proficiencies multiclassing into it grants.
+    pub fn multiclass(class_type: ClassType) -> Self {
+        CharacterClass {
+            proficiencies: catalog().class(class_type).multiclass.proficiencies(),
+            ..CharacterClass::from(class_type)
+        }
+    }
+}
+
+impl From<ClassType> for CharacterClass {
+    fn from(class_type: ClassType) -> Self {
+        let data = catalog().class(class_type);
+        CharacterClass {
+            class_type,
+            hit_die: data.hit_die,
+            proficiencies: data.proficiencies(),
+            skill_choices: data.skill_choices.clone(),
+            ability_modifier: Ability::default(),
+        }
+    }
+}
+
+/// The levels a character has in one of its classes, and the hit dice they come with.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ClassLevel {
+    pub class: CharacterClass,
+    pub level: u32,
+    pub hit_dice: HitDice,
+}
+
+impl ClassLevel {
+    pub fn new(class: CharacterClass, level: u32) -> Self {
+        let hit_dice = HitDice::new(class.hit_die, level);
+        ClassLevel { class, level, hit_dice }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CharacterRace {
+    pub race_type: RaceType,
+    pub size: Size,
+    pub speed: u8,
     // languages: Vec<String>,
-    ability_modifier: Ability,
+    pub ability_modifier: Ability,
+}
+
+impl CharacterRace {
+    pub fn name(&self) -> String {
+        self.race_type.to_string()
+    }
+}
+
+impl From<RaceType> for CharacterRace {
+    fn from(race_type: RaceType) -> Self {
+        let data = catalog().race(race_type);
+        CharacterRace {
+            race_type,
+            size: data.size,
+            speed: data.speed,
+            ability_modifier: data.ability_modifier(),
+        }
+    }
 }
 
-trait Character {
+pub trait Character {
     fn ability(&self) -> &Ability;
     fn name(&self) -> String;
     fn level(&self) -> u32;
     fn health(&self) -> u32;
+    fn max_health(&self) -> u32;
     fn is_alive(&self) -> bool {
         self.health() > 0
     }
@@ -31,9 +116,50 @@ trait Character {
     fn alignment(&self) -> Alignment;
     fn class(&self) -> &CharacterClass;
     fn race(&self) -> &CharacterRace;
+    fn proficiencies(&self) -> &Proficiencies;
     // fn skills(&self) -> Vec<Skill>;
     // fn equipment(&self) -> Vec<Equipment>;
     // fn spells(&self) -> Vec<Spell>;
+
+    /// Penalties from the worn armor and shield, none for characters that do not track gear.
+    fn armor_penalty(&self) -> ArmorPenalty {
+        ArmorPenalty::default()
+    }
+
+    fn encumbrance(&self) -> Encumbrance {
+        Encumbrance::Unencumbered
+    }
+
+    /// The conditions affecting the character, `None` for characters that do not track them.
+    fn conditions(&self) -> Option<&Conditions> {
+        None
+    }
+
+    /// Roll mode for the character's attacks, from armor, load and conditions.
+    fn attack_mode(&self) -> RollMode {
+        self.armor_penalty().attack_mode()
+            .combine(self.encumbrance().attack_mode())
+            .combine(self.conditions().map_or(RollMode::Normal, Conditions::attack_mode))
+    }
+
+    /// Roll mode for the character's ability checks and saving throws of `kind`.
+    fn check_mode(&self, kind: CheckKind) -> RollMode {
+        self.armor_penalty().check_mode(kind)
+            .combine(self.encumbrance().check_mode(kind))
+            .combine(self.conditions().map_or(RollMode::Normal, |conditions| conditions.check_mode(kind)))
+    }
+
+    fn speed(&self) -> u8 {
+        self.encumbrance().speed(self.race().speed.saturating_sub(self.armor_penalty().speed))
+    }
+
+    fn proficiency_bonus(&self) -> i32 {
+        proficiency_bonus(self.level())
+    }
+
+    fn check_context(&self) -> CheckContext<'_> {
+        CheckContext::new(self.ability(), self.proficiencies(), self.level())
+    }
 }
 trait Weapon {
     fn r#type(&self) -> WeaponType;
@@ -45,8 +171,585 @@ trait Shield {
     fn defend(&self);
 }
 
+/// Hit points at `level`, taking the full hit die at first level and the fixed average afterwards.
+pub fn max_hit_points(class: &CharacterClass, ability: &Ability, level: u32) -> u32 {
+    let con = ability.modifier(AbilityType::Constitution);
+    let first = (class.hit_die as i32 + con).max(1);
+    let rest = (class.average_hit_die() as i32 + con).max(1) * (level.max(1) as i32 - 1);
+    (first + rest) as u32
+}
+
+/// Armor class without armor. Barbarians and monks add a second ability through Unarmored Defense.
+pub fn unarmored_armor_class(class: &CharacterClass, ability: &Ability) -> u8 {
+    let dex = ability.modifier(AbilityType::Dexterity);
+    let extra = match class.class_type {
+        ClassType::Barbarian => ability.modifier(AbilityType::Constitution),
+        ClassType::Monk => ability.modifier(AbilityType::Wisdom),
+        _ => 0,
+    };
+    (10 + dex + extra).max(0) as u8
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Footman {
+    name: String,
+    /// The character level, all classes together.
+    level: u32,
+    race: CharacterRace,
+    /// The class the character started out in comes first.
+    classes: Vec<ClassLevel>,
+    ability: Ability,
+    health: u32,
+    max_health: u32,
+    alignment: Alignment,
+    proficiencies: Proficiencies,
+    experience: Experience,
+    spellcaster: Option<Spellcaster>,
+    #[serde(default)]
+    life: LifeState,
+    #[serde(default)]
+    faction: Option<String>,
+    #[serde(default)]
+    reputation: Reputation,
+    #[serde(default)]
+    inventory: Inventory,
+    #[serde(default)]
+    conditions: Conditions,
+}
+
+/// What a hit did besides taking hit points away.
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub struct DamageTaken {
+    /// The saving throw to keep concentrating on a spell, if one was made.
+    pub concentration: Option<CheckResult>,
+    pub event: Option<LifeEvent>,
+}
+
+impl Footman {
+    pub fn builder(name: impl Into<String>, race: RaceType, class: ClassType) -> FootmanBuilder {
+        FootmanBuilder::new(name, race, class)
+    }
+
+    pub fn experience(&self) -> &Experience {
+        &self.experience
+    }
+
+    /// The armor and shield equipped in the inventory.
+    pub fn equipment(&self) -> Equipment {
+        let armor = self.inventory.equipped_in(EquipmentSlot::Armor)
+            .and_then(|item| catalog().armor(&item.name))
+            .cloned();
+        let shield = match self.inventory.equipped_in(EquipmentSlot::OffHand).map(|item| item.kind) {
+            Some(ItemKind::Shield(shield)) => Some(catalog().shield(shield).clone()),
+            _ => None,
+        };
+        Equipment { armor, shield }
+    }
+
+    pub fn inventory(&self) -> &Inventory {
+        &self.inventory
+    }
+
+    pub fn conditions_mut(&mut self) -> &mut Conditions {
+        &mut self.conditions
+    }
+
+    pub fn add_item(&mut self, item: &ItemData, count: u32) {
+        self.inventory.add(item, count);
+    }
+
+    pub fn remove_item(&mut self, name: &str, count: u32) -> Result<ItemData, InventoryError> {
+        self.inventory.remove(name, count)
+    }
+
+    /// Equips an item from the inventory and returns whatever was in `slot` before. Armor and
+    /// shields are worn as soon as they are equipped.
+    pub fn equip_item(&mut self, slot: EquipmentSlot, name: &str) -> Result<Option<ItemData>, InventoryError> {
+        self.inventory.equip(slot, name)
+    }
+
+    pub fn unequip_item(&mut self, slot: EquipmentSlot) -> Result<ItemData, InventoryError> {
+        self.inventory.unequip(slot)
+    }
+
+    /// The weapon held in the main hand, if any.
+    pub fn weapon(&self) -> Option<&WeaponData> {
+        self.inventory.equipped_in(EquipmentSlot::MainHand)
+            .filter(|item| item.kind == ItemKind::Weapon)
+            .and_then(|item| catalog().weapon(&item.name))
+    }
+
+    pub fn spellcaster(&self) -> Option<&Spellcaster> {
+        self.spellcaster.as_ref()
+    }
+
+    pub fn spellcaster_mut(&mut self) -> Option<&mut Spellcaster> {
+        self.spellcaster.as_mut()
+    }
+
+    pub fn cast<R: Rng + ?Sized>(&mut self, spell: &SpellData, slot_level: u8, rng: &mut R) -> Result<Cast, SpellError> {
+        let caster = CheckContext::new(&self.ability, &self.proficiencies, self.level);
+        let spellcaster = self.spellcaster.as_mut().ok_or(SpellError::NotASpellcaster(self.classes[0].class.class_type))?;
+        spellcaster.cast(&caster, spell, slot_level, rng)
+    }
+
+    pub fn faction(&self) -> Option<&str> {
+        self.faction.as_deref()
+    }
+
+    pub fn reputation(&self) -> &Reputation {
+        &self.reputation
+    }
+
+    pub fn reputation_mut(&mut self) -> &mut Reputation {
+        &mut self.reputation
+    }
+
+    /// What others go by when deciding how to react to this character.
+    pub fn disposition(&self) -> Disposition {
+        Disposition {
+            alignment: self.alignment,
+            faction: self.faction.clone(),
+            reputation: self.reputation.clone(),
+        }
+    }
+
+    pub fn life(&self) -> LifeState {
+        self.life
+    }
+
+    pub fn classes(&self) -> &[ClassLevel] {
+        &self.classes
+    }
+
+    /// Levels taken in `class`, `0` if the character never took it up.
+    pub fn class_level(&self, class: ClassType) -> u32 {
+        self.classes.iter().find(|c| c.class.class_type == class).map_or(0, |c| c.level)
+    }
+
+    pub fn class_levels(&self) -> Vec<(ClassType, u32)> {
+        self.classes.iter().map(|c| (c.class.class_type, c.level)).collect()
+    }
+
+    /// Whether the character may take its next level in `class`. Taking up a new class needs its
+    /// ability score prerequisites, and those of every class the character already has.
+    pub fn can_take_level_in(&self, class: ClassType) -> Result<(), LevelUpError> {
+        if self.class_level(class) > 0 {
+            return Ok(())
+        }
+        let current = self.classes.iter().map(|c| c.class.class_type);
+        match current.chain([class]).find(|&c| !catalog().class(c).multiclass.allows(&self.ability)) {
+            Some(unmet) => Err(LevelUpError::PrerequisitesNotMet(unmet)),
+            None => Ok(()),
+        }
+    }
+
+    pub fn hit_dice(&self) -> impl Iterator<Item = &HitDice> {
+        self.classes.iter().map(|c| &c.hit_dice)
+    }
+
+    pub fn hit_dice_remaining(&self) -> u32 {
+        self.hit_dice().map(HitDice::remaining).sum()
+    }
+
+    /// The classes from the biggest hit die to the smallest, the order hit dice are spent and
+    /// recovered in.
+    fn classes_by_hit_die(&mut self) -> Vec<&mut ClassLevel> {
+        let mut classes: Vec<_> = self.classes.iter_mut().collect();
+        classes.sort_by_key(|c| std::cmp::Reverse(c.hit_dice.die()));
+        classes
+    }
+
+    /// Loses `amount` hit points. A concentrating caster also has to keep its concentration, at 0
+    /// hit points the character falls unconscious, and every hit after that costs a death saving
+    /// throw, two for a `critical` one.
+    pub fn take_damage<R: Rng + ?Sized>(&mut self, amount: u32, critical: bool, rng: &mut R) -> DamageTaken {
+        let event = match self.life {
+            LifeState::Conscious if amount >= self.health => {
+                let overflow = amount - self.health;
+                self.health = 0;
+                Some(self.life.drop_to_zero(overflow, self.max_health))
+            }
+            LifeState::Conscious => {
+                self.health -= amount;
+                None
+            }
+            _ => self.life.damage_at_zero(amount, self.max_health, critical),
+        };
+        if !self.life.is_conscious() {
+            // nobody keeps concentrating while unconscious
+            if let Some(spellcaster) = self.spellcaster.as_mut() {
+                spellcaster.end_concentration();
+            }
+            return DamageTaken { concentration: None, event }
+        }
+        let caster = CheckContext::new(&self.ability, &self.proficiencies, self.level);
+        let concentration = self.spellcaster.as_mut()
+            .and_then(|spellcaster| spellcaster.concentration_check(&caster, amount, rng));
+        DamageTaken { concentration, event }
+    }
+
+    /// Regains up to `amount` hit points, which wakes an unconscious character. The dead need more
+    /// than healing.
+    pub fn heal(&mut self, amount: u32) -> Option<LifeEvent> {
+        if self.life.is_dead() || amount == 0 {
+            return None
+        }
+        self.health = self.health.saturating_add(amount).min(self.max_health);
+        self.life.heal()
+    }
+
+    /// Rolls the death saving throw for this turn, `None` unless the character is dying.
+    pub fn death_save<R: Rng + ?Sized>(&mut self, rng: &mut R) -> Option<DeathSave> {
+        let save = self.life.death_save(RollMode::Normal, rng)?;
+        if save.event == Some(LifeEvent::Revived) {
+            self.health = 1;
+        }
+        Some(save)
+    }
+
+    pub fn stabilize(&mut self) -> Option<LifeEvent> {
+        self.life.stabilize()
+    }
+
+    fn can_rest(&self) -> Result<(), RestError> {
+        match self.life {
+            LifeState::Conscious => Ok(()),
+            LifeState::Dead => Err(RestError::Dead),
+            LifeState::Dying(_) | LifeState::Stable => Err(RestError::Unconscious),
+        }
+    }
+
+    /// Spends `hit_dice`, the biggest first, to regain hit points. Warlocks also get their pact
+    /// slots back.
+    pub fn short_rest<R: Rng + ?Sized>(&mut self, hit_dice: u32, rng: &mut R) -> Result<ShortRest, RestError> {
+        self.can_rest()?;
+        let remaining = self.hit_dice_remaining();
+        if hit_dice > remaining {
+            return Err(RestError::NotEnoughHitDice { requested: hit_dice, remaining })
+        }
+        let con = self.ability.modifier(AbilityType::Constitution);
+        let mut left = hit_dice;
+        let mut rolls = Vec::new();
+        let mut rolled = 0;
+        for class in self.classes_by_hit_die() {
+            let count = left.min(class.hit_dice.remaining());
+            let (class_rolls, class_rolled) = class.hit_dice.spend(count, con, rng)?;
+            rolls.extend(class_rolls);
+            rolled += class_rolled;
+            left -= count;
+        }
+        let healed = rolled.min(self.max_health - self.health);
+        self.health += healed;
+        if let Some(spellcaster) = self.spellcaster.as_mut() {
+            spellcaster.short_rest();
+        }
+        Ok(ShortRest { rolls, healed })
+    }
+
+    /// Restores all hit points and spell slots and half of the spent hit dice.
+    pub fn long_rest(&mut self) -> Result<LongRest, RestError> {
+        self.can_rest()?;
+        let healed = self.max_health - self.health;
+        self.health = self.max_health;
+        let total: u32 = self.hit_dice().map(HitDice::total).sum();
+        let mut hit_dice_recovered = 0;
+        for class in self.classes_by_hit_die() {
+            hit_dice_recovered += class.hit_dice.restore((total / 2).max(1) - hit_dice_recovered);
+        }
+        if let Some(spellcaster) = self.spellcaster.as_mut() {
+            spellcaster.long_rest();
+        }
+        Ok(LongRest { healed, hit_dice_recovered })
+    }
+
+    /// Adds `xp` and returns how many level ups are now waiting to be applied.
+    pub fn gain_experience(&mut self, xp: u32) -> u32 {
+        self.experience.gain(xp);
+        self.experience.earned_level().saturating_sub(self.level)
+    }
+
+    /// The choices the player has to make before the next level can be applied.
+    pub fn pending_level_up(&self) -> Option<PendingLevelUp> {
+        self.pending_level_up_in(self.class().class_type)
+    }
+
+    /// Like [`Footman::pending_level_up`] for taking the next level in `class`, which may be a new
+    /// one. Whether the character qualifies for it is up to [`Footman::can_take_level_in`].
+    pub fn pending_level_up_in(&self, class: ClassType) -> Option<PendingLevelUp> {
+        if self.experience.earned_level() <= self.level {
+            return None
+        }
+        let level = self.level + 1;
+        let class_level = self.class_level(class) + 1;
+        let data = catalog().class(class);
+        let mut choices = vec![LevelUpChoice::HitPoints { hit_die: data.hit_die, average: data.hit_die / 2 + 1 }];
+        if grants_ability_score_improvement(class, class_level) {
+            choices.push(LevelUpChoice::AbilityScoreImprovement);
+        }
+        if class_level == 1 && data.multiclass.skills > 0 {
+            choices.push(LevelUpChoice::Skills { class, count: data.multiclass.skills });
+        }
+        Some(PendingLevelUp { level, class, class_level, choices })
+    }
+
+    /// Applies the next level in the class of the `decision`, multiclassing into it if it is a new
+    /// one.
+    pub fn level_up<R: Rng + ?Sized>(&mut self, decision: LevelUpDecision, rng: &mut R) -> Result<LevelUp, LevelUpError> {
+        let class = decision.class.unwrap_or(self.class().class_type);
+        let pending = self.pending_level_up_in(class).ok_or(LevelUpError::NothingPending)?;
+        self.can_take_level_in(class)?;
+        let skill_count = pending.choices.iter()
+            .find_map(|choice| match choice {
+                LevelUpChoice::Skills { count, .. } => Some(*count),
+                _ => None,
+            })
+            .unwrap_or(0);
+        let skill_choices = SkillChoices { count: skill_count, from: catalog().class(class).skill_choices.from.clone() };
+        if !skill_choices.allows(&decision.skills) {
+            return Err(LevelUpError::InvalidSkills(class))
+        }
+        let wants_improvement = pending.choices.contains(&LevelUpChoice::AbilityScoreImprovement);
+        let ability = match (wants_improvement, decision.ability_score_improvement) {
+            (true, None) => return Err(LevelUpError::MissingAbilityScoreImprovement { level: pending.level }),
+            (false, Some(_)) => return Err(LevelUpError::UnexpectedAbilityScoreImprovement { level: pending.level }),
+            (_, Some(improvement)) => self.improved_ability(improvement)?,
+            (_, None) => self.ability.clone(),
+        };
+
+        // a higher Constitution modifier applies to every level the character already has
+        let old_con = self.ability.modifier(AbilityType::Constitution);
+        let con = ability.modifier(AbilityType::Constitution);
+        let retroactive = (con - old_con) * self.level as i32;
+
+        let (die, hit_point_roll) = match pending.choices[0] {
+            LevelUpChoice::HitPoints { average, .. } if decision.hit_points == HitPointChoice::Average =>
+                (average as i32, None),
+            LevelUpChoice::HitPoints { hit_die, .. } => {
+                let roll = DiceExpr::new(Dice::new(1, hit_die)).roll(rng);
+                (roll.total, Some(roll))
+            }
+            _ => unreachable!("hit points are always the first choice"),
+        };
+        let gained = (die + con).max(1) + retroactive;
+
+        self.ability = ability;
+        self.level = pending.level;
+        match self.classes.iter_mut().find(|c| c.class.class_type == class) {
+            Some(taken) => {
+                taken.level = pending.class_level;
+                taken.hit_dice.set_level(taken.level);
+            }
+            None => self.multiclass(class, &decision.skills),
+        }
+        let levels = self.class_levels();
+        match self.spellcaster.as_mut() {
+            Some(spellcaster) => spellcaster.set_levels(&levels),
+            None => self.spellcaster = Spellcaster::multiclass(&levels),
+        }
+        self.max_health = (self.max_health as i32 + gained).max(1) as u32;
+        self.health = (self.health as i32 + gained).clamp(0, self.max_health as i32) as u32;
+
+        Ok(LevelUp {
+            level: self.level,
+            class,
+            class_level: pending.class_level,
+            hit_points_gained: gained.max(0) as u32,
+            hit_point_roll,
+            proficiency_bonus: self.proficiency_bonus(),
+            ability_score_improvement: decision.ability_score_improvement,
+        })
+    }
+
+    /// Takes up `class` at its first level with the proficiencies multiclassing into it grants.
+    fn multiclass(&mut self, class: ClassType, skills: &[SkillType]) {
+        let class = CharacterClass::multiclass(class);
+        for proficiency in class.proficiencies.get_proficiency_list() {
+            self.proficiencies.add_proficiency(proficiency);
+        }
+        skills.iter().for_each(|&skill| self.proficiencies.add_skill(skill));
+        self.classes.push(ClassLevel::new(class, 1));
+    }
+
+    fn improved_ability(&self, improvement: AbilityScoreImprovement) -> Result<Ability, LevelUpError> {
+        if let AbilityScoreImprovement::Pair(a, b) = improvement {
+            if a == b {
+                return Err(LevelUpError::SameAbilityTwice(a))
+            }
+        }
+        let mut ability = self.ability.clone();
+        for (ability_type, increase) in improvement.increases() {
+            let score = ability.score_mut(ability_type);
+            *score += increase;
+            if *score > 20 {
+                return Err(LevelUpError::ScoreAboveMaximum { ability: ability_type, score: *score })
+            }
+        }
+        Ok(ability)
+    }
+}
+
+impl Character for Footman {
+    fn ability(&self) -> &Ability {
+        &self.ability
+    }
+
+    fn name(&self) -> String {
+        self.name.clone()
+    }
+
+    fn level(&self) -> u32 {
+        self.level
+    }
+
+    fn health(&self) -> u32 {
+        self.health
+    }
+
+    fn max_health(&self) -> u32 {
+        self.max_health
+    }
+
+    fn is_alive(&self) -> bool {
+        !self.life.is_dead()
+    }
+
+    fn armor_class(&self) -> u8 {
+        self.equipment().armor_class(unarmored_armor_class(self.class(), &self.ability), &self.ability)
+    }
+
+    fn alignment(&self) -> Alignment {
+        self.alignment
+    }
+
+    /// The class the character started out in.
+    fn class(&self) -> &CharacterClass {
+        &self.classes[0].class
+    }
+
+    fn race(&self) -> &CharacterRace {
+        &self.race
+    }
+
+    fn proficiencies(&self) -> &Proficiencies {
+        &self.proficiencies
+    }
+
+    fn armor_penalty(&self) -> ArmorPenalty {
+        self.equipment().penalty(&self.ability, &self.proficiencies)
+    }
+
+    fn encumbrance(&self) -> Encumbrance {
+        self.inventory.encumbrance(self.ability.strength)
+    }
+
+    fn conditions(&self) -> Option<&Conditions> {
+        Some(&self.conditions)
+    }
+}
+
 #[derive(Debug, Clone)]
-struct HumanWarrior {
+pub struct FootmanBuilder {
+    name: String,
+    race: CharacterRace,
+    class: CharacterClass,
+    base_ability: Ability,
+    level: u32,
+    alignment: Alignment,
+    faction: Option<String>,
+    items: Vec<(ItemData, u32)>,
+    equipped: Vec<(EquipmentSlot, String)>,
+}
+
+impl FootmanBuilder {
+    pub fn new(name: impl Into<String>, race: RaceType, class: ClassType) -> Self {
+        FootmanBuilder {
+            name: name.into(),
+            race: race.into(),
+            class: class.into(),
+            base_ability: Ability {
+                strength: 10,
+                dexterity: 10,
+                constitution: 10,
+                intelligence: 10,
+                wisdom: 10,
+                charisma: 10,
+                hit_points: 0,
+            },
+            level: 1,
+            alignment: Alignment::new(Moral::Neutral, Ethical::Neutral),
+            faction: None,
+            items: Vec::new(),
+            equipped: Vec::new(),
+        }
+    }
+
+    pub fn ability(mut self, base_ability: Ability) -> Self {
+        self.base_ability = base_ability;
+        self
+    }
+
+    pub fn level(mut self, level: u32) -> Self {
+        self.level = level.clamp(1, 20);
+        self
+    }
+
+    pub fn alignment(mut self, alignment: Alignment) -> Self {
+        self.alignment = alignment;
+        self
+    }
+
+    pub fn faction(mut self, faction: impl Into<String>) -> Self {
+        self.faction = Some(faction.into());
+        self
+    }
+
+    pub fn item(mut self, item: ItemData, count: u32) -> Self {
+        self.items.push((item, count));
+        self
+    }
+
+    /// Starts out with `item` equipped in `slot`. An item that does not fit stays in the pack.
+    pub fn equip(mut self, slot: EquipmentSlot, item: ItemData) -> Self {
+        self.equipped.push((slot, item.name.clone()));
+        self.item(item, 1)
+    }
+
+    pub fn build(self) -> Footman {
+        let ability = self.base_ability + self.race.ability_modifier.clone() + self.class.ability_modifier.clone();
+        let max_health = max_hit_points(&self.class, &ability, self.level) + ability.hit_points;
+        let spellcaster = Spellcaster::new(self.class.class_type, self.level);
+        let mut footman = Footman {
+            name: self.name,
+            level: self.level,
+            proficiencies: self.class.proficiencies,
+            race: self.race,
+            classes: vec![ClassLevel::new(self.class, self.level)],
+            ability,
+            health: max_health,
+            max_health,
+            alignment: self.alignment,
+            experience: Experience::at_level(self.level),
+            spellcaster,
+            life: LifeState::Conscious,
+            faction: self.faction,
+            reputation: Reputation::default(),
+            inventory: Inventory::default(),
+            conditions: Conditions::default(),
+        };
+        for (item, count) in &self.items {
+            footman.add_item(item, *count);
+        }
+        for (slot, name) in &self.equipped {
+            // a misfit is no reason to fail, it just stays in the pack
+            let _ = footman.equip_item(*slot, name);
+        }
+        footman
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct HumanWarrior {
     ability: Ability,
     name: String,
     level: u32,
@@ -54,73 +757,57 @@ struct HumanWarrior {
     armor_class: u8,
     alignment: Alignment,
     proficiencies: Proficiencies,
+    race: CharacterRace,
+    class: CharacterClass,
 }
 
 impl Character for HumanWarrior {
 
     fn ability(&self) -> &Ability {
-        todo!()
+        &self.ability
     }
 
     fn name(&self) -> String {
-        todo!()
+        self.name.clone()
     }
 
     fn level(&self) -> u32 {
-        todo!()
+        self.level
     }
 
     fn health(&self) -> u32 {
-        todo!()
+        self.health
+    }
+
+    fn max_health(&self) -> u32 {
+        self.health
     }
 
     fn armor_class(&self) -> u8 {
-        todo!()
+        self.armor_class
     }
 
     fn alignment(&self) -> Alignment {
-        todo!()
+        self.alignment
     }
 
     fn class(&self) -> &CharacterClass {
-        todo!()
+        &self.class
     }
 
     fn race(&self) -> &CharacterRace {
-        todo!()
+        &self.race
+    }
+
+    fn proficiencies(&self) -> &Proficiencies {
+        &self.proficiencies
     }
 }
 
 impl HumanWarrior {
-    fn new(name: String, level: u32, health: u32, armor_class: u8) -> Self {
-        let human = CharacterRace {
-            name: "Human".to_string(),
-            size: "Medium".to_string(),
-            speed: 30,
-            ability_modifier: Ability {
-                strength: 1,
-                dexterity: 1,
-                constitution: 1,
-                intelligence: 1,
-                wisdom: 1,
-                charisma: 1,
-                hit_points: 0,
-            }
-        };
-
-        let warrior = CharacterClass {
-            name: "Warrior".to_string(),
-            proficiencies: Proficiencies::empty(),
-            ability_modifier: Ability {
-                strength: 1,
-                dexterity: 1,
-                constitution: 1,
-                intelligence: 1,
-                wisdom: 1,
-                charisma: 1,
-                hit_points: 0,
-            }
-        };
+    pub fn new(name: String, level: u32, health: u32, armor_class: u8) -> Self {
+        let human = CharacterRace::from(RaceType::Human);
+        let warrior = CharacterClass::from(ClassType::Fighter);
 
         let base_ability = Ability {
             strength: 10,
@@ -131,7 +818,7 @@ impl HumanWarrior {
             charisma: 10,
             hit_points: 10,
         };
-        let ability = base_ability + human.ability_modifier + warrior.ability_modifier;
+        let ability = base_ability + human.ability_modifier.clone() + warrior.ability_modifier.clone();
         HumanWarrior {
             ability,
             name,
@@ -142,14 +829,17 @@ impl HumanWarrior {
                 Moral::Good,
                 Ethical::Neutral,
             ),
-            proficiencies: Proficiencies::empty()
+            proficiencies: warrior.proficiencies,
+            race: human,
+            class: warrior,
         }
     }
 }
 
 #[cfg(test)]
 mod test {
-    use crate::dnd::enums::{AbilityType, ArmorType, ShieldType, SkillType, WeaponType};
+    use crate::dnd::enums::{AbilityType, ArmorType, ClassType, RaceType, ShieldType, SkillType, WeaponType};
+    use super::{Character, Footman};
 
     #[test]
     fn test_human_warrior() {
@@ -172,4 +862,275 @@ mod test {
         human_warrior.proficiencies.add_weapon(WeaponType::MartialMelee);
         println!("{:?}", human_warrior.proficiencies.get_proficiency_list());
     }
-}
\ No newline at end of file
+
+    #[test]
+    fn test_footman_builder() {
+        let base = super::Ability {
+            strength: 15,
+            dexterity: 14,
+            constitution: 13,
+            intelligence: 12,
+            wisdom: 10,
+            charisma: 8,
+            hit_points: 0,
+        };
+        let dwarf = Footman::builder("Gimli", RaceType::Dwarf, ClassType::Barbarian)
+            .ability(base.clone())
+            .level(3)
+            .build();
+        assert_eq!(dwarf.race().name(), "Dwarf");
+        assert_eq!(dwarf.class().name(), "Barbarian");
+        assert_eq!(dwarf.ability().constitution, 15);
+        assert_eq!(dwarf.speed(), 25);
+        // 12 + 2 at first level, then (7 + 2) for each of the next two levels
+        assert_eq!(dwarf.max_health(), 32);
+        // 10 + dex 2 + con 2
+        assert_eq!(dwarf.armor_class(), 14);
+        assert_eq!(dwarf.proficiency_bonus(), 2);
+
+        let gnome = Footman::builder("Fizban", RaceType::Gnome, ClassType::Wizard)
+            .ability(base)
+            .build();
+        assert_eq!(gnome.ability().intelligence, 14);
+        assert_eq!(gnome.max_health(), 7);
+        assert_eq!(gnome.armor_class(), 12);
+        assert!(gnome.is_alive());
+    }
+
+    #[test]
+    fn test_equipment() {
+        use crate::dnd::catalog::catalog;
+        use crate::tools::inventory::EquipmentSlot;
+
+        let item = |name| catalog().item(name).unwrap().clone();
+        let mut fighter = Footman::builder("Bob", RaceType::Human, ClassType::Fighter)
+            .equip(EquipmentSlot::Armor, item("Chain Mail"))
+            .build();
+        // 16 from chain mail, strength 11 is short of the required 13
+        assert_eq!(fighter.armor_class(), 16);
+        assert_eq!(fighter.speed(), 20);
+        assert!(fighter.armor_penalty().stealth_disadvantage);
+
+        fighter.add_item(&item("Heater Shield"), 1);
+        fighter.equip_item(EquipmentSlot::OffHand, "Heater Shield").unwrap();
+        assert_eq!(fighter.armor_class(), 18);
+        assert_eq!(fighter.equipment().shield.unwrap().shield, ShieldType::Heater);
+        assert_eq!(fighter.unequip_item(EquipmentSlot::Armor).unwrap().name, "Chain Mail");
+        assert_eq!(fighter.equipment().armor, None);
+        assert_eq!(fighter.armor_class(), 12);
+        fighter.remove_item("Chain Mail", 1).unwrap();
+        assert_eq!(fighter.speed(), 30);
+        assert!(!fighter.armor_penalty().untrained);
+    }
+
+    #[test]
+    fn test_conditions() {
+        use crate::dnd::check::CheckKind;
+        use crate::dnd::condition::{Condition, ConditionType};
+        use crate::dnd::dice::RollMode;
+
+        let mut fighter = Footman::builder("Bob", RaceType::Human, ClassType::Fighter).build();
+        assert_eq!(fighter.attack_mode(), RollMode::Normal);
+        fighter.conditions_mut().apply(Condition::for_seconds(ConditionType::Restrained, 6.0));
+        assert_eq!(fighter.attack_mode(), RollMode::Disadvantage);
+        assert_eq!(fighter.check_mode(CheckKind::SavingThrow(AbilityType::Dexterity)), RollMode::Disadvantage);
+        assert_eq!(fighter.check_mode(CheckKind::SavingThrow(AbilityType::Wisdom)), RollMode::Normal);
+        // conditions are kept with the character
+        let saved: Footman = serde_json::from_str(&serde_json::to_string(&fighter).unwrap()).unwrap();
+        assert!(saved.conditions().is_some_and(|conditions| conditions.has(ConditionType::Restrained)));
+    }
+
+    #[test]
+    fn test_inventory() {
+        use crate::dnd::catalog::catalog;
+        use crate::tools::inventory::{Encumbrance, EquipmentSlot};
+
+        let item = |name| catalog().item(name).unwrap().clone();
+        let mut fighter = Footman::builder("Bob", RaceType::Human, ClassType::Fighter)
+            .equip(EquipmentSlot::MainHand, item("Longsword"))
+            .equip(EquipmentSlot::Armor, item("Chain Mail"))
+            .equip(EquipmentSlot::Armor, item("Rope"))
+            .item(item("Kite Shield"), 1)
+            .build();
+        assert_eq!(fighter.weapon().unwrap().name, "Longsword");
+        assert_eq!(fighter.armor_class(), 16);
+        assert_eq!(fighter.inventory().count("Rope"), 1);
+        // 76 pounds are more than strength 11 carries with ease, on top of the chain mail being too heavy
+        assert_eq!(fighter.encumbrance(), Encumbrance::Encumbered);
+        assert_eq!(fighter.speed(), 10);
+
+        fighter.equip_item(EquipmentSlot::OffHand, "Kite Shield").unwrap();
+        assert_eq!(fighter.armor_class(), 19);
+        assert_eq!(fighter.unequip_item(EquipmentSlot::Armor).unwrap().name, "Chain Mail");
+        assert_eq!(fighter.armor_class(), 13);
+        fighter.remove_item("Chain Mail", 1).unwrap();
+        assert_eq!(fighter.encumbrance(), Encumbrance::Unencumbered);
+        assert_eq!(fighter.speed(), 20);
+    }
+
+    #[test]
+    fn test_serialization() {
+        use crate::dnd::catalog::catalog;
+        use crate::dnd::enums::Proficiencies;
+        use crate::tools::inventory::EquipmentSlot;
+
+        let fighter = Footman::builder("Bob", RaceType::Human, ClassType::Fighter)
+            .equip(EquipmentSlot::Armor, catalog().item("Chain Mail").unwrap().clone())
+            .build();
+        let json = serde_json::to_string(&fighter).unwrap();
+        let from_json: Footman = serde_json::from_str(&json).unwrap();
+        let ron = ron::to_string(&fighter).unwrap();
+        let from_ron: Footman = ron::from_str(&ron).unwrap();
+        for copy in [from_json, from_ron] {
+            assert_eq!(copy.ability(), fighter.ability());
+            assert_eq!(copy.proficiencies(), fighter.proficiencies());
+            assert_eq!(copy.alignment(), fighter.alignment());
+            assert_eq!(copy.armor_class(), fighter.armor_class());
+        }
+
+        let mut proficiencies = Proficiencies::empty();
+        proficiencies.add_armor(ArmorType::Light);
+        proficiencies.add_skill(SkillType::Stealth);
+        assert_eq!(
+            serde_json::to_string(&proficiencies).unwrap(),
+            r#"{"armor":["Light"],"weapons":[],"shields":[],"saving_throws":[],"skills":["Stealth"]}"#
+        );
+        let parsed: Proficiencies = ron::from_str("(armor: [Light], skills: [Stealth])").unwrap();
+        assert_eq!(parsed, proficiencies);
+
+        let err = serde_json::from_str::<Proficiencies>(r#"{"armor": ["Mithril"]}"#).unwrap_err();
+        assert!(err.to_string().contains("unknown variant `Mithril`"), "{}", err);
+        assert!(serde_json::from_str::<Proficiencies>(r#"{"armour": []}"#).is_err());
+    }
+
+    #[test]
+    fn test_level_up() {
+        use rand::rngs::StdRng;
+        use rand::SeedableRng;
+        use crate::dnd::progression::{AbilityScoreImprovement, LevelUpChoice, LevelUpDecision, LevelUpError};
+
+        let mut rng = StdRng::seed_from_u64(0);
+        let mut fighter = Footman::builder("Bob", RaceType::Human, ClassType::Fighter).level(3).build();
+        assert_eq!(fighter.max_health(), 10 + 2 * 6);
+        assert!(fighter.pending_level_up().is_none());
+        assert_eq!(fighter.level_up(LevelUpDecision::average(), &mut rng), Err(LevelUpError::NothingPending));
+
+        assert_eq!(fighter.gain_experience(6_000), 2);
+        let pending = fighter.pending_level_up().unwrap();
+        assert_eq!(pending.level, 4);
+        assert!(pending.choices.contains(&LevelUpChoice::AbilityScoreImprovement));
+        assert_eq!(
+            fighter.level_up(LevelUpDecision::average(), &mut rng),
+            Err(LevelUpError::MissingAbilityScoreImprovement { level: 4 })
+        );
+
+        // +1 Constitution moves the modifier from 0 to +1, which is worth a hit point per level
+        let decision = LevelUpDecision::average()
+            .with_improvement(AbilityScoreImprovement::Pair(AbilityType::Strength, AbilityType::Constitution));
+        let level_up = fighter.level_up(decision, &mut rng).unwrap();
+        assert_eq!(level_up.level, 4);
+        assert_eq!(level_up.hit_points_gained, 6 + 1 + 3);
+        assert_eq!(fighter.ability().constitution, 12);
+        assert_eq!(fighter.max_health(), 22 + 10);
+
+        let level_up = fighter.level_up(LevelUpDecision::rolled(), &mut rng).unwrap();
+        let rolled = level_up.hit_point_roll.unwrap().total;
+        assert!((1..=10).contains(&rolled));
+        assert_eq!(level_up.hit_points_gained as i32, rolled + 1);
+        assert_eq!(level_up.proficiency_bonus, 3);
+        assert!(fighter.pending_level_up().is_none());
+    }
+
+    #[test]
+    fn test_multiclass() {
+        use rand::rngs::StdRng;
+        use rand::SeedableRng;
+        use crate::dnd::ability::Ability;
+        use crate::dnd::progression::{LevelUpChoice, LevelUpDecision, LevelUpError};
+
+        let mut rng = StdRng::seed_from_u64(0);
+        let ability = Ability {
+            strength: 15, dexterity: 12, constitution: 14, intelligence: 13, wisdom: 10, charisma: 8, hit_points: 0,
+        };
+        let mut fighter = Footman::builder("Bob", RaceType::Human, ClassType::Fighter).ability(ability).build();
+        fighter.gain_experience(900);
+        assert_eq!(fighter.can_take_level_in(ClassType::Paladin), Err(LevelUpError::PrerequisitesNotMet(ClassType::Paladin)));
+        assert_eq!(
+            fighter.level_up(LevelUpDecision::average().in_class(ClassType::Paladin), &mut rng),
+            Err(LevelUpError::PrerequisitesNotMet(ClassType::Paladin))
+        );
+
+        let pending = fighter.pending_level_up_in(ClassType::Rogue).unwrap();
+        assert_eq!(pending.class_level, 1);
+        assert!(pending.choices.contains(&LevelUpChoice::Skills { class: ClassType::Rogue, count: 1 }));
+        let too_many = LevelUpDecision::average().in_class(ClassType::Rogue)
+            .with_skills(vec![SkillType::Stealth, SkillType::Acrobatics]);
+        assert_eq!(fighter.level_up(too_many, &mut rng), Err(LevelUpError::InvalidSkills(ClassType::Rogue)));
+        let level_up = fighter.level_up(LevelUpDecision::average().in_class(ClassType::Rogue).with_skills(vec![SkillType::Stealth]), &mut rng).unwrap();
+        assert_eq!((level_up.level, level_up.class, level_up.class_level), (2, ClassType::Rogue, 1));
+        assert_eq!(level_up.hit_points_gained, 5 + 2);
+        assert!(fighter.proficiencies().has_skill(SkillType::Stealth));
+        assert!(!fighter.proficiencies().has_saving_throw(AbilityType::Dexterity));
+        assert_eq!(fighter.class().class_type, ClassType::Fighter);
+        assert!(fighter.spellcaster().is_none());
+
+        let level_up = fighter.level_up(LevelUpDecision::average().in_class(ClassType::Wizard), &mut rng).unwrap();
+        assert_eq!(level_up.class_level, 1);
+        assert_eq!(fighter.class_levels(), vec![(ClassType::Fighter, 1), (ClassType::Rogue, 1), (ClassType::Wizard, 1)]);
+        assert_eq!(fighter.spellcaster().unwrap().slots.max(1), 2);
+        // a d10, a d8 and a d6, to be spent biggest first
+        assert_eq!(fighter.hit_dice_remaining(), 3);
+        assert_eq!(fighter.short_rest(1, &mut rng).unwrap().rolls.len(), 1);
+        assert_eq!(fighter.classes()[0].hit_dice.remaining(), 0);
+    }
+
+    #[test]
+    fn test_dying_and_resting() {
+        use rand::rngs::StdRng;
+        use rand::SeedableRng;
+        use crate::dnd::death::{LifeEvent, LifeState};
+        use crate::dnd::rest::RestError;
+
+        let mut rng = StdRng::seed_from_u64(1);
+        let mut fighter = Footman::builder("Bob", RaceType::Human, ClassType::Fighter).level(2).build();
+        assert_eq!(fighter.max_health(), 16);
+        assert_eq!(fighter.take_damage(10, false, &mut rng).event, None);
+        let rest = fighter.short_rest(1, &mut rng).unwrap();
+        assert_eq!(fighter.health(), 6 + rest.healed);
+        assert_eq!(fighter.hit_dice_remaining(), 1);
+
+        assert_eq!(fighter.take_damage(20, false, &mut rng).event, Some(LifeEvent::Downed));
+        assert_eq!(fighter.health(), 0);
+        assert!(fighter.is_alive());
+        assert_eq!(fighter.short_rest(1, &mut rng), Err(RestError::Unconscious));
+        fighter.take_damage(1, true, &mut rng);
+        assert!(matches!(fighter.life(), LifeState::Dying(saves) if saves.failures == 2));
+        assert_eq!(fighter.stabilize(), Some(LifeEvent::Stabilized));
+        assert_eq!(fighter.death_save(&mut rng), None);
+
+        assert_eq!(fighter.heal(3), Some(LifeEvent::Revived));
+        assert_eq!(fighter.health(), 3);
+        let rest = fighter.long_rest().unwrap();
+        assert_eq!(rest.healed, 13);
+        assert_eq!(rest.hit_dice_recovered, 1);
+        assert_eq!(fighter.hit_dice_remaining(), 2);
+        fighter.heal(u32::MAX);
+        assert_eq!(fighter.health(), 16);
+
+        // damage past 0 that reaches the hit point maximum kills outright
+        assert_eq!(fighter.take_damage(32, false, &mut rng).event, Some(LifeEvent::Died));
+        assert!(!fighter.is_alive());
+        assert_eq!(fighter.heal(10), None);
+        assert_eq!(fighter.long_rest(), Err(RestError::Dead));
+
+        let mut fighter = Footman::builder("Bob", RaceType::Human, ClassType::Fighter).build();
+        fighter.take_damage(10, false, &mut rng);
+        while fighter.life().is_dying() {
+            fighter.death_save(&mut rng).unwrap();
+        }
+        match fighter.life() {
+            LifeState::Conscious => assert_eq!(fighter.health(), 1),
+            life => assert!(matches!(life, LifeState::Stable | LifeState::Dead)),
+        }
+    }
+}
@@ -0,0 +1,158 @@
+use std::fmt::{Display, Formatter};
+use rand::Rng;
+use crate::dnd::ability::Ability;
+use crate::dnd::dice::{Dice, DiceExpr, Roll};
+use crate::dnd::enums::AbilityType;
+
+pub const POINT_BUY_BUDGET: u32 = 27;
+pub const STANDARD_ARRAY: [u8; 6] = [15, 14, 13, 12, 10, 8];
+
+pub const ABILITY_ORDER: [AbilityType; 6] = [
+    AbilityType::Strength,
+    AbilityType::Dexterity,
+    AbilityType::Constitution,
+    AbilityType::Intelligence,
+    AbilityType::Wisdom,
+    AbilityType::Charisma,
+];
+
+/// Cost of a score under the 27-point buy, `None` outside of the purchasable 8..=15 range.
+#[rustfmt::skip]
+pub fn point_buy_cost(score: u8) -> Option<u32> {
+    match score {
+        8  => Some(0),
+        9  => Some(1),
+        10 => Some(2),
+        11 => Some(3),
+        12 => Some(4),
+        13 => Some(5),
+        14 => Some(7),
+        15 => Some(9),
+        _  => None,
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum GenerationError {
+    InvalidScores(Vec<i32>),
+    ScoreOutOfRange { ability: AbilityType, score: u8 },
+    OverBudget { spent: u32, budget: u32 },
+    NotAPermutation { scores: Vec<u8>, expected: Vec<u8> },
+}
+
+impl Display for GenerationError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            GenerationError::InvalidScores(scores) =>
+                write!(f, "expected six scores between 0 and 255, got {:?}", scores),
+            GenerationError::ScoreOutOfRange { ability, score } =>
+                write!(f, "{:?} {} cannot be bought, point buy scores must be between 8 and 15", ability, score),
+            GenerationError::OverBudget { spent, budget } =>
+                write!(f, "spent {} points but only {} are available", spent, budget),
+            GenerationError::NotAPermutation { scores, expected } =>
+                write!(f, "scores {:?} must use each of {:?} exactly once", scores, expected),
+        }
+    }
+}
+
+impl std::error::Error for GenerationError {}
+
+fn ability_from_scores(scores: [u8; 6]) -> Ability {
+    let mut ability = Ability::default();
+    for (ability_type, score) in ABILITY_ORDER.iter().zip(scores) {
+        *ability.score_mut(*ability_type) = score;
+    }
+    ability
+}
+
+/// Scores in `ABILITY_ORDER`, i.e. strength first and charisma last.
+pub fn point_buy(scores: [u8; 6]) -> Result<Ability, GenerationError> {
+    let mut spent = 0;
+    for (ability, score) in ABILITY_ORDER.iter().zip(scores) {
+        spent += point_buy_cost(score).ok_or(GenerationError::ScoreOutOfRange { ability: *ability, score })?;
+    }
+    if spent > POINT_BUY_BUDGET {
+        return Err(GenerationError::OverBudget { spent, budget: POINT_BUY_BUDGET })
+    }
+    Ok(ability_from_scores(scores))
+}
+
+pub fn point_buy_remaining(scores: [u8; 6]) -> Option<i32> {
+    let spent: Option<u32> = scores.iter().map(|&score| point_buy_cost(score)).sum();
+    spent.map(|spent| POINT_BUY_BUDGET as i32 - spent as i32)
+}
+
+/// Assigns the standard array, `scores` has to be a permutation of `STANDARD_ARRAY`.
+pub fn standard_array(scores: [u8; 6]) -> Result<Ability, GenerationError> {
+    assign_permutation(scores, STANDARD_ARRAY)
+}
+
+/// Assigns previously rolled scores, e.g. from [`roll_scores`], in whatever order the player picked.
+pub fn assign_rolled(scores: [u8; 6], rolled: [u8; 6]) -> Result<Ability, GenerationError> {
+    assign_permutation(scores, rolled)
+}
+
+fn assign_permutation(scores: [u8; 6], expected: [u8; 6]) -> Result<Ability, GenerationError> {
+    let mut sorted = scores;
+    let mut expected_sorted = expected;
+    sorted.sort_unstable();
+    expected_sorted.sort_unstable();
+    if sorted != expected_sorted {
+        return Err(GenerationError::NotAPermutation { scores: scores.to_vec(), expected: expected.to_vec() })
+    }
+    Ok(ability_from_scores(scores))
+}
+
+/// Six rolls of 4d6, dropping the lowest die of each.
+pub fn roll_scores<R: Rng + ?Sized>(rng: &mut R) -> [Roll; 6] {
+    let expr = DiceExpr::new(Dice::new(4, 6).keep_highest(3));
+    std::array::from_fn(|_| expr.roll(rng))
+}
+
+pub fn roll_ability<R: Rng + ?Sized>(rng: &mut R) -> (Ability, [Roll; 6]) {
+    let rolls = roll_scores(rng);
+    let scores = std::array::from_fn(|i| rolls[i].total as u8);
+    (ability_from_scores(scores), rolls)
+}
+
+#[cfg(test)]
+mod test {
+    use rand::rngs::StdRng;
+    use rand::SeedableRng;
+    use super::*;
+
+    #[test]
+    fn test_point_buy() {
+        let ability = point_buy([15, 15, 15, 8, 8, 8]).unwrap();
+        assert_eq!((ability.strength, ability.charisma), (15, 8));
+        assert_eq!(point_buy_remaining([15, 15, 15, 8, 8, 8]), Some(0));
+        assert_eq!(point_buy_remaining([8; 6]), Some(27));
+
+        assert_eq!(
+            point_buy([15, 15, 15, 9, 8, 8]),
+            Err(GenerationError::OverBudget { spent: 28, budget: 27 })
+        );
+        assert_eq!(
+            point_buy([16, 8, 8, 8, 8, 8]),
+            Err(GenerationError::ScoreOutOfRange { ability: AbilityType::Strength, score: 16 })
+        );
+    }
+
+    #[test]
+    fn test_standard_array() {
+        let ability = standard_array([8, 10, 12, 13, 14, 15]).unwrap();
+        assert_eq!((ability.strength, ability.charisma), (8, 15));
+        assert!(matches!(standard_array([15, 15, 13, 12, 10, 8]), Err(GenerationError::NotAPermutation { .. })));
+    }
+
+    #[test]
+    fn test_roll_ability() {
+        let mut rng = StdRng::seed_from_u64(9);
+        let (ability, rolls) = roll_ability(&mut rng);
+        for (ability_type, roll) in ABILITY_ORDER.iter().zip(rolls.iter()) {
+            assert!((3..=18).contains(&roll.total));
+            assert_eq!(ability.score(*ability_type) as i32, roll.total);
+            assert_eq!(roll.dice().filter(|d| !d.kept).count(), 1);
+        }
+    }
+}
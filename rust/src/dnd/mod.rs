@@ -1,4 +1,17 @@
 pub mod enums;
 pub mod ability;
 pub mod alignment;
-pub mod footman;
\ No newline at end of file
+pub mod footman;
+pub mod dice;
+pub mod check;
+pub mod catalog;
+pub mod progression;
+pub mod generation;
+pub mod character_creation;
+pub mod attack;
+pub mod condition;
+pub mod spell;
+pub mod death;
+pub mod rest;
+pub mod encounter;
+pub mod reaction;
\ No newline at end of file
@@ -0,0 +1,218 @@
+use std::fmt::{Display, Formatter};
+use serde::{Deserialize, Serialize};
+use crate::dnd::dice::Roll;
+use crate::dnd::enums::{AbilityType, ClassType, SkillType};
+
+pub const MAX_LEVEL: u32 = 20;
+
+#[rustfmt::skip]
+const XP_THRESHOLDS: [u32; MAX_LEVEL as usize] = [
+          0,     300,     900,   2_700,   6_500,
+     14_000,  23_000,  34_000,  48_000,  64_000,
+     85_000, 100_000, 120_000, 140_000, 165_000,
+    195_000, 225_000, 265_000, 305_000, 355_000,
+];
+
+pub fn xp_for_level(level: u32) -> u32 {
+    XP_THRESHOLDS[(level.clamp(1, MAX_LEVEL) - 1) as usize]
+}
+
+pub fn level_for_xp(xp: u32) -> u32 {
+    XP_THRESHOLDS.iter().take_while(|&&threshold| threshold <= xp).count() as u32
+}
+
+pub fn grants_ability_score_improvement(class: ClassType, level: u32) -> bool {
+    matches!(
+        (class, level),
+        (_, 4 | 8 | 12 | 16 | 19) | (ClassType::Fighter, 6 | 14) | (ClassType::Rogue, 10)
+    )
+}
+
+/// The XP ledger. It only counts experience, the level a character actually plays at only
+/// changes once the level up has been applied with the choices the player made.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+pub struct Experience {
+    xp: u32,
+}
+
+impl Experience {
+    pub fn new(xp: u32) -> Self {
+        Experience { xp }
+    }
+
+    pub fn at_level(level: u32) -> Self {
+        Experience::new(xp_for_level(level))
+    }
+
+    pub fn xp(&self) -> u32 {
+        self.xp
+    }
+
+    pub fn earned_level(&self) -> u32 {
+        level_for_xp(self.xp)
+    }
+
+    pub fn next_level_at(&self) -> Option<u32> {
+        XP_THRESHOLDS.iter().copied().find(|&threshold| threshold > self.xp)
+    }
+
+    /// Adds `xp` and returns how many levels it earned.
+    pub fn gain(&mut self, xp: u32) -> u32 {
+        let before = self.earned_level();
+        self.xp = self.xp.saturating_add(xp);
+        self.earned_level() - before
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum HitPointChoice {
+    Average,
+    Roll,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AbilityScoreImprovement {
+    /// +2 to a single score.
+    Single(AbilityType),
+    /// +1 to two different scores.
+    Pair(AbilityType, AbilityType),
+}
+
+impl AbilityScoreImprovement {
+    pub fn increases(&self) -> Vec<(AbilityType, u8)> {
+        match *self {
+            AbilityScoreImprovement::Single(ability) => vec![(ability, 2)],
+            AbilityScoreImprovement::Pair(a, b) => vec![(a, 1), (b, 1)],
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LevelUpChoice {
+    HitPoints { hit_die: u32, average: u32 },
+    AbilityScoreImprovement,
+    /// Skills to pick from the class's skill choices when multiclassing into it.
+    Skills { class: ClassType, count: u8 },
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PendingLevelUp {
+    /// The character level after the level up.
+    pub level: u32,
+    pub class: ClassType,
+    /// The level in `class` after the level up, `1` when multiclassing into it.
+    pub class_level: u32,
+    pub choices: Vec<LevelUpChoice>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct LevelUpDecision {
+    pub hit_points: HitPointChoice,
+    pub ability_score_improvement: Option<AbilityScoreImprovement>,
+    /// The class to take the level in, `None` for the class the character started in.
+    pub class: Option<ClassType>,
+    pub skills: Vec<SkillType>,
+}
+
+impl LevelUpDecision {
+    pub fn average() -> Self {
+        LevelUpDecision {
+            hit_points: HitPointChoice::Average,
+            ability_score_improvement: None,
+            class: None,
+            skills: Vec::new(),
+        }
+    }
+
+    pub fn rolled() -> Self {
+        LevelUpDecision { hit_points: HitPointChoice::Roll, ..LevelUpDecision::average() }
+    }
+
+    pub fn in_class(mut self, class: ClassType) -> Self {
+        self.class = Some(class);
+        self
+    }
+
+    pub fn with_skills(mut self, skills: Vec<SkillType>) -> Self {
+        self.skills = skills;
+        self
+    }
+
+    pub fn with_improvement(mut self, improvement: AbilityScoreImprovement) -> Self {
+        self.ability_score_improvement = Some(improvement);
+        self
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct LevelUp {
+    pub level: u32,
+    pub class: ClassType,
+    pub class_level: u32,
+    pub hit_points_gained: u32,
+    pub hit_point_roll: Option<Roll>,
+    pub proficiency_bonus: i32,
+    pub ability_score_improvement: Option<AbilityScoreImprovement>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum LevelUpError {
+    NothingPending,
+    MissingAbilityScoreImprovement { level: u32 },
+    UnexpectedAbilityScoreImprovement { level: u32 },
+    SameAbilityTwice(AbilityType),
+    ScoreAboveMaximum { ability: AbilityType, score: u8 },
+    /// Multiclassing needs the ability scores of the new class and of every class already taken.
+    PrerequisitesNotMet(ClassType),
+    InvalidSkills(ClassType),
+}
+
+impl Display for LevelUpError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            LevelUpError::NothingPending => write!(f, "not enough experience for the next level"),
+            LevelUpError::MissingAbilityScoreImprovement { level } =>
+                write!(f, "level {} grants an ability score improvement that has not been chosen", level),
+            LevelUpError::UnexpectedAbilityScoreImprovement { level } =>
+                write!(f, "level {} does not grant an ability score improvement", level),
+            LevelUpError::SameAbilityTwice(ability) =>
+                write!(f, "+1/+1 must go to two different abilities, got {:?} twice", ability),
+            LevelUpError::ScoreAboveMaximum { ability, score } =>
+                write!(f, "{:?} would become {}, ability score improvements cannot go above 20", ability, score),
+            LevelUpError::PrerequisitesNotMet(class) =>
+                write!(f, "ability scores fall short of the {} multiclassing prerequisites", class),
+            LevelUpError::InvalidSkills(class) => write!(f, "skills picked are not among the {} skill choices", class),
+        }
+    }
+}
+
+impl std::error::Error for LevelUpError {}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_thresholds() {
+        assert_eq!(level_for_xp(0), 1);
+        assert_eq!(level_for_xp(299), 1);
+        assert_eq!(level_for_xp(300), 2);
+        assert_eq!(level_for_xp(6_499), 4);
+        assert_eq!(level_for_xp(1_000_000), 20);
+        assert_eq!(xp_for_level(5), 6_500);
+
+        let mut experience = Experience::default();
+        assert_eq!(experience.gain(250), 0);
+        assert_eq!(experience.gain(700), 2);
+        assert_eq!(experience.earned_level(), 3);
+        assert_eq!(experience.next_level_at(), Some(2_700));
+    }
+
+    #[test]
+    fn test_ability_score_improvement_levels() {
+        let wizard: Vec<_> = (1..=20).filter(|&l| grants_ability_score_improvement(ClassType::Wizard, l)).collect();
+        let fighter: Vec<_> = (1..=20).filter(|&l| grants_ability_score_improvement(ClassType::Fighter, l)).collect();
+        assert_eq!(wizard, vec![4, 8, 12, 16, 19]);
+        assert_eq!(fighter, vec![4, 6, 8, 12, 14, 16, 19]);
+    }
+}
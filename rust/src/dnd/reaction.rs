@@ -0,0 +1,206 @@
+use std::collections::BTreeMap;
+use std::fmt::{Display, Formatter};
+use std::str::FromStr;
+use serde::{Deserialize, Serialize};
+use crate::dnd::alignment::Alignment;
+use crate::dnd::catalog::{catalog, Catalog};
+
+pub const MAX_REPUTATION: i32 = 100;
+/// Reputation points worth one step of attitude.
+const REPUTATION_PER_STEP: i32 = 25;
+/// How much sharing a faction, or belonging to allied or enemy factions, sways an attitude.
+const FACTION_WEIGHT: i32 = 3;
+
+/// How a creature feels about another, from the worst to the best.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+pub enum Attitude {
+    /// Attacks on sight.
+    Hostile,
+    Unfriendly,
+    Indifferent,
+    Friendly,
+}
+
+impl Attitude {
+    pub const ALL: [Attitude; 4] = [Attitude::Hostile, Attitude::Unfriendly, Attitude::Indifferent, Attitude::Friendly];
+
+    pub fn from_score(score: i32) -> Self {
+        match score {
+            i32::MIN..=-3 => Attitude::Hostile,
+            -2..=-1 => Attitude::Unfriendly,
+            0..=1 => Attitude::Indifferent,
+            _ => Attitude::Friendly,
+        }
+    }
+}
+
+impl Display for Attitude {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Attitude::Hostile => write!(f, "hostile"),
+            Attitude::Unfriendly => write!(f, "unfriendly"),
+            Attitude::Indifferent => write!(f, "indifferent"),
+            Attitude::Friendly => write!(f, "friendly"),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct UnknownAttitude(pub String);
+
+impl Display for UnknownAttitude {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "unknown attitude {:?}", self.0)
+    }
+}
+
+impl std::error::Error for UnknownAttitude {}
+
+impl FromStr for Attitude {
+    type Err = UnknownAttitude;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Attitude::ALL.into_iter()
+            .find(|attitude| attitude.to_string().eq_ignore_ascii_case(s))
+            .ok_or_else(|| UnknownAttitude(s.to_string()))
+    }
+}
+
+/// A faction as defined in the catalog, see [`Catalog::faction`].
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Faction {
+    pub name: String,
+    #[serde(default)]
+    pub allies: Vec<String>,
+    #[serde(default)]
+    pub enemies: Vec<String>,
+}
+
+impl Faction {
+    pub fn is_ally(&self, faction: &str) -> bool {
+        self.name.eq_ignore_ascii_case(faction) || self.allies.iter().any(|f| f.eq_ignore_ascii_case(faction))
+    }
+
+    pub fn is_enemy(&self, faction: &str) -> bool {
+        self.enemies.iter().any(|f| f.eq_ignore_ascii_case(faction))
+    }
+}
+
+/// What each faction thinks of a character, from -[`MAX_REPUTATION`] to [`MAX_REPUTATION`].
+#[derive(Debug, Clone, PartialEq, Eq, Default, Serialize, Deserialize)]
+pub struct Reputation {
+    standing: BTreeMap<String, i32>,
+}
+
+impl Reputation {
+    pub fn get(&self, faction: &str) -> i32 {
+        self.standing.iter()
+            .find(|(name, _)| name.eq_ignore_ascii_case(faction))
+            .map_or(0, |(_, &standing)| standing)
+    }
+
+    /// Adds `delta` to the standing with `faction` and returns the new standing.
+    pub fn change(&mut self, faction: &str, delta: i32) -> i32 {
+        let standing = (self.get(faction) + delta).clamp(-MAX_REPUTATION, MAX_REPUTATION);
+        self.standing.retain(|name, _| !name.eq_ignore_ascii_case(faction));
+        self.standing.insert(faction.to_string(), standing);
+        standing
+    }
+}
+
+/// Everything that decides how others react to a creature.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Disposition {
+    pub alignment: Alignment,
+    pub faction: Option<String>,
+    #[serde(default)]
+    pub reputation: Reputation,
+}
+
+impl Disposition {
+    pub fn new(alignment: Alignment, faction: Option<String>) -> Self {
+        Disposition { alignment, faction, reputation: Reputation::default() }
+    }
+
+    /// How this creature feels about `other`, with factions looked up in the installed catalog.
+    pub fn attitude_toward(&self, other: &Disposition) -> Attitude {
+        self.attitude_in(catalog(), other)
+    }
+
+    /// Like [`Disposition::attitude_toward`] with the factions of `catalog`.
+    ///
+    /// Alignments two steps apart leave the attitude alone, closer ones improve it and further ones
+    /// sour it. Factions sway it a lot more, and so does the reputation `other` earned with this
+    /// creature's faction.
+    pub fn attitude_in(&self, catalog: &Catalog, other: &Disposition) -> Attitude {
+        let alignment = 2 - self.alignment.distance(&other.alignment) as i32;
+        let (faction, reputation) = match self.faction.as_deref().and_then(|name| catalog.faction(name)) {
+            Some(faction) => {
+                let relation = match other.faction.as_deref() {
+                    Some(theirs) if faction.is_ally(theirs) => FACTION_WEIGHT,
+                    Some(theirs) if faction.is_enemy(theirs) => -FACTION_WEIGHT,
+                    _ => 0,
+                };
+                (relation, other.reputation.get(&faction.name) / REPUTATION_PER_STEP)
+            }
+            None => (0, 0),
+        };
+        Attitude::from_score(alignment + faction + reputation)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::dnd::alignment::{Ethical, Moral};
+    use super::*;
+
+    fn goblin() -> Disposition {
+        Disposition::new(Alignment::new(Moral::Evil, Ethical::Neutral), Some("Goblins".to_string()))
+    }
+
+    #[test]
+    fn test_alignment() {
+        let catalog = Catalog::builtin();
+        let loner = |moral, ethical| Disposition::new(Alignment::new(moral, ethical), None);
+        let paladin = loner(Moral::Good, Ethical::Lawful);
+        assert_eq!(paladin.attitude_in(&catalog, &paladin), Attitude::Friendly);
+        assert_eq!(paladin.attitude_in(&catalog, &loner(Moral::Neutral, Ethical::Neutral)), Attitude::Indifferent);
+        assert_eq!(paladin.attitude_in(&catalog, &loner(Moral::Evil, Ethical::Neutral)), Attitude::Unfriendly);
+        assert_eq!(paladin.attitude_in(&catalog, &loner(Moral::Evil, Ethical::Chaotic)), Attitude::Unfriendly);
+        assert_eq!(Alignment::new(Moral::Evil, Ethical::Chaotic).to_string(), "Chaotic Evil");
+        assert_eq!(Alignment::new(Moral::Neutral, Ethical::Lawful).to_string(), "Lawful Neutral");
+        assert_eq!(Alignment::new(Moral::Neutral, Ethical::Neutral).to_string(), "True Neutral");
+        assert_eq!("Chaotic Evil".parse(), Ok(Alignment::new(Moral::Evil, Ethical::Chaotic)));
+        assert_eq!("evil chaotic".parse(), Ok(Alignment::new(Moral::Evil, Ethical::Chaotic)));
+        assert_eq!("Neutral Good".parse(), Ok(Alignment::new(Moral::Good, Ethical::Neutral)));
+        assert_eq!("Neutral".parse(), Ok(Alignment::new(Moral::Neutral, Ethical::Neutral)));
+        assert_eq!("true neutral".parse(), Ok(Alignment::new(Moral::Neutral, Ethical::Neutral)));
+        for alignment in [Alignment::new(Moral::Good, Ethical::Lawful), Alignment::new(Moral::Neutral, Ethical::Chaotic)] {
+            assert_eq!(alignment.to_string().parse(), Ok(alignment));
+        }
+        assert!("Chaotic Lawful".parse::<Alignment>().is_err());
+    }
+
+    #[test]
+    fn test_factions() {
+        let catalog = Catalog::builtin();
+        let goblin = goblin();
+        let mut knight = Disposition::new(Alignment::new(Moral::Good, Ethical::Lawful), Some("Kingdom".to_string()));
+        let wanderer = Disposition::new(Alignment::new(Moral::Good, Ethical::Lawful), None);
+
+        assert_eq!(goblin.attitude_in(&catalog, &goblin), Attitude::Friendly);
+        assert_eq!(goblin.attitude_in(&catalog, &knight), Attitude::Hostile);
+        assert_eq!(goblin.attitude_in(&catalog, &wanderer), Attitude::Unfriendly);
+
+        // bribes go a long way
+        assert_eq!(knight.reputation.change("goblins", 60), 60);
+        assert_eq!(goblin.attitude_in(&catalog, &knight), Attitude::Unfriendly);
+        assert_eq!(knight.reputation.change("Goblins", 500), MAX_REPUTATION);
+        assert_eq!(goblin.attitude_in(&catalog, &knight), Attitude::Indifferent);
+
+        let mut outcast = wanderer.clone();
+        outcast.reputation.change("Goblins", -50);
+        assert_eq!(goblin.attitude_in(&catalog, &outcast), Attitude::Hostile);
+        assert_eq!("Hostile".parse(), Ok(Attitude::Hostile));
+    }
+}
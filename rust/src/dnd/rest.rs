@@ -0,0 +1,132 @@
+use std::fmt::{Display, Formatter};
+use rand::Rng;
+use serde::{Deserialize, Serialize};
+use crate::dnd::dice::{Dice, DiceExpr, Roll};
+
+/// The hit dice a character can spend on short rests, one per level.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct HitDice {
+    die: u32,
+    total: u32,
+    remaining: u32,
+}
+
+impl HitDice {
+    pub fn new(die: u32, level: u32) -> Self {
+        HitDice { die, total: level, remaining: level }
+    }
+
+    pub fn die(&self) -> u32 {
+        self.die
+    }
+
+    pub fn total(&self) -> u32 {
+        self.total
+    }
+
+    pub fn remaining(&self) -> u32 {
+        self.remaining
+    }
+
+    /// A new level comes with a fresh hit die.
+    pub fn set_level(&mut self, level: u32) {
+        let gained = level.saturating_sub(self.total);
+        self.total = level;
+        self.remaining = (self.remaining + gained).min(self.total);
+    }
+
+    /// Rolls `count` hit dice, each adding the constitution modifier, and returns the rolls with
+    /// the hit points they restore. A die never restores less than 0.
+    pub fn spend<R: Rng + ?Sized>(&mut self, count: u32, constitution: i32, rng: &mut R) -> Result<(Vec<Roll>, u32), RestError> {
+        if count > self.remaining {
+            return Err(RestError::NotEnoughHitDice { requested: count, remaining: self.remaining })
+        }
+        self.remaining -= count;
+        let expr = DiceExpr::new(Dice::new(1, self.die)).plus(constitution);
+        let rolls: Vec<Roll> = (0..count).map(|_| expr.roll(rng)).collect();
+        let healed = rolls.iter().map(|roll| roll.total.max(0) as u32).sum();
+        Ok((rolls, healed))
+    }
+
+    /// A long rest gives back half of the hit dice, at least one. Returns how many came back.
+    pub fn recover(&mut self) -> u32 {
+        self.restore((self.total / 2).max(1))
+    }
+
+    /// Gives back up to `count` spent hit dice and returns how many came back.
+    pub fn restore(&mut self, count: u32) -> u32 {
+        let restored = count.min(self.total - self.remaining);
+        self.remaining += restored;
+        restored
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ShortRest {
+    pub rolls: Vec<Roll>,
+    pub healed: u32,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct LongRest {
+    pub healed: u32,
+    pub hit_dice_recovered: u32,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum RestError {
+    /// Only a conscious character with at least 1 hit point can rest.
+    Unconscious,
+    Dead,
+    NotEnoughHitDice { requested: u32, remaining: u32 },
+}
+
+impl Display for RestError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            RestError::Unconscious => write!(f, "cannot rest while unconscious"),
+            RestError::Dead => write!(f, "the dead do not rest"),
+            RestError::NotEnoughHitDice { requested, remaining } =>
+                write!(f, "cannot spend {} hit dice, only {} left", requested, remaining),
+        }
+    }
+}
+
+impl std::error::Error for RestError {}
+
+#[cfg(test)]
+mod test {
+    use rand::rngs::StdRng;
+    use rand::SeedableRng;
+    use super::*;
+
+    #[test]
+    fn test_hit_dice() {
+        let mut rng = StdRng::seed_from_u64(7);
+        let mut hit_dice = HitDice::new(10, 5);
+        let (rolls, healed) = hit_dice.spend(2, 2, &mut rng).unwrap();
+        assert_eq!(rolls.len(), 2);
+        assert!((6..=24).contains(&healed));
+        assert_eq!(healed as i32, rolls.iter().map(|roll| roll.total).sum::<i32>());
+        assert_eq!(hit_dice.remaining(), 3);
+        assert_eq!(
+            hit_dice.spend(4, 2, &mut rng),
+            Err(RestError::NotEnoughHitDice { requested: 4, remaining: 3 })
+        );
+
+        // a terrible constitution never drains hit points
+        let (_, healed) = hit_dice.spend(3, -20, &mut rng).unwrap();
+        assert_eq!(healed, 0);
+
+        assert_eq!(hit_dice.recover(), 2);
+        assert_eq!(hit_dice.recover(), 2);
+        assert_eq!(hit_dice.recover(), 1);
+        assert_eq!(hit_dice.remaining(), 5);
+
+        hit_dice.set_level(6);
+        assert_eq!((hit_dice.total(), hit_dice.remaining()), (6, 6));
+        let mut first = HitDice::new(8, 1);
+        first.spend(1, 0, &mut rng).unwrap();
+        assert_eq!(first.recover(), 1);
+    }
+}
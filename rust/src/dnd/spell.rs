@@ -0,0 +1,694 @@
+use std::fmt::{Display, Formatter};
+use godot::prelude::Gd;
+use rand::Rng;
+use serde::{Deserialize, Serialize};
+use crate::dnd::ability::proficiency_bonus;
+use crate::dnd::attack::AttackRoll;
+use crate::dnd::check::{Check, CheckContext, CheckResult};
+use crate::dnd::condition::{Condition, ConditionType};
+use crate::dnd::dice::{DiceExpr, Roll, RollMode};
+use crate::dnd::enums::{AbilityType, ClassType, DamageType};
+use crate::interactable::effect::{Damage, Effect, Effects, Heal};
+
+pub const MAX_SPELL_LEVEL: u8 = 9;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+pub enum SpellSchool {
+    Abjuration,
+    Conjuration,
+    Divination,
+    Enchantment,
+    Evocation,
+    Illusion,
+    Necromancy,
+    Transmutation,
+}
+
+/// Area of effect, sizes in feet.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum Area {
+    Sphere { radius: u32 },
+    Cone { length: u32 },
+    Line { length: u32, width: u32 },
+    Cube { size: u32 },
+}
+
+/// How a spell reaches its target.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum Delivery {
+    /// The spell simply takes effect, e.g. magic missile or cure wounds.
+    Automatic,
+    /// A spell attack roll against the target's armor class.
+    Attack,
+    /// The target makes a saving throw against the caster's spell save DC.
+    Save { ability: AbilityType, half_on_success: bool },
+}
+
+/// A spell as defined in the catalog, see [`crate::dnd::catalog::Catalog::spell`].
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SpellData {
+    pub name: String,
+    /// `0` for cantrips.
+    pub level: u8,
+    pub school: SpellSchool,
+    /// Range in feet, `0` for touch and self.
+    pub range: u32,
+    #[serde(default)]
+    pub area: Option<Area>,
+    pub delivery: Delivery,
+    #[serde(default)]
+    pub damage: Option<DiceExpr>,
+    #[serde(default)]
+    pub damage_type: Option<DamageType>,
+    #[serde(default)]
+    pub healing: Option<DiceExpr>,
+    /// Healing adds the caster's spellcasting modifier.
+    #[serde(default)]
+    pub add_modifier: bool,
+    /// Extra dice for every slot level above the spell's own.
+    #[serde(default)]
+    pub upcast: Option<DiceExpr>,
+    /// Condition inflicted on a hit or a failed save.
+    #[serde(default)]
+    pub condition: Option<ConditionType>,
+    /// Duration in rounds, `0` for instantaneous spells.
+    #[serde(default)]
+    pub duration: u32,
+    #[serde(default)]
+    pub concentration: bool,
+    pub classes: Vec<ClassType>,
+}
+
+impl SpellData {
+    pub fn is_cantrip(&self) -> bool {
+        self.level == 0
+    }
+
+    /// Damage when cast with a slot of `slot_level` by a character of `character_level`. Cantrips
+    /// grow with the character instead of the slot.
+    pub fn damage_at(&self, slot_level: u8, character_level: u32) -> Option<DiceExpr> {
+        let damage = self.damage.as_ref()?;
+        if self.is_cantrip() {
+            return Some(damage.multiplied_dice(cantrip_dice(character_level)))
+        }
+        Some(self.upcast_dice(damage.clone(), slot_level))
+    }
+
+    pub fn healing_at(&self, slot_level: u8) -> Option<DiceExpr> {
+        let healing = self.healing.as_ref()?;
+        Some(self.upcast_dice(healing.clone(), slot_level))
+    }
+
+    fn upcast_dice(&self, dice: DiceExpr, slot_level: u8) -> DiceExpr {
+        let Some(upcast) = &self.upcast else {
+            return dice
+        };
+        (self.level..slot_level).fold(dice, |dice, _| dice.plus_expr(upcast))
+    }
+}
+
+/// Cantrips roll one extra set of dice at levels 5, 11 and 17.
+pub fn cantrip_dice(character_level: u32) -> u32 {
+    match character_level {
+        0..=4 => 1,
+        5..=10 => 2,
+        11..=16 => 3,
+        _ => 4,
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum CasterProgression {
+    Full,
+    /// Paladins and rangers, who get their first slots at level 2.
+    Half,
+    /// Warlock pact magic: few slots, all of the highest level, back after a short rest.
+    Pact,
+}
+
+pub fn caster_progression(class: ClassType) -> Option<CasterProgression> {
+    match class {
+        ClassType::Bard | ClassType::Cleric | ClassType::Druid | ClassType::Sorcerer | ClassType::Wizard =>
+            Some(CasterProgression::Full),
+        ClassType::Paladin | ClassType::Ranger => Some(CasterProgression::Half),
+        ClassType::Warlock => Some(CasterProgression::Pact),
+        _ => None,
+    }
+}
+
+pub fn spellcasting_ability(class: ClassType) -> Option<AbilityType> {
+    match class {
+        ClassType::Wizard => Some(AbilityType::Intelligence),
+        ClassType::Cleric | ClassType::Druid | ClassType::Ranger => Some(AbilityType::Wisdom),
+        ClassType::Bard | ClassType::Paladin | ClassType::Sorcerer | ClassType::Warlock => Some(AbilityType::Charisma),
+        _ => None,
+    }
+}
+
+#[rustfmt::skip]
+const FULL_CASTER_SLOTS: [[u8; MAX_SPELL_LEVEL as usize]; 20] = [
+    [2, 0, 0, 0, 0, 0, 0, 0, 0],
+    [3, 0, 0, 0, 0, 0, 0, 0, 0],
+    [4, 2, 0, 0, 0, 0, 0, 0, 0],
+    [4, 3, 0, 0, 0, 0, 0, 0, 0],
+    [4, 3, 2, 0, 0, 0, 0, 0, 0],
+    [4, 3, 3, 0, 0, 0, 0, 0, 0],
+    [4, 3, 3, 1, 0, 0, 0, 0, 0],
+    [4, 3, 3, 2, 0, 0, 0, 0, 0],
+    [4, 3, 3, 3, 1, 0, 0, 0, 0],
+    [4, 3, 3, 3, 2, 0, 0, 0, 0],
+    [4, 3, 3, 3, 2, 1, 0, 0, 0],
+    [4, 3, 3, 3, 2, 1, 0, 0, 0],
+    [4, 3, 3, 3, 2, 1, 1, 0, 0],
+    [4, 3, 3, 3, 2, 1, 1, 0, 0],
+    [4, 3, 3, 3, 2, 1, 1, 1, 0],
+    [4, 3, 3, 3, 2, 1, 1, 1, 0],
+    [4, 3, 3, 3, 2, 1, 1, 1, 1],
+    [4, 3, 3, 3, 3, 1, 1, 1, 1],
+    [4, 3, 3, 3, 3, 2, 1, 1, 1],
+    [4, 3, 3, 3, 3, 2, 2, 1, 1],
+];
+
+/// Slots per spell level of a full caster of `caster_level`, index 0 being 1st level slots.
+pub fn full_caster_slots(caster_level: u32) -> [u8; MAX_SPELL_LEVEL as usize] {
+    match caster_level {
+        0 => [0; MAX_SPELL_LEVEL as usize],
+        level => FULL_CASTER_SLOTS[(level.min(20) - 1) as usize],
+    }
+}
+
+/// Number of pact slots and their level for a warlock of `level`.
+#[rustfmt::skip]
+pub fn pact_slots(level: u32) -> (u8, u8) {
+    match level {
+        0      => (0, 0),
+        1      => (1, 1),
+        2      => (2, 1),
+        3..=4  => (2, 2),
+        5..=6  => (2, 3),
+        7..=8  => (2, 4),
+        9..=10 => (2, 5),
+        11..=16 => (3, 5),
+        _      => (4, 5),
+    }
+}
+
+/// Slot table of a single-class character.
+pub fn class_spell_slots(class: ClassType, level: u32) -> [u8; MAX_SPELL_LEVEL as usize] {
+    match caster_progression(class) {
+        Some(CasterProgression::Full) => full_caster_slots(level),
+        Some(CasterProgression::Half) if level >= 2 => full_caster_slots(level.div_ceil(2)),
+        Some(CasterProgression::Pact) => {
+            let (count, slot_level) = pact_slots(level);
+            let mut slots = [0; MAX_SPELL_LEVEL as usize];
+            slots[slot_level.max(1) as usize - 1] = count;
+            slots
+        }
+        _ => [0; MAX_SPELL_LEVEL as usize],
+    }
+}
+
+/// Slot table of a character with levels in several classes, pairing each class with the levels
+/// taken in it. Every level of a full caster counts toward the caster level, half of them for
+/// paladins and rangers, rounded down. Warlock pact magic is kept apart, see [`pact_slots`].
+pub fn multiclass_spell_slots(levels: &[(ClassType, u32)]) -> [u8; MAX_SPELL_LEVEL as usize] {
+    let caster_level = levels.iter()
+        .map(|&(class, level)| match caster_progression(class) {
+            Some(CasterProgression::Full) => level,
+            Some(CasterProgression::Half) => level / 2,
+            _ => 0,
+        })
+        .sum();
+    full_caster_slots(caster_level)
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum SlotRecovery {
+    LongRest,
+    ShortRest,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SpellSlots {
+    max: [u8; MAX_SPELL_LEVEL as usize],
+    remaining: [u8; MAX_SPELL_LEVEL as usize],
+    recovery: SlotRecovery,
+}
+
+impl SpellSlots {
+    pub fn new(max: [u8; MAX_SPELL_LEVEL as usize], recovery: SlotRecovery) -> Self {
+        SpellSlots { max, remaining: max, recovery }
+    }
+
+    pub fn for_class(class: ClassType, level: u32) -> Self {
+        let recovery = match caster_progression(class) {
+            Some(CasterProgression::Pact) => SlotRecovery::ShortRest,
+            _ => SlotRecovery::LongRest,
+        };
+        SpellSlots::new(class_spell_slots(class, level), recovery)
+    }
+
+    pub fn max(&self, level: u8) -> u8 {
+        Self::index(level).map_or(0, |i| self.max[i])
+    }
+
+    pub fn remaining(&self, level: u8) -> u8 {
+        Self::index(level).map_or(0, |i| self.remaining[i])
+    }
+
+    pub fn expend(&mut self, level: u8) -> Result<(), SpellError> {
+        let i = Self::index(level).ok_or(SpellError::InvalidSlotLevel(level))?;
+        if self.remaining[i] == 0 {
+            return Err(SpellError::NoSlotsLeft { level })
+        }
+        self.remaining[i] -= 1;
+        Ok(())
+    }
+
+    /// Changes the table, e.g. after a level up. New slots are available right away.
+    pub fn set_max(&mut self, max: [u8; MAX_SPELL_LEVEL as usize]) {
+        for ((remaining, old), new) in self.remaining.iter_mut().zip(self.max).zip(max) {
+            let used = old.saturating_sub(*remaining);
+            *remaining = new.saturating_sub(used);
+        }
+        self.max = max;
+    }
+
+    pub fn short_rest(&mut self) {
+        if self.recovery == SlotRecovery::ShortRest {
+            self.remaining = self.max;
+        }
+    }
+
+    pub fn long_rest(&mut self) {
+        self.remaining = self.max;
+    }
+
+    fn index(level: u8) -> Option<usize> {
+        (1..=MAX_SPELL_LEVEL).contains(&level).then(|| level as usize - 1)
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum SpellError {
+    NotASpellcaster(ClassType),
+    NotOnClassList { spell: String, class: ClassType },
+    SlotTooLow { spell: String, spell_level: u8, slot_level: u8 },
+    InvalidSlotLevel(u8),
+    NoSlotsLeft { level: u8 },
+}
+
+impl Display for SpellError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            SpellError::NotASpellcaster(class) => write!(f, "{} cannot cast spells", class),
+            SpellError::NotOnClassList { spell, class } => write!(f, "{} is not a {} spell", spell, class),
+            SpellError::SlotTooLow { spell, spell_level, slot_level } =>
+                write!(f, "{} is a level {} spell and cannot be cast with a level {} slot", spell, spell_level, slot_level),
+            SpellError::InvalidSlotLevel(level) => write!(f, "there are no level {} spell slots", level),
+            SpellError::NoSlotsLeft { level } => write!(f, "no level {} spell slots left", level),
+        }
+    }
+}
+
+impl std::error::Error for SpellError {}
+
+/// A saving throw the target of a spell has to make. Rolled by the target, since only it knows its
+/// own modifiers.
+#[derive(Debug, Clone, PartialEq)]
+pub struct SavingThrowEffect {
+    pub ability: AbilityType,
+    pub dc: i32,
+    pub damage: Option<Roll>,
+    pub half_on_success: bool,
+    pub condition: Option<Condition>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct SavingThrowOutcome {
+    pub check: CheckResult,
+    pub damage: i32,
+    pub condition: Option<Condition>,
+}
+
+impl SavingThrowEffect {
+    pub fn resolve<R: Rng + ?Sized>(&self, target: &CheckContext, mode: RollMode, rng: &mut R) -> SavingThrowOutcome {
+        let check = target.resolve(Check::saving_throw(self.ability, self.dc).with_mode(mode), rng);
+        let full = self.damage.as_ref().map_or(0, |roll| roll.total.max(0));
+        let damage = match (check.success, self.half_on_success) {
+            (false, _) => full,
+            (true, true) => full / 2,
+            (true, false) => 0,
+        };
+        let condition = if check.success { None } else { self.condition };
+        SavingThrowOutcome { check, damage, condition }
+    }
+}
+
+/// Everything a cast produced: the effects to deliver and what happened to the caster.
+#[derive(Debug, Clone)]
+pub struct Cast {
+    pub spell: String,
+    pub slot_level: u8,
+    pub effects: Vec<Effect>,
+    /// Concentration spell that ended because this one needs concentration as well.
+    pub dropped_concentration: Option<String>,
+}
+
+impl Cast {
+    pub fn to_effects(&self) -> Gd<Effects> {
+        Effects::new(self.effects.clone())
+    }
+}
+
+/// A character's spellcasting: its slots and the spell it is concentrating on.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Spellcaster {
+    pub class: ClassType,
+    pub ability: AbilityType,
+    pub slots: SpellSlots,
+    /// The other classes a multiclass character casts spells from, with their spellcasting ability.
+    #[serde(default)]
+    pub other_classes: Vec<(ClassType, AbilityType)>,
+    /// Pact slots of a warlock with levels in another spellcasting class. They come back after a
+    /// short rest, so they are kept apart from `slots`.
+    #[serde(default)]
+    pub pact: Option<SpellSlots>,
+    concentration: Option<String>,
+}
+
+impl Spellcaster {
+    pub fn new(class: ClassType, level: u32) -> Option<Self> {
+        Some(Spellcaster {
+            class,
+            ability: spellcasting_ability(class)?,
+            slots: SpellSlots::for_class(class, level),
+            other_classes: Vec::new(),
+            pact: None,
+            concentration: None,
+        })
+    }
+
+    /// Spellcasting of a character with levels in several classes, `None` if none of them casts
+    /// spells. A single spellcasting class keeps its own slot table.
+    pub fn multiclass(levels: &[(ClassType, u32)]) -> Option<Self> {
+        let casters: Vec<_> = levels.iter().copied()
+            .filter(|&(class, _)| spellcasting_ability(class).is_some())
+            .collect();
+        let (&(class, level), others) = casters.split_first()?;
+        let mut spellcaster = Spellcaster::new(class, level)?;
+        if others.is_empty() {
+            return Some(spellcaster)
+        }
+        spellcaster.other_classes = others.iter()
+            .filter_map(|&(class, _)| Some((class, spellcasting_ability(class)?)))
+            .collect();
+        spellcaster.slots = SpellSlots::new(multiclass_spell_slots(&casters), SlotRecovery::LongRest);
+        spellcaster.pact = casters.iter()
+            .find(|&&(class, _)| class == ClassType::Warlock)
+            .map(|&(_, level)| SpellSlots::for_class(ClassType::Warlock, level));
+        Some(spellcaster)
+    }
+
+    pub fn set_level(&mut self, level: u32) {
+        self.slots.set_max(class_spell_slots(self.class, level));
+    }
+
+    /// Follows the class levels of a multiclass character, see [`Spellcaster::multiclass`]. Slots
+    /// already spent stay spent.
+    pub fn set_levels(&mut self, levels: &[(ClassType, u32)]) {
+        let Some(fresh) = Spellcaster::multiclass(levels) else {
+            return
+        };
+        if fresh.slots.recovery == self.slots.recovery {
+            self.slots.set_max(fresh.slots.max);
+        } else {
+            self.slots = fresh.slots;
+        }
+        self.pact = match (self.pact.take(), fresh.pact) {
+            (Some(mut pact), Some(fresh)) => {
+                pact.set_max(fresh.max);
+                Some(pact)
+            }
+            (_, fresh) => fresh,
+        };
+        self.other_classes = fresh.other_classes;
+    }
+
+    pub fn short_rest(&mut self) {
+        self.slots.short_rest();
+        if let Some(pact) = self.pact.as_mut() {
+            pact.short_rest();
+        }
+    }
+
+    pub fn long_rest(&mut self) {
+        self.slots.long_rest();
+        if let Some(pact) = self.pact.as_mut() {
+            pact.long_rest();
+        }
+    }
+
+    /// The first class of the caster with `spell` on its list, and its spellcasting ability.
+    fn class_for(&self, spell: &SpellData) -> Option<(ClassType, AbilityType)> {
+        std::iter::once((self.class, self.ability))
+            .chain(self.other_classes.iter().copied())
+            .find(|(class, _)| spell.classes.contains(class))
+    }
+
+    /// Spends a slot of `level`, pact slots first since they are back sooner.
+    fn expend(&mut self, level: u8) -> Result<(), SpellError> {
+        match self.pact.as_mut() {
+            Some(pact) if pact.remaining(level) > 0 => pact.expend(level),
+            _ => self.slots.expend(level),
+        }
+    }
+
+    pub fn concentration(&self) -> Option<&str> {
+        self.concentration.as_deref()
+    }
+
+    pub fn end_concentration(&mut self) -> Option<String> {
+        self.concentration.take()
+    }
+
+    pub fn spell_save_dc(&self, caster: &CheckContext) -> i32 {
+        8 + proficiency_bonus(caster.level) + caster.ability.modifier(self.ability)
+    }
+
+    pub fn spell_attack_bonus(&self, caster: &CheckContext) -> i32 {
+        proficiency_bonus(caster.level) + caster.ability.modifier(self.ability)
+    }
+
+    /// Casts `spell` using a slot of `slot_level`, which is ignored for cantrips.
+    pub fn cast<R: Rng + ?Sized>(&mut self, caster: &CheckContext, spell: &SpellData, slot_level: u8, rng: &mut R) -> Result<Cast, SpellError> {
+        let Some((_, ability)) = self.class_for(spell) else {
+            return Err(SpellError::NotOnClassList { spell: spell.name.clone(), class: self.class })
+        };
+        let slot_level = if spell.is_cantrip() { 0 } else { slot_level };
+        if !spell.is_cantrip() {
+            if slot_level < spell.level {
+                return Err(SpellError::SlotTooLow { spell: spell.name.clone(), spell_level: spell.level, slot_level })
+            }
+            self.expend(slot_level)?;
+        }
+
+        let dropped_concentration = if spell.concentration {
+            self.concentration.replace(spell.name.clone())
+        } else {
+            None
+        };
+        Ok(Cast {
+            spell: spell.name.clone(),
+            slot_level,
+            effects: Spellcaster::effects(caster, ability, spell, slot_level, rng),
+            dropped_concentration,
+        })
+    }
+
+    /// What casting `spell` does, using the spellcasting `ability` of the class it was cast from.
+    fn effects<R: Rng + ?Sized>(caster: &CheckContext, ability: AbilityType, spell: &SpellData, slot_level: u8, rng: &mut R) -> Vec<Effect> {
+        let modifier = caster.ability.modifier(ability);
+        let damage = spell.damage_at(slot_level, caster.level);
+        let condition = spell.condition.map(|kind| match spell.duration {
+            0 => Condition::for_rounds(kind, 1),
+            rounds => Condition::for_rounds(kind, rounds),
+        });
+
+        let mut effects = Vec::new();
+        if let Some(healing) = spell.healing_at(slot_level) {
+            let healing = if spell.add_modifier { healing.plus(modifier) } else { healing };
+            effects.push(Effect::Heal(Heal { amount: healing.roll(rng).total.max(0) }));
+        }
+        match spell.delivery {
+            Delivery::Automatic => {
+                if let Some(damage) = damage {
+                    effects.push(Effect::Damage(Damage { amount: damage.roll(rng).total.max(0) }));
+                }
+                effects.extend(condition.map(Effect::Condition));
+            }
+            Delivery::Attack => {
+                let Some(damage) = damage else {
+                    return effects
+                };
+                let roll = DiceExpr::d20(RollMode::Normal).roll(rng);
+                let proficiency_bonus = proficiency_bonus(caster.level);
+                effects.push(Effect::Attack(AttackRoll {
+                    total: roll.total + modifier + proficiency_bonus,
+                    roll,
+                    ability_modifier: modifier,
+                    proficiency_bonus,
+                    damage_roll: damage.roll(rng),
+                    critical_damage_roll: damage.doubled_dice().roll(rng),
+                    condition,
+                }));
+            }
+            Delivery::Save { ability, half_on_success } => {
+                effects.push(Effect::SavingThrow(SavingThrowEffect {
+                    ability,
+                    dc: 8 + proficiency_bonus(caster.level) + modifier,
+                    damage: damage.map(|damage| damage.roll(rng)),
+                    half_on_success,
+                    condition,
+                }));
+            }
+        }
+        effects
+    }
+
+    /// Taking damage while concentrating calls for a Constitution save against the higher of 10
+    /// and half the damage. Concentration ends when it fails.
+    pub fn concentration_check<R: Rng + ?Sized>(&mut self, caster: &CheckContext, damage: u32, rng: &mut R) -> Option<CheckResult> {
+        self.concentration.as_ref()?;
+        let dc = (damage as i32 / 2).max(10);
+        let result = caster.resolve(Check::saving_throw(AbilityType::Constitution, dc), rng);
+        if !result.success {
+            self.concentration = None;
+        }
+        Some(result)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use rand::rngs::StdRng;
+    use rand::SeedableRng;
+    use crate::dnd::ability::Ability;
+    use crate::dnd::catalog::Catalog;
+    use crate::dnd::enums::Proficiencies;
+    use super::*;
+
+    #[test]
+    fn test_slot_tables() {
+        assert_eq!(class_spell_slots(ClassType::Wizard, 5), [4, 3, 2, 0, 0, 0, 0, 0, 0]);
+        assert_eq!(class_spell_slots(ClassType::Paladin, 1), [0; 9]);
+        assert_eq!(class_spell_slots(ClassType::Paladin, 5), [4, 2, 0, 0, 0, 0, 0, 0, 0]);
+        assert_eq!(class_spell_slots(ClassType::Warlock, 5), [0, 0, 2, 0, 0, 0, 0, 0, 0]);
+        assert_eq!(class_spell_slots(ClassType::Fighter, 20), [0; 9]);
+        assert!(Spellcaster::new(ClassType::Barbarian, 5).is_none());
+
+        let mut slots = SpellSlots::for_class(ClassType::Warlock, 1);
+        assert_eq!(slots.expend(1), Ok(()));
+        assert_eq!(slots.expend(1), Err(SpellError::NoSlotsLeft { level: 1 }));
+        slots.short_rest();
+        assert_eq!(slots.remaining(1), 1);
+        slots.expend(1).unwrap();
+        slots.set_max(class_spell_slots(ClassType::Warlock, 2));
+        assert_eq!((slots.max(1), slots.remaining(1)), (2, 1));
+    }
+
+    #[test]
+    fn test_cast() {
+        let catalog = Catalog::builtin();
+        let ability = Ability { intelligence: 16, constitution: 10, ..Default::default() };
+        let proficiencies = Proficiencies::empty();
+        let wizard = CheckContext::new(&ability, &proficiencies, 5);
+        let mut caster = Spellcaster::new(ClassType::Wizard, 5).unwrap();
+        let mut rng = StdRng::seed_from_u64(5);
+        assert_eq!(caster.spell_save_dc(&wizard), 14);
+
+        let fireball = catalog.spell("Fireball").unwrap();
+        assert_eq!(fireball.damage_at(4, 7).unwrap().to_string(), "8d6+1d6");
+        let cast = caster.cast(&wizard, fireball, 3, &mut rng).unwrap();
+        assert_eq!(caster.slots.remaining(3), 1);
+        match &cast.effects[..] {
+            [Effect::SavingThrow(save)] => {
+                assert_eq!((save.ability, save.dc, save.half_on_success), (AbilityType::Dexterity, 14, true));
+                assert!((8..=48).contains(&save.damage.as_ref().unwrap().total));
+            }
+            effects => panic!("unexpected effects {:?}", effects),
+        }
+        assert_eq!(
+            caster.cast(&wizard, fireball, 2, &mut rng).unwrap_err(),
+            SpellError::SlotTooLow { spell: "Fireball".into(), spell_level: 3, slot_level: 2 }
+        );
+
+        let fire_bolt = catalog.spell("Fire Bolt").unwrap();
+        assert_eq!(fire_bolt.damage_at(0, 5).unwrap().to_string(), "2d10");
+        let cast = caster.cast(&wizard, fire_bolt, 0, &mut rng).unwrap();
+        assert!(matches!(cast.effects[..], [Effect::Attack(ref attack)] if attack.total == attack.roll.total + 6));
+        // the condition of an attack spell comes with a hit only
+        let chilling = SpellData { condition: Some(ConditionType::Slowed), duration: 2, ..fire_bolt.clone() };
+        let cast = caster.cast(&wizard, &chilling, 0, &mut rng).unwrap();
+        let [Effect::Attack(ref attack)] = cast.effects[..] else { panic!("unexpected effects {:?}", cast.effects) };
+        assert_eq!(attack.condition, Some(Condition::for_rounds(ConditionType::Slowed, 2)));
+        assert_eq!(attack.against(0).condition().is_some(), attack.against(0).is_hit());
+        assert_eq!(attack.against(u8::MAX).condition().is_some(), attack.against(u8::MAX).is_hit());
+
+        let hold_person = catalog.spell("Hold Person").unwrap();
+        caster.cast(&wizard, hold_person, 2, &mut rng).unwrap();
+        assert_eq!(caster.concentration(), Some("Hold Person"));
+        // DC 10 with +0 fails on anything below 10
+        let mut broke = false;
+        for _ in 0..50 {
+            caster.concentration = Some("Hold Person".into());
+            let result = caster.concentration_check(&wizard, 4, &mut rng).unwrap();
+            assert_eq!(result.check.dc, 10);
+            assert_eq!(caster.concentration().is_none(), !result.success);
+            broke |= !result.success;
+        }
+        assert!(broke);
+    }
+
+    #[test]
+    fn test_multiclass() {
+        // wizard 3 and cleric 2 cast like a 5th level wizard, paladin 3 adds a single level
+        assert_eq!(multiclass_spell_slots(&[(ClassType::Wizard, 3), (ClassType::Cleric, 2)]), full_caster_slots(5));
+        assert_eq!(multiclass_spell_slots(&[(ClassType::Paladin, 3), (ClassType::Fighter, 5)]), full_caster_slots(1));
+        assert_eq!(multiclass_spell_slots(&[(ClassType::Warlock, 5), (ClassType::Rogue, 2)]), [0; 9]);
+
+        // only one class casts spells, so it keeps its own table
+        let paladin = Spellcaster::multiclass(&[(ClassType::Fighter, 2), (ClassType::Paladin, 5)]).unwrap();
+        assert_eq!(paladin.slots.max(2), 2);
+        assert!(paladin.other_classes.is_empty());
+        assert!(Spellcaster::multiclass(&[(ClassType::Fighter, 2), (ClassType::Rogue, 5)]).is_none());
+
+        let catalog = Catalog::builtin();
+        let ability = Ability { intelligence: 16, charisma: 10, ..Default::default() };
+        let proficiencies = Proficiencies::empty();
+        let character = CheckContext::new(&ability, &proficiencies, 5);
+        let mut caster = Spellcaster::multiclass(&[(ClassType::Warlock, 2), (ClassType::Wizard, 3)]).unwrap();
+        let mut rng = StdRng::seed_from_u64(3);
+        assert_eq!((caster.class, caster.other_classes.clone()), (ClassType::Warlock, vec![(ClassType::Wizard, AbilityType::Intelligence)]));
+        assert_eq!((caster.slots.max(1), caster.slots.max(2)), (4, 2));
+        assert_eq!(caster.pact.as_ref().unwrap().max(1), 2);
+
+        // a wizard spell goes off with intelligence, paid with a pact slot first
+        let burning_hands = catalog.spell("Burning Hands").unwrap();
+        let cast = caster.cast(&character, burning_hands, 1, &mut rng).unwrap();
+        assert!(matches!(cast.effects[..], [Effect::SavingThrow(ref save)] if save.dc == 14));
+        assert_eq!((caster.pact.as_ref().unwrap().remaining(1), caster.slots.remaining(1)), (1, 4));
+        caster.cast(&character, burning_hands, 1, &mut rng).unwrap();
+        caster.cast(&character, burning_hands, 1, &mut rng).unwrap();
+        assert_eq!((caster.pact.as_ref().unwrap().remaining(1), caster.slots.remaining(1)), (0, 3));
+        caster.short_rest();
+        assert_eq!((caster.pact.as_ref().unwrap().remaining(1), caster.slots.remaining(1)), (2, 3));
+
+        let cure_wounds = catalog.spell("Cure Wounds").unwrap();
+        assert!(matches!(caster.cast(&character, cure_wounds, 1, &mut rng), Err(SpellError::NotOnClassList { .. })));
+
+        caster.set_levels(&[(ClassType::Warlock, 3), (ClassType::Wizard, 3)]);
+        assert_eq!(caster.pact.as_ref().unwrap().max(2), 2);
+        assert_eq!(caster.slots.remaining(1), 3);
+    }
+}
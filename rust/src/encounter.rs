@@ -0,0 +1,209 @@
+use std::collections::VecDeque;
+use godot::engine::{INode2D, Node2D};
+use godot::prelude::*;
+
+use crate::characters::common::Action;
+use crate::characters::goblin::Goblin;
+use crate::characters::warrior::Warrior;
+use crate::dnd::encounter::{CombatantId, Encounter, EncounterEvent};
+use crate::interactable::effect::{Damage, Effect, Effects};
+
+const PARTY: u32 = 0;
+const MONSTERS: u32 = 1;
+
+/// Plays a turn-based encounter between its `Warrior` and `Goblin` children. The fight itself is
+/// decided by [`Encounter`], this node only moves and animates the children to show the result.
+/// Damage reaches the children through their `hurt`, so they die and reward their killers just
+/// like in real time. They leave with the conditions they ended the encounter with.
+#[derive(GodotClass)]
+#[class(base=Node2D)]
+pub struct TurnBasedEncounter {
+    #[export]
+    pixels_per_foot: real,
+    /// Seconds every move or attack is shown for.
+    #[export]
+    step_seconds: f64,
+    encounter: Option<Encounter>,
+    /// The child node of every combatant, by combatant id.
+    nodes: Vec<Gd<Node2D>>,
+    /// Hit points of every combatant as far as its node knows, by combatant id.
+    health: Vec<u32>,
+    pending: VecDeque<EncounterEvent>,
+    until_next_step: f64,
+    base: Base<Node2D>,
+}
+
+#[godot_api]
+impl TurnBasedEncounter {
+    #[signal]
+    fn turn_started(combatant: Gd<Node2D>);
+
+    /// `winner` is 0 for the party, 1 for the monsters and -1 when nobody is left.
+    #[signal]
+    fn encounter_ended(winner: i64);
+
+    /// Rolls initiative and hands the children over to the encounter until it is over.
+    #[func]
+    fn start(&mut self) {
+        let mut combatants = Vec::new();
+        let mut nodes = Vec::new();
+        for child in self.base().get_children().iter_shared() {
+            let warrior = child.is_class("Warrior".into());
+            if !warrior && !child.is_class("Goblin".into()) {
+                continue
+            }
+            let mut node = child.cast::<Node2D>();
+            let position = node.get_position() / self.pixels_per_foot;
+            let combatant = if warrior {
+                node.clone().cast::<Warrior>().bind().combatant(PARTY, position)
+            } else {
+                node.clone().cast::<Goblin>().bind().combatant(MONSTERS, position)
+            };
+            // real-time movement and input would fight with the turns
+            node.set_process(false);
+            node.set_physics_process(false);
+            combatants.push(combatant);
+            nodes.push(node);
+        }
+
+        self.health = combatants.iter().map(|combatant| combatant.health).collect();
+        let mut encounter = Encounter::new(combatants);
+        let initiative = encounter.roll_initiative(&mut rand::thread_rng());
+        self.pending = initiative.into();
+        self.encounter = Some(encounter);
+        self.nodes = nodes;
+        self.until_next_step = 0.0;
+    }
+
+    #[func]
+    fn is_running(&self) -> bool {
+        self.encounter.is_some()
+    }
+
+    fn finish(&mut self, winner: Option<u32>) {
+        // whatever was not shown yet still hurts
+        for id in 0..self.nodes.len() {
+            let health = self.encounter.as_ref()
+                .and_then(|encounter| encounter.combatant(id))
+                .map_or(self.health[id], |combatant| combatant.health);
+            let damage = self.health[id].saturating_sub(health);
+            self.hurt(id, damage, None);
+        }
+        // conditions gained or worn off during the encounter carry over too
+        if let Some(encounter) = self.encounter.take() {
+            for (node, combatant) in self.nodes.iter().zip(encounter.combatants()) {
+                let conditions = combatant.conditions.clone();
+                if node.is_class("Warrior".into()) {
+                    node.clone().cast::<Warrior>().bind_mut().set_conditions(conditions);
+                } else {
+                    node.clone().cast::<Goblin>().bind_mut().set_conditions(conditions);
+                }
+            }
+        }
+        for node in self.nodes.iter_mut() {
+            node.set_process(true);
+            node.set_physics_process(true);
+        }
+        let winner = winner.map_or(-1, |team| team as i64);
+        self.base_mut().emit_signal("encounter_ended".into(), &[winner.to_variant()]);
+    }
+
+    /// Passes `damage` on to the node of `target`, at most the hit points it has left.
+    fn hurt(&mut self, target: CombatantId, damage: u32, source: Option<CombatantId>) {
+        let damage = damage.min(self.health[target]);
+        if damage == 0 {
+            return
+        }
+        self.health[target] -= damage;
+        let mut effects = Effects::new(vec![Effect::Damage(Damage { amount: damage as i32 })]);
+        effects.bind_mut().source = source.map(|source| self.nodes[source].clone().upcast());
+        self.nodes[target].call("hurt".into(), &[effects.to_variant()]);
+    }
+
+    /// Shows `event`, returns whether there was anything to see.
+    fn play(&mut self, event: EncounterEvent) -> bool {
+        tracing::debug!("encounter: {}", event);
+        match event {
+            EncounterEvent::TurnStarted(id) => {
+                let node = self.nodes[id].clone();
+                self.base_mut().emit_signal("turn_started".into(), &[node.to_variant()]);
+                false
+            }
+            EncounterEvent::Moved { id, to, .. } => {
+                let node = &mut self.nodes[id];
+                node.set("action".into(), Action::Walk.to_variant());
+                node.set_position(to * self.pixels_per_foot);
+                true
+            }
+            EncounterEvent::Attacked { id, target, result, .. } => {
+                self.nodes[id].set("action".into(), Action::Attack.to_variant());
+                self.hurt(target, result.damage.max(0) as u32, Some(id));
+                true
+            }
+            // the killing blow already went through `hurt`
+            EncounterEvent::Defeated(_) => true,
+            EncounterEvent::TurnEnded(id) => {
+                let alive = self.encounter.as_ref()
+                    .and_then(|encounter| encounter.combatant(id))
+                    .is_some_and(|combatant| combatant.is_alive());
+                if alive {
+                    self.nodes[id].set("action".into(), Action::Idle.to_variant());
+                }
+                false
+            }
+            EncounterEvent::Ended { winner } => {
+                self.finish(winner);
+                false
+            }
+            _ => false,
+        }
+    }
+}
+
+#[godot_api]
+impl INode2D for TurnBasedEncounter {
+    fn init(base: Base<Node2D>) -> Self {
+        TurnBasedEncounter {
+            pixels_per_foot: 8.0,
+            step_seconds: 0.5,
+            encounter: None,
+            nodes: Vec::new(),
+            health: Vec::new(),
+            pending: VecDeque::new(),
+            until_next_step: 0.0,
+            base,
+        }
+    }
+
+    fn process(&mut self, delta: f64) {
+        if self.encounter.is_none() {
+            return
+        }
+        self.until_next_step -= delta;
+        if self.until_next_step > 0.0 {
+            return
+        }
+        self.until_next_step = self.step_seconds;
+        // skip ahead to the next thing worth showing
+        while self.encounter.is_some() {
+            if self.pending.is_empty() {
+                let Some(encounter) = self.encounter.as_mut() else {
+                    return
+                };
+                let events = encounter.take_turn(&mut rand::thread_rng());
+                if events.is_empty() {
+                    let winner = encounter.winner();
+                    self.finish(winner);
+                    return
+                }
+                self.pending.extend(events);
+            }
+            let Some(event) = self.pending.pop_front() else {
+                return
+            };
+            if self.play(event) {
+                return
+            }
+        }
+    }
+}
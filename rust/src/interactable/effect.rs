@@ -1,19 +1,30 @@
 use godot::obj::NewAlloc;
-use godot::prelude::{Base, Gd, godot_api, GodotClass, IObject, Object};
+use godot::prelude::{Base, Gd, godot_api, GodotClass, IObject, Node, Object};
+use crate::dnd::attack::AttackRoll;
+use crate::dnd::condition::Condition;
+use crate::dnd::spell::SavingThrowEffect;
 
 #[derive(GodotClass, Debug)]
 #[class(base=Object)]
 pub struct Effects {
     pub effects: Vec<Effect>,
+    pub source: Option<Gd<Node>>,
     base: Base<Object>,
 }
 
 #[derive(Clone, Debug)]
 pub enum Effect {
     Damage(Damage),
+    /// An attack roll that only turns into damage once the target's armor class is known.
+    Attack(AttackRoll),
+    Condition(Condition),
+    /// A saving throw the target rolls itself, e.g. against a spell.
+    SavingThrow(SavingThrowEffect),
     Heal(Heal),
     Buff(Buff),
     DeBuff(DeBuff),
+    /// Makes the target go after the source for a while, see [`crate::ai::threat::ThreatTable`].
+    Taunt(Taunt),
 }
 
 #[derive(Clone, Debug, PartialEq, Eq)]
@@ -36,6 +47,11 @@ pub struct DeBuff {
     pub duration: i32,
 }
 
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Taunt {
+    pub duration: i32,
+}
+
 #[godot_api]
 impl Effects {
     pub fn new(effects: Vec<Effect>) -> Gd<Self> {
@@ -50,6 +66,7 @@ impl IObject for Effects {
     fn init(base: Base<Object>) -> Self {
         Effects {
             effects: Vec::new(),
+            source: None,
             base,
         }
     }
@@ -19,6 +19,7 @@ impl HitBox {
 
     pub fn set_effects(&mut self, effects: Gd<Effects>) {
         self.effects = effects;
+        self.effects.bind_mut().source = self.base().get_owner();
     }
 
     #[func]
@@ -37,6 +38,7 @@ impl IArea2D for HitBox {
     }
 
     fn ready(&mut self) {
+        self.effects.bind_mut().source = self.base().get_owner();
         let listener = self.base().callable("on_area_entered");
         self.base_mut().connect("area_entered".into(), listener);
     }
@@ -5,3 +5,4 @@ pub mod hurt_box;
 pub mod effect;
 pub mod sight;
 pub mod navigator;
+pub mod skill_caster;
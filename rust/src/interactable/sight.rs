@@ -3,6 +3,8 @@ use godot::engine::{Area2D, CollisionShape2D, IArea2D, Timer};
 use godot::prelude::*;
 use tokio::runtime::Runtime;
 use tokio::sync::mpsc::error::TryRecvError;
+use crate::dnd::catalog::catalog;
+use crate::dnd::reaction::{Attitude, Disposition};
 use crate::runtime::RT;
 
 #[derive(GodotClass)]
@@ -23,6 +25,74 @@ impl SightArea2D {
     pub fn get_overlapping_areas(&self) -> &[Gd<Area2D>] {
         &self.overlapping_areas
     }
+
+    /// Owners of the areas in sight that `observer` is hostile toward. Owners that do not tell
+    /// their disposition are ignored.
+    pub fn hostile_owners(&self, observer: &Disposition) -> Vec<Gd<Node>> {
+        self.owners_where(observer, |attitude| attitude == Attitude::Hostile)
+    }
+
+    /// Like [`SightArea2D::hostile_owners`] for those `observer` is friendly toward, never the
+    /// owner of the sight area itself.
+    pub fn friendly_owners(&self, observer: &Disposition) -> Vec<Gd<Node>> {
+        self.owners_where(observer, |attitude| attitude == Attitude::Friendly)
+    }
+
+    /// Owners of the areas in sight that are of `class`, e.g. trees.
+    pub fn owners_of_class(&self, class: &str) -> Vec<Gd<Node>> {
+        let mut owners: Vec<Gd<Node>> = Vec::new();
+        for owner in self.base().get_overlapping_areas().iter_shared().filter_map(|area| area.get_owner()) {
+            if owner.is_class(class.into()) && !owners.contains(&owner) {
+                owners.push(owner);
+            }
+        }
+        owners
+    }
+
+    fn owners_where(&self, observer: &Disposition, wanted: impl Fn(Attitude) -> bool) -> Vec<Gd<Node>> {
+        let me = self.base().get_owner();
+        let mut owners: Vec<Gd<Node>> = Vec::new();
+        for area in self.base().get_overlapping_areas().iter_shared() {
+            let Some(owner) = area.get_owner() else {
+                continue
+            };
+            if owners.contains(&owner) || me.as_ref() == Some(&owner) {
+                continue
+            }
+            let attitude = disposition_of(&owner).map(|other| observer.attitude_toward(&other));
+            if attitude.is_some_and(&wanted) {
+                owners.push(owner);
+            }
+        }
+        owners
+    }
+}
+
+/// Reads the disposition of a node through its `get_alignment`, `get_faction` and
+/// `get_reputation` methods, so script characters can take part as well.
+pub fn disposition_of(node: &Gd<Node>) -> Option<Disposition> {
+    let mut node = node.clone();
+    if !node.has_method("get_alignment".into()) {
+        return None
+    }
+    let alignment = node.call("get_alignment".into(), &[]).try_to::<GString>().ok()?;
+    let alignment = alignment.to_string().parse().ok()?;
+    let faction = if node.has_method("get_faction".into()) {
+        node.call("get_faction".into(), &[]).try_to::<GString>().ok()
+            .map(|faction| faction.to_string())
+            .filter(|faction| !faction.is_empty())
+    } else {
+        None
+    };
+    let mut disposition = Disposition::new(alignment, faction);
+    if node.has_method("get_reputation".into()) {
+        for faction in catalog().factions() {
+            let standing = node.call("get_reputation".into(), &[faction.name.to_variant()])
+                .try_to::<i32>().unwrap_or_default();
+            disposition.reputation.change(&faction.name, standing);
+        }
+    }
+    Some(disposition)
 }
 
 #[godot_api]
@@ -0,0 +1,187 @@
+use godot::engine::{INode2D, PackedScene};
+use godot::prelude::*;
+use crate::ai::command::AttackTarget;
+use crate::ai::skill::{SkillBook, SkillCast, SkillError};
+use crate::dnd::catalog::catalog;
+use crate::dnd::condition::Conditions;
+use crate::dnd::reaction::Attitude;
+use crate::interactable::effect::{Effect, Effects};
+use crate::interactable::sight::disposition_of;
+
+/// Casts its owner's skills. Skills are looked up in the catalog by name; anything next to the
+/// owner with a `hurt` method can be hit, enemies by harmful skills and friends by healing ones.
+/// Healing is announced to the enemies next to the owner with a `notice_healing` method.
+#[derive(GodotClass)]
+#[class(base=Node2D)]
+pub struct SkillCaster {
+    #[export]
+    skills: PackedStringArray,
+    book: SkillBook,
+    /// The cast in progress and the seconds left until it goes off.
+    casting: Option<(SkillCast<Gd<Node2D>>, f64)>,
+
+    base: Base<Node2D>,
+}
+
+#[godot_api]
+impl SkillCaster {
+    #[signal]
+    fn cast_started(skill: GString, cast_time: f64);
+
+    #[signal]
+    fn cast_finished(skill: GString);
+
+    #[signal]
+    fn cast_failed(skill: GString, reason: GString);
+
+    /// Casts for scripts, which keep track of their own conditions.
+    #[func]
+    fn cast_at(&mut self, skill: GString, position: Vector2) -> bool {
+        self.cast(&skill.to_string(), AttackTarget::Point(position), &Conditions::default()).is_ok()
+    }
+
+    #[func]
+    fn is_casting(&self) -> bool {
+        self.casting.is_some()
+    }
+
+    /// Seconds until `skill` is ready again, `-1` for skills the owner does not know.
+    #[func]
+    fn get_cooldown(&self, skill: GString) -> f64 {
+        self.book.skill(&skill.to_string()).map_or(-1.0, |s| s.remaining_cooldown())
+    }
+
+    /// The skill learned `slot`th, for hotkeys.
+    pub fn skill_name(&self, slot: usize) -> Option<String> {
+        self.book.skills().get(slot).map(|skill| skill.name.clone())
+    }
+
+    /// Starts casting `name`, unless the owner's `conditions` keep it from using skills.
+    pub fn cast(&mut self, name: &str, target: AttackTarget, conditions: &Conditions) -> Result<(), SkillError> {
+        let result = if !conditions.can_use_skills() {
+            Err(SkillError::Silenced)
+        } else if self.casting.is_some() {
+            Err(SkillError::Casting)
+        } else {
+            let origin = self.base().get_global_position();
+            let candidates = match self.book.skill(name) {
+                Some(skill) => self.candidates(|attitude| skill.reaches(attitude)),
+                None => Vec::new(),
+            };
+            self.book.cast(name, origin, &target, &candidates, &mut rand::thread_rng())
+        };
+        match result {
+            Ok(cast) => {
+                let args = [cast.skill.to_variant(), cast.cast_time.to_variant()];
+                let cast_time = cast.cast_time;
+                self.casting = Some((cast, cast_time));
+                self.base_mut().emit_signal("cast_started".into(), &args);
+                Ok(())
+            }
+            Err(err) => {
+                tracing::debug!("cannot cast {}: {}", name, err);
+                let args = [GString::from(name).to_variant(), err.to_string().to_variant()];
+                self.base_mut().emit_signal("cast_failed".into(), &args);
+                Err(err)
+            }
+        }
+    }
+
+    /// Everything next to the owner that can be hurt and that the owner has a `wanted` attitude
+    /// toward. Those that do not tell their disposition are left alone.
+    fn candidates(&self, wanted: impl Fn(Attitude) -> bool) -> Vec<Gd<Node2D>> {
+        let Some(owner) = self.base().get_owner() else {
+            return Vec::new()
+        };
+        let (Some(parent), Some(observer)) = (owner.get_parent(), disposition_of(&owner)) else {
+            return Vec::new()
+        };
+        parent.get_children().iter_shared()
+            .filter(|child| *child != owner && child.has_method("hurt".into()) && child.is_class("Node2D".into()))
+            .filter(|child| disposition_of(child).is_some_and(|other| wanted(observer.attitude_toward(&other))))
+            .map(|child| child.cast::<Node2D>())
+            .collect()
+    }
+
+    fn finish(&mut self, cast: SkillCast<Gd<Node2D>>) {
+        let owner = self.base().get_owner();
+        let healing = |effects: &[Effect]| -> i32 {
+            effects.iter().map(|eff| if let Effect::Heal(heal) = eff { heal.amount } else { 0 }).sum()
+        };
+        let healed = healing(&cast.on_targets) * cast.targets.len() as i32 + healing(&cast.on_caster);
+        if healed > 0 {
+            if let Some(healer) = owner.clone().filter(|o| o.is_class("Node2D".into())) {
+                let enemies = self.candidates(|attitude| attitude == Attitude::Hostile);
+                for mut witness in enemies.into_iter().filter(|c| c.has_method("notice_healing".into())) {
+                    witness.call("notice_healing".into(), &[healer.to_variant(), healed.to_variant()]);
+                }
+            }
+        }
+        for mut target in cast.targets.into_iter().filter(|t| t.is_instance_valid()) {
+            if !cast.on_targets.is_empty() {
+                let mut effects = Effects::new(cast.on_targets.clone());
+                effects.bind_mut().source = owner.clone();
+                target.call("hurt".into(), &[effects.to_variant()]);
+            }
+            let away = target.get_global_position() - cast.origin;
+            if cast.knockback > 0.0 && away.length() > 0.0 {
+                let position = target.get_global_position() + away.normalized() * cast.knockback;
+                target.set_global_position(position);
+            }
+        }
+        if let Some(mut owner) = owner.filter(|o| o.is_class("Node2D".into())).map(|o| o.cast::<Node2D>()) {
+            // no source, so the owner does not shrug off its own skill
+            if !cast.on_caster.is_empty() && owner.has_method("hurt".into()) {
+                owner.call("hurt".into(), &[Effects::new(cast.on_caster).to_variant()]);
+            }
+            if let Some(destination) = cast.teleport {
+                owner.set_global_position(destination);
+            }
+            if let Some(mut parent) = owner.get_parent() {
+                for spawn in cast.spawns {
+                    match try_load::<PackedScene>(&spawn.scene) {
+                        Ok(scene) => {
+                            let mut node = scene.instantiate_as::<Node2D>();
+                            node.set_global_position(spawn.position);
+                            parent.add_child(node.upcast());
+                        }
+                        Err(err) => tracing::warn!("cannot spawn {}: {}", spawn.scene, err),
+                    }
+                }
+            }
+        }
+        self.base_mut().emit_signal("cast_finished".into(), &[cast.skill.to_variant()]);
+    }
+}
+
+#[godot_api]
+impl INode2D for SkillCaster {
+    fn init(base: Base<Node2D>) -> Self {
+        SkillCaster {
+            skills: PackedStringArray::new(),
+            book: SkillBook::default(),
+            casting: None,
+            base,
+        }
+    }
+
+    fn ready(&mut self) {
+        for name in self.skills.to_vec().iter().map(GString::to_string) {
+            match catalog().skill(&name) {
+                Some(skill) => self.book.learn(skill.clone()),
+                None => tracing::warn!("unknown skill {}", name),
+            }
+        }
+    }
+
+    fn process(&mut self, delta: f64) {
+        self.book.tick(delta);
+        if let Some((_, remaining)) = &mut self.casting {
+            *remaining -= delta;
+            if *remaining <= 0.0 {
+                let (cast, _) = self.casting.take().expect("cast in progress");
+                self.finish(cast);
+            }
+        }
+    }
+}
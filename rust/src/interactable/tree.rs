@@ -22,19 +22,33 @@ pub struct PineTree {
 
 #[godot_api]
 impl PineTree {
+    #[func]
+    fn is_stump(&self) -> bool {
+        matches!(self.state, State::Stump)
+    }
+
     #[func]
     fn hurt(&mut self, effects: Gd<Effects>) {
         tracing::debug!("hurt: {:?}", effects);
 
         let Some(_has_damage) = effects.bind().effects.iter().find(|&eff| {
             match eff {
-                Effect::Damage(_) => true,
+                Effect::Damage(_) | Effect::Attack(_) => true,
                 _ => false
             }
         }) else {
             return
         };
+        if self.is_stump() {
+            return
+        }
         self.hp -= 1;
+        // whoever chops gets a log out of it
+        if let Some(mut source) = effects.bind().source.clone() {
+            if source.has_method("add_item".into()) {
+                source.call("add_item".into(), &["Wood".to_variant(), 1.to_variant()]);
+            }
+        }
 
         if self.hp <= 0 {
             self.state = State::Stump;
@@ -8,7 +8,10 @@
  */
 
 use log::Level;
+use godot::engine::file_access::ModeFlags;
+use godot::engine::FileAccess;
 use godot::prelude::*;
+use crate::dnd::catalog::{Catalog, CatalogError};
 use crate::logger::{deinit_tracing, init_tracing, init_with_level};
 
 mod hud;
@@ -18,6 +21,7 @@ mod player;
 mod characters;
 mod logger;
 mod bullet;
+mod encounter;
 pub mod pool;
 pub mod dnd;
 pub mod ai;
@@ -37,7 +41,7 @@ unsafe impl ExtensionLibrary for DodgeTheCreeps {
                 let _ = init_with_level(Level::Trace);
             }
             InitLevel::Servers => {}
-            InitLevel::Scene => {}
+            InitLevel::Scene => load_dnd_catalog(),
             InitLevel::Editor => {}
         }
     }
@@ -49,3 +53,27 @@ unsafe impl ExtensionLibrary for DodgeTheCreeps {
         }
     }
 }
+
+/// Reads a text file through Godot, so that `res://` paths work in exported games as well.
+pub(crate) fn read_resource(path: &str) -> std::io::Result<String> {
+    match FileAccess::open(path.into(), ModeFlags::READ) {
+        Some(file) => Ok(file.get_as_text().to_string()),
+        None => Err(std::io::Error::new(
+            std::io::ErrorKind::NotFound,
+            format!("cannot open {}: {:?}", path, FileAccess::get_open_error()),
+        )),
+    }
+}
+
+fn load_dnd_catalog() {
+    let path = "res://data/catalog.ron";
+    let catalog = read_resource(path).map_err(CatalogError::Io).and_then(|src| Catalog::from_ron(&src));
+    match catalog {
+        Ok(catalog) => {
+            if dnd::catalog::install(catalog).is_err() {
+                tracing::warn!("dnd catalog was already in use, {} ignored", path);
+            }
+        }
+        Err(err) => tracing::warn!("using built-in dnd catalog, {}", err),
+    }
+}
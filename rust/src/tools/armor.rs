@@ -0,0 +1,143 @@
+use serde::{Deserialize, Serialize};
+use crate::dnd::ability::Ability;
+use crate::dnd::check::CheckKind;
+use crate::dnd::dice::RollMode;
+use crate::dnd::enums::{AbilityType, ArmorType, Proficiencies, ShieldType, SkillType};
+
+/// Speed lost when wearing armor without meeting its strength requirement, in feet.
+pub const ARMOR_SPEED_PENALTY: u8 = 10;
+
+/// Armor as defined in the catalog, see [`crate::dnd::catalog::Catalog::armor`].
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ArmorData {
+    pub name: String,
+    pub category: ArmorType,
+    pub base_armor_class: u8,
+    /// Most dexterity modifier that still counts, `None` for no limit.
+    pub max_dexterity_bonus: Option<u8>,
+    #[serde(default)]
+    pub strength_requirement: u8,
+    #[serde(default)]
+    pub stealth_disadvantage: bool,
+}
+
+impl ArmorData {
+    pub fn armor_class(&self, ability: &Ability) -> i32 {
+        let dex = ability.modifier(AbilityType::Dexterity);
+        let dex = match self.max_dexterity_bonus {
+            Some(cap) => dex.min(cap as i32),
+            None => dex,
+        };
+        self.base_armor_class as i32 + dex
+    }
+}
+
+/// A shield as defined in the catalog, see [`crate::dnd::catalog::Catalog::shield`].
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ShieldData {
+    pub shield: ShieldType,
+    pub armor_class_bonus: u8,
+    #[serde(default)]
+    pub strength_requirement: u8,
+    #[serde(default)]
+    pub stealth_disadvantage: bool,
+}
+
+/// What wearing the current gear costs a character.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct ArmorPenalty {
+    /// Speed lost to armor that is too heavy for the wearer.
+    pub speed: u8,
+    pub stealth_disadvantage: bool,
+    /// Gear is worn without proficiency: strength and dexterity rolls, including attacks, are made
+    /// with disadvantage.
+    pub untrained: bool,
+}
+
+impl ArmorPenalty {
+    pub fn check_mode(&self, kind: CheckKind) -> RollMode {
+        let stealth = self.stealth_disadvantage && kind == CheckKind::Skill(SkillType::Stealth);
+        let untrained = self.untrained
+            && matches!(kind.ability(), AbilityType::Strength | AbilityType::Dexterity);
+        if stealth || untrained { RollMode::Disadvantage } else { RollMode::Normal }
+    }
+
+    /// Weapon attacks always use strength or dexterity.
+    pub fn attack_mode(&self) -> RollMode {
+        if self.untrained { RollMode::Disadvantage } else { RollMode::Normal }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Default, Serialize, Deserialize)]
+pub struct Equipment {
+    pub armor: Option<ArmorData>,
+    pub shield: Option<ShieldData>,
+}
+
+impl Equipment {
+    /// Armor class from the worn gear, `unarmored` is used when no armor is worn.
+    pub fn armor_class(&self, unarmored: u8, ability: &Ability) -> u8 {
+        let base = match &self.armor {
+            Some(armor) => armor.armor_class(ability),
+            None => unarmored as i32,
+        };
+        let shield = self.shield.as_ref().map_or(0, |shield| shield.armor_class_bonus as i32);
+        (base + shield).max(0) as u8
+    }
+
+    pub fn penalty(&self, ability: &Ability, proficiencies: &Proficiencies) -> ArmorPenalty {
+        let armor = self.armor.as_ref();
+        let shield = self.shield.as_ref();
+        let requirement = armor.map_or(0, |a| a.strength_requirement)
+            .max(shield.map_or(0, |s| s.strength_requirement));
+        ArmorPenalty {
+            speed: if ability.strength < requirement { ARMOR_SPEED_PENALTY } else { 0 },
+            stealth_disadvantage: armor.is_some_and(|a| a.stealth_disadvantage)
+                || shield.is_some_and(|s| s.stealth_disadvantage),
+            untrained: armor.is_some_and(|a| !proficiencies.has_armor(a.category))
+                || shield.is_some_and(|s| !proficiencies.has_shield(s.shield)),
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::dnd::catalog::Catalog;
+    use super::*;
+
+    #[test]
+    fn test_armor_class() {
+        let catalog = Catalog::builtin();
+        let ability = Ability { strength: 10, dexterity: 18, ..Default::default() };
+        let mut equipment = Equipment::default();
+        assert_eq!(equipment.armor_class(14, &ability), 14);
+
+        equipment.armor = catalog.armor("Leather").cloned();
+        assert_eq!(equipment.armor_class(14, &ability), 15);
+        equipment.armor = catalog.armor("Half Plate").cloned();
+        assert_eq!(equipment.armor_class(14, &ability), 17);
+        equipment.armor = catalog.armor("Plate").cloned();
+        equipment.shield = Some(catalog.shield(ShieldType::Kite).clone());
+        assert_eq!(equipment.armor_class(14, &ability), 18 + catalog.shield(ShieldType::Kite).armor_class_bonus);
+    }
+
+    #[test]
+    fn test_penalty() {
+        let catalog = Catalog::builtin();
+        let ability = Ability { strength: 10, dexterity: 10, ..Default::default() };
+        let fighter = catalog.class(crate::dnd::enums::ClassType::Fighter).proficiencies();
+        let wizard = catalog.class(crate::dnd::enums::ClassType::Wizard).proficiencies();
+        let equipment = Equipment { armor: catalog.armor("Plate").cloned(), shield: None };
+
+        let penalty = equipment.penalty(&ability, &fighter);
+        assert_eq!(penalty, ArmorPenalty { speed: ARMOR_SPEED_PENALTY, stealth_disadvantage: true, untrained: false });
+        assert_eq!(penalty.check_mode(CheckKind::Skill(SkillType::Stealth)), RollMode::Disadvantage);
+        assert_eq!(penalty.check_mode(CheckKind::Skill(SkillType::Athletics)), RollMode::Normal);
+
+        let penalty = equipment.penalty(&ability, &wizard);
+        assert!(penalty.untrained);
+        assert_eq!(penalty.check_mode(CheckKind::SavingThrow(AbilityType::Dexterity)), RollMode::Disadvantage);
+        assert_eq!(penalty.check_mode(CheckKind::SavingThrow(AbilityType::Wisdom)), RollMode::Normal);
+        assert_eq!(penalty.attack_mode(), RollMode::Disadvantage);
+    }
+}
@@ -0,0 +1,420 @@
+use std::fmt::{Display, Formatter};
+use std::str::FromStr;
+use serde::{Deserialize, Serialize};
+use crate::dnd::check::CheckKind;
+use crate::dnd::dice::RollMode;
+use crate::dnd::enums::{AbilityType, ShieldType};
+
+/// Pounds a character can carry per point of strength.
+pub const CARRYING_CAPACITY_PER_STRENGTH: f32 = 15.0;
+/// Pounds per point of strength above which a character is encumbered.
+pub const ENCUMBERED_PER_STRENGTH: f32 = 5.0;
+/// Pounds per point of strength above which a character is heavily encumbered.
+pub const HEAVILY_ENCUMBERED_PER_STRENGTH: f32 = 10.0;
+pub const ENCUMBERED_SPEED_PENALTY: u8 = 10;
+pub const HEAVILY_ENCUMBERED_SPEED_PENALTY: u8 = 20;
+/// Speed left to a character carrying more than it can.
+pub const OVER_CAPACITY_SPEED: u8 = 5;
+pub const ACCESSORY_SLOTS: u8 = 3;
+
+/// What an item is good for, which decides the slots it can be equipped in.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum ItemKind {
+    /// Named after a weapon in the catalog.
+    Weapon,
+    /// Named after armor in the catalog.
+    Armor,
+    Shield(ShieldType),
+    /// Rings, amulets, cloaks and the like.
+    Accessory,
+    Consumable,
+    Gear,
+}
+
+impl ItemKind {
+    pub fn fits(&self, slot: EquipmentSlot) -> bool {
+        match slot {
+            // a torch or a lantern is held like a weapon
+            EquipmentSlot::MainHand => matches!(self, ItemKind::Weapon | ItemKind::Gear),
+            EquipmentSlot::OffHand => matches!(self, ItemKind::Weapon | ItemKind::Shield(_) | ItemKind::Gear),
+            EquipmentSlot::Armor => *self == ItemKind::Armor,
+            EquipmentSlot::Accessory(_) => *self == ItemKind::Accessory,
+        }
+    }
+}
+
+impl Display for ItemKind {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ItemKind::Weapon => write!(f, "weapon"),
+            ItemKind::Armor => write!(f, "armor"),
+            ItemKind::Shield(_) => write!(f, "shield"),
+            ItemKind::Accessory => write!(f, "accessory"),
+            ItemKind::Consumable => write!(f, "consumable"),
+            ItemKind::Gear => write!(f, "gear"),
+        }
+    }
+}
+
+fn single() -> u32 {
+    1
+}
+
+/// An item as defined in the catalog, see [`crate::dnd::catalog::Catalog::item`].
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ItemData {
+    pub name: String,
+    pub kind: ItemKind,
+    /// Pounds per item.
+    pub weight: f32,
+    /// How many of the item fit in one stack.
+    #[serde(default = "single")]
+    pub max_stack: u32,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ItemStack {
+    pub item: ItemData,
+    pub count: u32,
+}
+
+impl ItemStack {
+    pub fn weight(&self) -> f32 {
+        self.item.weight * self.count as f32
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+pub enum EquipmentSlot {
+    MainHand,
+    OffHand,
+    Armor,
+    /// One of the [`ACCESSORY_SLOTS`], counting from 0.
+    Accessory(u8),
+}
+
+impl EquipmentSlot {
+    pub fn all() -> impl Iterator<Item = EquipmentSlot> {
+        [EquipmentSlot::MainHand, EquipmentSlot::OffHand, EquipmentSlot::Armor].into_iter()
+            .chain((0..ACCESSORY_SLOTS).map(EquipmentSlot::Accessory))
+    }
+}
+
+impl Display for EquipmentSlot {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            EquipmentSlot::MainHand => write!(f, "main hand"),
+            EquipmentSlot::OffHand => write!(f, "off hand"),
+            EquipmentSlot::Armor => write!(f, "armor"),
+            EquipmentSlot::Accessory(index) => write!(f, "accessory {}", index + 1),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct UnknownSlot(pub String);
+
+impl Display for UnknownSlot {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "unknown equipment slot {:?}", self.0)
+    }
+}
+
+impl std::error::Error for UnknownSlot {}
+
+impl FromStr for EquipmentSlot {
+    type Err = UnknownSlot;
+
+    /// Parses the [`Display`] form, ignoring case and taking underscores for spaces.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let name = s.replace('_', " ");
+        EquipmentSlot::all()
+            .find(|slot| slot.to_string().eq_ignore_ascii_case(&name))
+            .ok_or_else(|| UnknownSlot(s.to_string()))
+    }
+}
+
+/// How much a character's load slows it down.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Default)]
+pub enum Encumbrance {
+    #[default]
+    Unencumbered,
+    Encumbered,
+    /// Slower still, and strength, dexterity and constitution rolls are made with disadvantage.
+    HeavilyEncumbered,
+    /// Barely moves at all.
+    OverCapacity,
+}
+
+impl Encumbrance {
+    pub fn of(weight: f32, strength: u8) -> Self {
+        let strength = strength as f32;
+        if weight > strength * CARRYING_CAPACITY_PER_STRENGTH {
+            Encumbrance::OverCapacity
+        } else if weight > strength * HEAVILY_ENCUMBERED_PER_STRENGTH {
+            Encumbrance::HeavilyEncumbered
+        } else if weight > strength * ENCUMBERED_PER_STRENGTH {
+            Encumbrance::Encumbered
+        } else {
+            Encumbrance::Unencumbered
+        }
+    }
+
+    /// What is left of `speed`, in feet, under this load.
+    pub fn speed(&self, speed: u8) -> u8 {
+        match self {
+            Encumbrance::Unencumbered => speed,
+            Encumbrance::Encumbered => speed.saturating_sub(ENCUMBERED_SPEED_PENALTY),
+            Encumbrance::HeavilyEncumbered => speed.saturating_sub(HEAVILY_ENCUMBERED_SPEED_PENALTY),
+            Encumbrance::OverCapacity => speed.min(OVER_CAPACITY_SPEED),
+        }
+    }
+
+    fn is_straining(&self) -> bool {
+        *self >= Encumbrance::HeavilyEncumbered
+    }
+
+    pub fn check_mode(&self, kind: CheckKind) -> RollMode {
+        let physical = matches!(kind.ability(), AbilityType::Strength | AbilityType::Dexterity | AbilityType::Constitution);
+        if self.is_straining() && physical { RollMode::Disadvantage } else { RollMode::Normal }
+    }
+
+    pub fn attack_mode(&self) -> RollMode {
+        if self.is_straining() { RollMode::Disadvantage } else { RollMode::Normal }
+    }
+}
+
+impl Display for Encumbrance {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Encumbrance::Unencumbered => write!(f, "unencumbered"),
+            Encumbrance::Encumbered => write!(f, "encumbered"),
+            Encumbrance::HeavilyEncumbered => write!(f, "heavily encumbered"),
+            Encumbrance::OverCapacity => write!(f, "over capacity"),
+        }
+    }
+}
+
+/// Pounds a character with `strength` can carry before it can barely move.
+pub fn carrying_capacity(strength: u8) -> f32 {
+    strength as f32 * CARRYING_CAPACITY_PER_STRENGTH
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum InventoryError {
+    NotCarried(String),
+    NotEnough { item: String, requested: u32, carried: u32 },
+    WrongSlot { item: String, slot: EquipmentSlot },
+    NoSuchSlot(EquipmentSlot),
+    SlotEmpty(EquipmentSlot),
+}
+
+impl Display for InventoryError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            InventoryError::NotCarried(item) => write!(f, "{} is not carried", item),
+            InventoryError::NotEnough { item, requested, carried } =>
+                write!(f, "cannot take {} {}, only {} carried", requested, item, carried),
+            InventoryError::WrongSlot { item, slot } => write!(f, "{} cannot be equipped in the {} slot", item, slot),
+            InventoryError::NoSuchSlot(slot) => write!(f, "there is no {} slot", slot),
+            InventoryError::SlotEmpty(slot) => write!(f, "nothing is equipped in the {} slot", slot),
+        }
+    }
+}
+
+impl std::error::Error for InventoryError {}
+
+/// The items a character carries in its pack, and those it has equipped. Both count toward its
+/// load.
+#[derive(Debug, Clone, PartialEq, Default, Serialize, Deserialize)]
+pub struct Inventory {
+    stacks: Vec<ItemStack>,
+    equipped: Vec<(EquipmentSlot, ItemData)>,
+}
+
+impl Inventory {
+    pub fn stacks(&self) -> &[ItemStack] {
+        &self.stacks
+    }
+
+    pub fn equipped(&self) -> impl Iterator<Item = (EquipmentSlot, &ItemData)> {
+        self.equipped.iter().map(|(slot, item)| (*slot, item))
+    }
+
+    pub fn equipped_in(&self, slot: EquipmentSlot) -> Option<&ItemData> {
+        self.equipped.iter().find(|(s, _)| *s == slot).map(|(_, item)| item)
+    }
+
+    /// How many of the item are in the pack, not counting an equipped one.
+    pub fn count(&self, name: &str) -> u32 {
+        self.stacks.iter()
+            .filter(|stack| stack.item.name.eq_ignore_ascii_case(name))
+            .map(|stack| stack.count)
+            .sum()
+    }
+
+    /// Pounds carried, equipped items included.
+    pub fn weight(&self) -> f32 {
+        let pack: f32 = self.stacks.iter().map(ItemStack::weight).sum();
+        pack + self.equipped.iter().map(|(_, item)| item.weight).sum::<f32>()
+    }
+
+    pub fn encumbrance(&self, strength: u8) -> Encumbrance {
+        Encumbrance::of(self.weight(), strength)
+    }
+
+    /// Puts `count` of `item` in the pack, topping up the stacks already there before starting new
+    /// ones.
+    pub fn add(&mut self, item: &ItemData, count: u32) {
+        let max_stack = item.max_stack.max(1);
+        let mut left = count;
+        for stack in self.stacks.iter_mut().filter(|stack| stack.item.name.eq_ignore_ascii_case(&item.name)) {
+            let added = left.min(max_stack.saturating_sub(stack.count));
+            stack.count += added;
+            left -= added;
+        }
+        while left > 0 {
+            let count = left.min(max_stack);
+            self.stacks.push(ItemStack { item: item.clone(), count });
+            left -= count;
+        }
+    }
+
+    /// Takes `count` of the item out of the pack, emptying the last stacks first.
+    pub fn remove(&mut self, name: &str, count: u32) -> Result<ItemData, InventoryError> {
+        let carried = self.count(name);
+        let Some(item) = self.find(name).cloned() else {
+            return Err(InventoryError::NotCarried(name.to_string()))
+        };
+        if count > carried {
+            return Err(InventoryError::NotEnough { item: item.name, requested: count, carried })
+        }
+        let mut left = count;
+        for stack in self.stacks.iter_mut().rev().filter(|stack| stack.item.name.eq_ignore_ascii_case(name)) {
+            let taken = left.min(stack.count);
+            stack.count -= taken;
+            left -= taken;
+        }
+        self.stacks.retain(|stack| stack.count > 0);
+        Ok(item)
+    }
+
+    /// Equips one of the item from the pack and puts back whatever was in `slot`, which is also
+    /// returned.
+    pub fn equip(&mut self, slot: EquipmentSlot, name: &str) -> Result<Option<ItemData>, InventoryError> {
+        if let EquipmentSlot::Accessory(index) = slot {
+            if index >= ACCESSORY_SLOTS {
+                return Err(InventoryError::NoSuchSlot(slot))
+            }
+        }
+        let Some(item) = self.find(name) else {
+            return Err(InventoryError::NotCarried(name.to_string()))
+        };
+        if !item.kind.fits(slot) {
+            return Err(InventoryError::WrongSlot { item: item.name.clone(), slot })
+        }
+        let item = self.remove(name, 1)?;
+        let previous = self.take(slot);
+        if let Some(previous) = &previous {
+            self.add(previous, 1);
+        }
+        self.equipped.push((slot, item));
+        self.equipped.sort_by_key(|(slot, _)| *slot);
+        Ok(previous)
+    }
+
+    /// Puts the item in `slot` back in the pack.
+    pub fn unequip(&mut self, slot: EquipmentSlot) -> Result<ItemData, InventoryError> {
+        let item = self.take(slot).ok_or(InventoryError::SlotEmpty(slot))?;
+        self.add(&item, 1);
+        Ok(item)
+    }
+
+    fn find(&self, name: &str) -> Option<&ItemData> {
+        self.stacks.iter()
+            .map(|stack| &stack.item)
+            .find(|item| item.name.eq_ignore_ascii_case(name))
+    }
+
+    fn take(&mut self, slot: EquipmentSlot) -> Option<ItemData> {
+        let index = self.equipped.iter().position(|(s, _)| *s == slot)?;
+        Some(self.equipped.remove(index).1)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::dnd::catalog::Catalog;
+    use super::*;
+
+    #[test]
+    fn test_stacks() {
+        let catalog = Catalog::builtin();
+        let torch = catalog.item("torch").unwrap();
+        let mut inventory = Inventory::default();
+        inventory.add(torch, 7);
+        inventory.add(torch, 7);
+        assert_eq!(inventory.stacks().iter().map(|stack| stack.count).collect::<Vec<_>>(), vec![10, 4]);
+        assert_eq!(inventory.count("Torch"), 14);
+        assert_eq!(inventory.weight(), 14.0);
+
+        inventory.remove("Torch", 5).unwrap();
+        assert_eq!(inventory.stacks().len(), 1);
+        assert_eq!(inventory.count("Torch"), 9);
+        assert_eq!(
+            inventory.remove("Torch", 10),
+            Err(InventoryError::NotEnough { item: "Torch".to_string(), requested: 10, carried: 9 })
+        );
+        assert_eq!(inventory.remove("Rope", 1), Err(InventoryError::NotCarried("Rope".to_string())));
+    }
+
+    #[test]
+    fn test_equip() {
+        let catalog = Catalog::builtin();
+        let mut inventory = Inventory::default();
+        for name in ["Longsword", "Shortsword", "Scale Mail", "Kite Shield", "Signet Ring"] {
+            inventory.add(catalog.item(name).unwrap(), 1);
+        }
+        let weight = inventory.weight();
+
+        assert_eq!(inventory.equip(EquipmentSlot::MainHand, "longsword"), Ok(None));
+        assert_eq!(inventory.count("Longsword"), 0);
+        assert_eq!(inventory.equip(EquipmentSlot::MainHand, "Shortsword").unwrap().unwrap().name, "Longsword");
+        assert_eq!(inventory.count("Longsword"), 1);
+        assert!(matches!(inventory.equip(EquipmentSlot::Armor, "Kite Shield"), Err(InventoryError::WrongSlot { .. })));
+        inventory.equip(EquipmentSlot::OffHand, "Kite Shield").unwrap();
+        inventory.equip(EquipmentSlot::Armor, "Scale Mail").unwrap();
+        assert_eq!(
+            inventory.equip(EquipmentSlot::Accessory(ACCESSORY_SLOTS), "Signet Ring"),
+            Err(InventoryError::NoSuchSlot(EquipmentSlot::Accessory(ACCESSORY_SLOTS)))
+        );
+        inventory.equip(EquipmentSlot::Accessory(1), "Signet Ring").unwrap();
+        assert_eq!(inventory.equipped().count(), 4);
+        // equipping moves items around without changing the load
+        assert_eq!(inventory.weight(), weight);
+
+        assert_eq!(inventory.unequip(EquipmentSlot::OffHand).unwrap().name, "Kite Shield");
+        assert_eq!(inventory.unequip(EquipmentSlot::OffHand), Err(InventoryError::SlotEmpty(EquipmentSlot::OffHand)));
+        assert_eq!(inventory.equipped_in(EquipmentSlot::MainHand).unwrap().name, "Shortsword");
+        assert_eq!("main_hand".parse(), Ok(EquipmentSlot::MainHand));
+        assert_eq!("Accessory 3".parse(), Ok(EquipmentSlot::Accessory(2)));
+        assert!("accessory 4".parse::<EquipmentSlot>().is_err());
+    }
+
+    #[test]
+    fn test_encumbrance() {
+        assert_eq!(carrying_capacity(10), 150.0);
+        assert_eq!(Encumbrance::of(50.0, 10), Encumbrance::Unencumbered);
+        assert_eq!(Encumbrance::of(51.0, 10), Encumbrance::Encumbered);
+        assert_eq!(Encumbrance::of(101.0, 10), Encumbrance::HeavilyEncumbered);
+        assert_eq!(Encumbrance::of(151.0, 10), Encumbrance::OverCapacity);
+
+        assert_eq!(Encumbrance::Encumbered.speed(30), 20);
+        assert_eq!(Encumbrance::HeavilyEncumbered.speed(25), 5);
+        assert_eq!(Encumbrance::OverCapacity.speed(30), 5);
+        let save = CheckKind::SavingThrow(AbilityType::Constitution);
+        assert_eq!(Encumbrance::Encumbered.check_mode(save), RollMode::Normal);
+        assert_eq!(Encumbrance::HeavilyEncumbered.check_mode(save), RollMode::Disadvantage);
+        assert_eq!(Encumbrance::HeavilyEncumbered.check_mode(CheckKind::Ability(AbilityType::Wisdom)), RollMode::Normal);
+    }
+}
@@ -1 +1,3 @@
-pub mod weapon;
\ No newline at end of file
+pub mod weapon;
+pub mod armor;
+pub mod inventory;
\ No newline at end of file
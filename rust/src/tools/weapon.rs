@@ -1,9 +1,126 @@
-use crate::dnd::enums::WeaponType;
+use serde::{Deserialize, Serialize};
+use crate::dnd::dice::DiceExpr;
+use crate::dnd::enums::{AbilityType, DamageType, WeaponType};
+
+/// Reach of a melee weapon without the reach property, in feet.
+pub const MELEE_REACH: u32 = 5;
+/// Reach of a melee weapon with the reach property, in feet.
+pub const LONG_REACH: u32 = 10;
+
+/// Normal and long range in feet. Attacks beyond `normal` have disadvantage, attacks beyond
+/// `long` are impossible.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub struct RangeBand {
+    pub normal: u32,
+    pub long: u32,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum WeaponProperty {
+    /// Attacks use the better of strength and dexterity.
+    Finesse,
+    Light,
+    Heavy,
+    TwoHanded,
+    Reach,
+    Loading,
+    /// Damage when wielded with two hands.
+    Versatile(DiceExpr),
+    Thrown(RangeBand),
+    Ammunition(RangeBand),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub enum Grip {
+    #[default]
+    OneHanded,
+    TwoHanded,
+}
 
 pub trait Weapon {
     fn r#type(&self) -> WeaponType;
-    fn damage(&self) -> u32;
+    fn damage(&self) -> DiceExpr;
     fn range(&self) -> u32;
+
+    /// Melee weapons hit with strength, ranged weapons aim with dexterity.
+    fn ability(&self) -> AbilityType {
+        match self.r#type() {
+            WeaponType::SimpleMelee | WeaponType::MartialMelee => AbilityType::Strength,
+            WeaponType::SimpleRanged | WeaponType::MartialRanged => AbilityType::Dexterity,
+        }
+    }
+
+    fn properties(&self) -> &[WeaponProperty] {
+        &[]
+    }
+
+    fn is_melee(&self) -> bool {
+        matches!(self.r#type(), WeaponType::SimpleMelee | WeaponType::MartialMelee)
+    }
+
+    fn is_finesse(&self) -> bool {
+        self.properties().contains(&WeaponProperty::Finesse)
+    }
+
+    fn is_two_handed(&self) -> bool {
+        self.properties().contains(&WeaponProperty::TwoHanded)
+    }
+
+    fn reach(&self) -> u32 {
+        if self.properties().contains(&WeaponProperty::Reach) { LONG_REACH } else { MELEE_REACH }
+    }
+
+    /// Range band when thrown or shot, `None` for weapons that can only be swung.
+    fn range_band(&self) -> Option<RangeBand> {
+        self.properties().iter().find_map(|property| match property {
+            WeaponProperty::Thrown(band) | WeaponProperty::Ammunition(band) => Some(*band),
+            _ => None,
+        })
+    }
+
+    /// Damage dice for the given grip, versatile weapons hit harder with both hands.
+    fn damage_with(&self, grip: Grip) -> DiceExpr {
+        let versatile = self.properties().iter().find_map(|property| match property {
+            WeaponProperty::Versatile(damage) => Some(damage),
+            _ => None,
+        });
+        match (grip, versatile) {
+            (Grip::TwoHanded, Some(damage)) => damage.clone(),
+            _ => self.damage(),
+        }
+    }
+}
+
+/// A weapon as defined in the catalog, see [`crate::dnd::catalog::Catalog::weapon`].
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct WeaponData {
+    pub name: String,
+    pub category: WeaponType,
+    pub damage: DiceExpr,
+    pub damage_type: DamageType,
+    #[serde(default)]
+    pub properties: Vec<WeaponProperty>,
+}
+
+impl Weapon for WeaponData {
+    fn r#type(&self) -> WeaponType {
+        self.category
+    }
+
+    fn damage(&self) -> DiceExpr {
+        self.damage.clone()
+    }
+
+    fn range(&self) -> u32 {
+        match self.range_band() {
+            Some(band) if !self.is_melee() => band.normal,
+            _ => self.reach(),
+        }
+    }
+
+    fn properties(&self) -> &[WeaponProperty] {
+        &self.properties
+    }
 }
 
 pub trait SimpleMeleeWeapon: Weapon {